@@ -0,0 +1,104 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::io::prelude::*;
+use std::rc::Rc;
+
+use loxrust::transcript::Transcript;
+
+// Console is where the REPL and scripts print their output and diagnostics,
+// i.e. stdout and stderr. A console can also record everything printed to a
+// Transcript.
+#[derive(Clone, Default)]
+pub struct Console {
+    // transcript is the optional Transcript the console records to.
+    transcript: Option<Rc<RefCell<Transcript>>>,
+
+    // quiet is true if the console only records, without printing.
+    quiet: bool,
+
+    // output_len is the number of bytes of program output so far.
+    output_len: Rc<Cell<usize>>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console::default()
+    }
+
+    // with_transcript makes the console record to a new transcript, in
+    // addition to printing.
+    pub fn with_transcript(mut self) -> Self {
+        self.transcript = Some(Rc::new(RefCell::new(Transcript::new())));
+        self
+    }
+
+    // with_quiet makes the console stop printing, only recording.
+    #[cfg(test)]
+    pub fn with_quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    // transcript returns what the console has recorded so far, or None if
+    // the console is not recording.
+    pub fn transcript(&self) -> Option<Transcript> {
+        self.transcript.as_ref().map(|t| t.borrow().clone())
+    }
+
+    // input records line as entered by the user.
+    pub fn input(&self, line: &str) {
+        if let Some(transcript) = &self.transcript {
+            transcript.borrow_mut().push_input(line);
+        }
+    }
+
+    // output returns a Write for the output of a program.
+    pub fn output(&self) -> ConsoleOutput {
+        ConsoleOutput {
+            console: self.clone(),
+        }
+    }
+
+    // output_len returns the number of bytes written to the output of the
+    // console so far.
+    pub fn output_len(&self) -> usize {
+        self.output_len.get()
+    }
+
+    // diagnostic prints an error or warning.
+    pub fn diagnostic(&self, msg: &str) {
+        if !self.quiet {
+            eprintln!("{}", msg);
+        }
+        if let Some(transcript) = &self.transcript {
+            transcript.borrow_mut().push_diagnostic(msg);
+        }
+    }
+}
+
+// ConsoleOutput is a Write printing to stdout of a Console.
+pub struct ConsoleOutput {
+    console: Console,
+}
+
+impl Write for ConsoleOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.console.quiet {
+            io::stdout().write_all(buf)?;
+        }
+        let output_len = &self.console.output_len;
+        output_len.set(output_len.get() + buf.len());
+        if let Some(transcript) = &self.console.transcript {
+            let text = String::from_utf8_lossy(buf);
+            transcript.borrow_mut().push_output(&text);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.console.quiet {
+            io::stdout().flush()?;
+        }
+        Ok(())
+    }
+}
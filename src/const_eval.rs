@@ -0,0 +1,255 @@
+use super::expr::*;
+use super::token::TokenType;
+use super::value::Value;
+
+// eval_const evaluates expr without an interpreter, returning its value if
+// expr is a side-effect-free constant expression, or None if the value
+// cannot be determined statically. The value is always nil, a boolean, a
+// number or a string.
+pub fn eval_const(expr: &Expr) -> Option<Value> {
+    expr.accept(&mut ConstEvaluator {})
+}
+
+// ConstEvaluator is a Visitor folding literal operands into a single value.
+struct ConstEvaluator {}
+
+impl Visitor for ConstEvaluator {
+    type Result = Option<Value>;
+
+    fn visit_assign_expr(&mut self, _expr: &AssignExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Option<Value> {
+        use TokenType::*;
+
+        let left = expr.left.accept(self)?;
+        let right = expr.right.accept(self)?;
+        let value = match (expr.operator.token_type, left, right) {
+            (Plus, Value::String(l), Value::String(r)) => Value::String(l.concat(&r)),
+            (EqualEqual, l, r) => Value::Bool(l == r),
+            (BangEqual, l, r) => Value::Bool(l != r),
+            (Comma, _, r) => r,
+            (op, Value::Number(l), Value::Number(r)) => match op {
+                Plus => Value::Number(l + r),
+                Minus => Value::Number(l - r),
+                Star => Value::Number(l * r),
+                Slash => Value::Number(l / r),
+                Percent => Value::Number(l % r),
+                StarStar => Value::Number(l.powf(r)),
+                Greater => Value::Bool(l > r),
+                GreaterEqual => Value::Bool(l >= r),
+                Less => Value::Bool(l < r),
+                LessEqual => Value::Bool(l <= r),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn visit_call_expr(&mut self, _expr: &CallExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_function_expr(&mut self, _expr: &FunctionExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_get_expr(&mut self, _expr: &GetExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Option<Value> {
+        expr.expression.accept(self)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Option<Value> {
+        Some(Value::from(expr.value.clone()))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Option<Value> {
+        let left = expr.left.accept(self)?;
+        match (expr.operator.token_type, left.is_truthy()) {
+            (TokenType::Or, true) | (TokenType::And, false) => Some(left),
+            _ => expr.right.accept(self),
+        }
+    }
+
+    fn visit_set_expr(&mut self, _expr: &SetExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_set_index_expr(&mut self, _expr: &SetIndexExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_index_expr(&mut self, _expr: &IndexExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_list_expr(&mut self, _expr: &ListExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_map_expr(&mut self, _expr: &MapExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_increment_expr(&mut self, _expr: &IncrementExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_super_expr(&mut self, _expr: &SuperExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_throw_expr(&mut self, _expr: &ThrowExpr) -> Option<Value> {
+        None
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Option<Value> {
+        let right = expr.expression.accept(self)?;
+        match (expr.operator.token_type, right) {
+            (TokenType::Minus, Value::Number(n)) => Some(Value::Number(-n)),
+            (TokenType::Bang, right) => Some(Value::Bool(!right.is_truthy())),
+            _ => None,
+        }
+    }
+
+    fn visit_variable_expr(&mut self, _expr: &VariableExpr) -> Option<Value> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{Position, Span};
+    use crate::token::{Lexeme, Literal, Token};
+
+    fn make_token(token_type: TokenType, lexeme: &str) -> Token {
+        let end = Position::new(1, 1 + lexeme.len() as u64, lexeme.len());
+        Token {
+            token_type,
+            lexeme: Lexeme::from(lexeme),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), end),
+            literal: None,
+            error: false,
+        }
+    }
+
+    fn number(n: f64) -> Expr {
+        Expr::make_literal(Literal::Number(n))
+    }
+
+    fn string(s: &str) -> Expr {
+        Expr::make_literal(Literal::String(s.into()))
+    }
+
+    #[test]
+    fn test_eval_const_arithmetic() {
+        // -(1 + 2) * 4 / 2
+        let ex = Expr::make_binary(
+            Expr::make_binary(
+                Expr::make_unary(
+                    make_token(TokenType::Minus, "-"),
+                    Expr::make_grouping(Expr::make_binary(
+                        number(1.0),
+                        make_token(TokenType::Plus, "+"),
+                        number(2.0),
+                    )),
+                ),
+                make_token(TokenType::Star, "*"),
+                number(4.0),
+            ),
+            make_token(TokenType::Slash, "/"),
+            number(2.0),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Number(-6.0)));
+    }
+
+    #[test]
+    fn test_eval_const_string_concatenation() {
+        let ex = Expr::make_binary(string("ab"), make_token(TokenType::Plus, "+"), string("c"));
+        assert_eq!(eval_const(&ex), Some(Value::from("abc")));
+    }
+
+    #[test]
+    fn test_eval_const_mismatched_operands() {
+        let ex = Expr::make_binary(string("a"), make_token(TokenType::Plus, "+"), number(1.0));
+        assert_eq!(eval_const(&ex), None);
+        let ex = Expr::make_unary(make_token(TokenType::Minus, "-"), string("a"));
+        assert_eq!(eval_const(&ex), None);
+    }
+
+    #[test]
+    fn test_eval_const_logical() {
+        let ex = Expr::make_logical(
+            Expr::make_literal(Literal::Nil),
+            make_token(TokenType::Or, "or"),
+            number(1.0),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Number(1.0)));
+        let ex = Expr::make_logical(
+            Expr::make_literal(Literal::Bool(false)),
+            make_token(TokenType::And, "and"),
+            number(1.0),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_eval_const_comparison() {
+        let ex = Expr::make_binary(number(1.0), make_token(TokenType::Less, "<"), number(2.0));
+        assert_eq!(eval_const(&ex), Some(Value::Bool(true)));
+        let ex = Expr::make_binary(
+            number(2.0),
+            make_token(TokenType::GreaterEqual, ">="),
+            number(3.0),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Bool(false)));
+        // Only numbers are ordered.
+        let ex = Expr::make_binary(string("a"), make_token(TokenType::Less, "<"), string("b"));
+        assert_eq!(eval_const(&ex), None);
+    }
+
+    #[test]
+    fn test_eval_const_equality() {
+        let ex = Expr::make_binary(
+            string("a"),
+            make_token(TokenType::EqualEqual, "=="),
+            string("a"),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Bool(true)));
+        let ex = Expr::make_binary(
+            Expr::make_literal(Literal::Nil),
+            make_token(TokenType::BangEqual, "!="),
+            number(0.0),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Bool(true)));
+        let ex = Expr::make_unary(
+            make_token(TokenType::Bang, "!"),
+            Expr::make_literal(Literal::Nil),
+        );
+        assert_eq!(eval_const(&ex), Some(Value::Bool(true)));
+        let ex = Expr::make_unary(make_token(TokenType::Bang, "!"), number(0.0));
+        assert_eq!(eval_const(&ex), Some(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_eval_const_unsupported_operator() {
+        let ex = Expr::make_binary(number(1.0), make_token(TokenType::Star, "*"), string("a"));
+        assert_eq!(eval_const(&ex), None);
+        let ex = Expr::make_unary(
+            make_token(TokenType::Minus, "-"),
+            Expr::make_literal(Literal::Nil),
+        );
+        assert_eq!(eval_const(&ex), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::token::Literal;
+
+// Constants is the constant table of a function, or of the top level of a
+// program: the distinct literal values in it, as canonicalized by the
+// Resolver. Each literal expression records the index of its value in the
+// table, so the table can serve as the constant pool of a compiled function.
+//
+// The table does not make the AST smaller. Literal expressions keep their
+// value, which the interpreter evaluates directly, and the index is stored
+// in addition to it. The table is for consumers that want each distinct
+// constant once, such as a bytecode compiler.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Constants {
+    values: Vec<Literal>,
+    indices: HashMap<Key, usize>,
+}
+
+// Key identifies a literal value in a table. Numbers are compared by their
+// bits, so that e.g. 0 and -0 remain distinct constants.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+enum Key {
+    String(Rc<str>),
+    Number(u64),
+    Bool(bool),
+    Nil,
+}
+
+impl Constants {
+    pub fn new() -> Self {
+        Constants::default()
+    }
+
+    // add returns the index of value in the table, adding value to the table
+    // if it does not already hold an equal value.
+    pub fn add(&mut self, value: &Literal) -> usize {
+        let key = match *value {
+            Literal::String(ref s) => Key::String(Rc::clone(s)),
+            Literal::Number(n) => Key::Number(n.to_bits()),
+            Literal::Bool(b) => Key::Bool(b),
+            Literal::Nil => Key::Nil,
+        };
+        let values = &mut self.values;
+        *self.indices.entry(key).or_insert_with(|| {
+            values.push(value.clone());
+            values.len() - 1
+        })
+    }
+
+    // get returns the value at index, or None if index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&Literal> {
+        self.values.get(index)
+    }
+
+    // values returns the values of the table, in the order they were added.
+    pub fn values(&self) -> &[Literal] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut constants = Constants::new();
+        assert_eq!(constants.add(&Literal::Number(1.0)), 0);
+        assert_eq!(constants.add(&Literal::String("1".into())), 1);
+        assert_eq!(constants.add(&Literal::Number(1.0)), 0);
+        assert_eq!(constants.add(&Literal::String("1".into())), 1);
+        assert_eq!(constants.add(&Literal::Number(-0.0)), 2);
+        assert_eq!(constants.add(&Literal::Number(0.0)), 3);
+        assert_eq!(constants.add(&Literal::Nil), 4);
+        assert_eq!(constants.len(), 5);
+        assert_eq!(constants.get(1), Some(&Literal::String("1".into())));
+        assert_eq!(constants.get(5), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+use std::io::prelude::*;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+// Input is the result of reading a line of input.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    // Line is a line of input, without the line terminator.
+    Line(String),
+    // Interrupted means the user cancelled the line being entered (Ctrl-C).
+    Interrupted,
+    // Eof means the end of input was reached (e.g. Ctrl-D).
+    Eof,
+}
+
+// LineEditor reads the lines of input of the REPL.
+pub trait LineEditor {
+    // read_line prints prompt and reads the next line of input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input>;
+}
+
+// TerminalEditor is a LineEditor for interactive use, with line editing and
+// history.
+pub struct TerminalEditor {
+    editor: DefaultEditor,
+}
+
+impl TerminalEditor {
+    pub fn new() -> io::Result<Self> {
+        let editor = DefaultEditor::new().map_err(to_io_error)?;
+        Ok(TerminalEditor { editor })
+    }
+}
+
+impl LineEditor for TerminalEditor {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    self.editor
+                        .add_history_entry(line.as_str())
+                        .map_err(to_io_error)?;
+                }
+                Ok(Input::Line(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+            Err(ReadlineError::Eof) => Ok(Input::Eof),
+            Err(err) => Err(to_io_error(err)),
+        }
+    }
+}
+
+fn to_io_error(err: ReadlineError) -> io::Error {
+    match err {
+        ReadlineError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+// PlainEditor is a LineEditor reading lines from input without any editing,
+// for when input is not a terminal, e.g. when it is piped in or in tests.
+pub struct PlainEditor<R: BufRead> {
+    input: R,
+}
+
+impl<R: BufRead> PlainEditor<R> {
+    pub fn new(input: R) -> Self {
+        PlainEditor { input }
+    }
+}
+
+impl<R: BufRead> LineEditor for PlainEditor<R> {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut buf = String::new();
+        if self.input.read_line(&mut buf)? == 0 {
+            return Ok(Input::Eof);
+        }
+        let len = buf.trim_end_matches(&['\r', '\n'][..]).len();
+        buf.truncate(len);
+        Ok(Input::Line(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_editor() {
+        let mut editor = PlainEditor::new("a \r\n\nb".as_bytes());
+        assert_eq!(editor.read_line("").unwrap(), Input::Line("a ".to_owned()));
+        assert_eq!(editor.read_line("").unwrap(), Input::Line(String::new()));
+        assert_eq!(editor.read_line("").unwrap(), Input::Line("b".to_owned()));
+        assert_eq!(editor.read_line("").unwrap(), Input::Eof);
+    }
+}
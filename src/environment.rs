@@ -0,0 +1,360 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::error::RuntimeError;
+use super::messages;
+use super::token::Token;
+use super::value::Value;
+
+// Environment stores the values of the variables of a scope. Environments
+// are chained via enclosing to form the nested lexical scopes of a program.
+//
+// Environments are shared behind Rc<RefCell<_>>, so that a scope can be
+// kept alive and reused, e.g. the global scope between lines in the REPL.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    // constants is the names of values bound by define_constant, which
+    // cannot be assigned to.
+    constants: HashSet<String>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+    // globals is the outermost environment of the chain, or None if this
+    // is the outermost environment.
+    globals: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    // new creates a new global (outermost) environment.
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    // new_enclosed creates a new environment for a scope nested in
+    // enclosing.
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        let globals = enclosing
+            .borrow()
+            .globals
+            .clone()
+            .unwrap_or_else(|| Rc::clone(&enclosing));
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: Some(enclosing),
+            globals: Some(globals),
+        }
+    }
+
+    // globals returns the outermost environment of the chain environment is
+    // part of, which holds the globals of the program (or module) that
+    // created it.
+    pub fn globals(environment: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        match environment.borrow().globals {
+            Some(ref globals) => Rc::clone(globals),
+            None => Rc::clone(environment),
+        }
+    }
+
+    // define binds name to value in this environment, replacing any
+    // existing binding of name in this environment.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.constants.remove(name);
+        self.values.insert(name.to_owned(), value);
+    }
+
+    // define_constant is as define, but binds name as a constant, which is
+    // an error to assign to.
+    pub fn define_constant(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_owned(), value);
+        self.constants.insert(name.to_owned());
+    }
+
+    // enclosing returns the environment this environment is nested in, or
+    // None for the global environment.
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    // bindings returns the names and values bound in this environment, not
+    // including its enclosing environments, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    // get returns the value bound to name, looking in this environment
+    // followed by each of the enclosing environments.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.lookup(&name.lexeme)
+            .ok_or_else(|| Self::undefined_variable(name))
+    }
+
+    // lookup is as get, but takes the name as a string and returns None if
+    // the name is not bound. It is used for the implicit names bound by the
+    // interpreter, such as "this".
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.values.get(name) {
+            return Some(value.clone());
+        }
+        self.enclosing.as_ref()?.borrow().lookup(name)
+    }
+
+    // get_at returns the value bound to name in the environment distance
+    // steps up the chain of enclosing environments, as resolved by the
+    // Resolver.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        self.lookup_at(distance, &name.lexeme)
+            .ok_or_else(|| Self::undefined_variable(name))
+    }
+
+    // lookup_at is as get_at, but takes the name as a string and returns
+    // None if the name is not bound.
+    pub fn lookup_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+        self.enclosing
+            .as_ref()?
+            .borrow()
+            .lookup_at(distance - 1, name)
+    }
+
+    // assign binds name to value in the innermost environment where name
+    // has been defined. It is an error to assign to an undefined variable.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(name.lexeme.as_str()) {
+            if self.constants.contains(name.lexeme.as_str()) {
+                return Err(Self::constant_variable(name));
+            }
+            *slot = value;
+            return Ok(());
+        }
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(Self::undefined_variable(name)),
+        }
+    }
+
+    // assign_at binds name to value in the environment distance steps up
+    // the chain of enclosing environments, as resolved by the Resolver.
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance > 0 {
+            return match self.enclosing {
+                Some(ref enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+                None => Err(Self::undefined_variable(name)),
+            };
+        }
+        if self.constants.contains(name.lexeme.as_str()) {
+            return Err(Self::constant_variable(name));
+        }
+        match self.values.get_mut(name.lexeme.as_str()) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(Self::undefined_variable(name)),
+        }
+    }
+
+    fn undefined_variable(name: &Token) -> RuntimeError {
+        let msg = messages::UNDEFINED_VARIABLE.with(&[&name.lexeme]);
+        RuntimeError::new(name, msg)
+    }
+
+    fn constant_variable(name: &Token) -> RuntimeError {
+        let msg = messages::ASSIGN_TO_CONSTANT_VARIABLE.with(&[&name.lexeme]);
+        RuntimeError::new(name, msg)
+    }
+}
+
+// Dropping an environment drops the chain of enclosing environments that are
+// no longer shared iteratively rather than recursively, so that dropping a
+// long chain cannot overflow the stack.
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let mut enclosing = self.enclosing.take();
+        while let Some(env) = enclosing {
+            enclosing = match Rc::try_unwrap(env) {
+                Ok(env) => env.into_inner().enclosing.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{Position, Span};
+    use crate::token::{Lexeme, TokenType};
+
+    fn make_identifier(name: &str) -> Token {
+        let end = Position::new(1, 1 + name.len() as u64, name.len());
+        Token {
+            token_type: TokenType::Identifier,
+            lexeme: Lexeme::from(name),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), end),
+            literal: None,
+            error: false,
+        }
+    }
+
+    #[test]
+    fn test_define_and_get() {
+        let mut env = Environment::new();
+        env.define("a", Value::Number(1.0));
+        assert_eq!(env.get(&make_identifier("a")), Ok(Value::Number(1.0)));
+        env.define("a", Value::Bool(true));
+        assert_eq!(env.get(&make_identifier("a")), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_get_undefined() {
+        let env = Environment::new();
+        let err = env.get(&make_identifier("a")).unwrap_err();
+        assert_eq!(err.message, "Undefined variable 'a'.");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Value::Nil);
+        let mut local = Environment::new_enclosed(globals);
+        local.define("b", Value::Bool(true));
+        assert_eq!(local.lookup("a"), Some(Value::Nil));
+        assert_eq!(local.lookup("b"), Some(Value::Bool(true)));
+        assert_eq!(local.lookup("c"), None);
+    }
+
+    #[test]
+    fn test_globals() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let outer = Environment::new_enclosed(Rc::clone(&globals));
+        let inner = Environment::new_enclosed(Rc::new(RefCell::new(outer)));
+        let inner = Rc::new(RefCell::new(inner));
+        assert!(Rc::ptr_eq(&Environment::globals(&inner), &globals));
+        assert!(Rc::ptr_eq(&Environment::globals(&globals), &globals));
+    }
+
+    #[test]
+    fn test_assign() {
+        let mut env = Environment::new();
+        env.define("a", Value::Nil);
+        env.assign(&make_identifier("a"), Value::Number(2.0))
+            .unwrap();
+        assert_eq!(env.get(&make_identifier("a")), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_assign_constant() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define_constant("a", Value::Nil);
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        let msg = "Cannot assign to constant 'a'.";
+        let err = local.assign(&make_identifier("a"), Value::Nil).unwrap_err();
+        assert_eq!(err.message, msg);
+        let err = local
+            .assign_at(1, &make_identifier("a"), Value::Nil)
+            .unwrap_err();
+        assert_eq!(err.message, msg);
+        // Redefining the name makes it a variable again.
+        globals.borrow_mut().define("a", Value::Nil);
+        assert!(local.assign(&make_identifier("a"), Value::Nil).is_ok());
+    }
+
+    #[test]
+    fn test_assign_undefined() {
+        let mut env = Environment::new();
+        let err = env.assign(&make_identifier("a"), Value::Nil).unwrap_err();
+        assert_eq!(err.message, "Undefined variable 'a'.");
+    }
+
+    #[test]
+    fn test_get_and_assign_at() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Value::Number(1.0));
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        local.define("a", Value::Number(2.0));
+
+        assert_eq!(
+            local.get_at(0, &make_identifier("a")),
+            Ok(Value::Number(2.0))
+        );
+        assert_eq!(
+            local.get_at(1, &make_identifier("a")),
+            Ok(Value::Number(1.0))
+        );
+        local
+            .assign_at(1, &make_identifier("a"), Value::Nil)
+            .unwrap();
+        assert_eq!(globals.borrow().get(&make_identifier("a")), Ok(Value::Nil));
+        assert_eq!(
+            local.get_at(0, &make_identifier("a")),
+            Ok(Value::Number(2.0))
+        );
+
+        let err = local.get_at(2, &make_identifier("a")).unwrap_err();
+        assert_eq!(err.message, "Undefined variable 'a'.");
+    }
+
+    #[test]
+    fn test_enclosing_scopes() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Value::Number(1.0));
+        globals.borrow_mut().define("b", Value::Number(2.0));
+
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        local.define("b", Value::Number(3.0));
+        // Reads see the innermost binding.
+        assert_eq!(local.get(&make_identifier("a")), Ok(Value::Number(1.0)));
+        assert_eq!(local.get(&make_identifier("b")), Ok(Value::Number(3.0)));
+
+        // Assignments update the innermost binding, leaving shadowed
+        // bindings untouched.
+        local.assign(&make_identifier("a"), Value::Nil).unwrap();
+        local.assign(&make_identifier("b"), Value::Nil).unwrap();
+        let a = globals.borrow().get(&make_identifier("a"));
+        let b = globals.borrow().get(&make_identifier("b"));
+        assert_eq!(a, Ok(Value::Nil));
+        assert_eq!(b, Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_bindings() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Value::Nil);
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        local.define("c", Value::Number(1.0));
+        local.define("b", Value::Bool(true));
+        assert_eq!(
+            local.bindings(),
+            vec![
+                (String::from("b"), Value::Bool(true)),
+                (String::from("c"), Value::Number(1.0)),
+            ]
+        );
+        assert!(Rc::ptr_eq(&local.enclosing().unwrap(), &globals));
+        assert!(globals.borrow().enclosing().is_none());
+    }
+
+    #[test]
+    fn test_drop_long_chain() {
+        let mut env = Rc::new(RefCell::new(Environment::new()));
+        for _ in 0..1_000_000 {
+            env = Rc::new(RefCell::new(Environment::new_enclosed(env)));
+        }
+        drop(env);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+use super::messages::{self, Arg, English, Locale, Message};
+use super::span::Span;
+use super::token::Token;
+use super::value::Value;
+
+// RuntimeError is an error raised while executing a program, at the
+// location of token. token is boxed to keep the Results returned throughout
+// the interpreter small.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub token: Box<Token>,
+    pub message: Message,
+    // thrown is the value thrown, if the error is raised by a throw
+    // expression. The message is then the value as printed.
+    pub thrown: Option<Value>,
+    // catchable is false for errors that a try statement must not catch,
+    // such as exceeding the limits of the interpreter.
+    pub catchable: bool,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: impl Into<Message>) -> Self {
+        RuntimeError {
+            token: Box::new(token.clone()),
+            message: message.into(),
+            thrown: None,
+            catchable: true,
+        }
+    }
+
+    // throw returns the error raised by throwing value at token.
+    pub fn throw(token: &Token, value: Value) -> Self {
+        RuntimeError {
+            message: Message::from(value.to_string()),
+            thrown: Some(value),
+            ..RuntimeError::new(token, "")
+        }
+    }
+
+    // uncatchable returns an error that is not caught by try statements.
+    pub fn uncatchable(token: &Token, message: impl Into<Message>) -> Self {
+        RuntimeError {
+            catchable: false,
+            ..RuntimeError::new(token, message)
+        }
+    }
+
+    // to_message returns the error as displayed, as a message that can be
+    // localized.
+    pub fn to_message(&self) -> Message {
+        let line = self.token.span.start.line.to_string();
+        messages::RUNTIME_ERROR.with_args(vec![self.message.clone().into(), line.into()])
+    }
+
+    // localize returns the error as displayed, in locale.
+    pub fn localize(&self, locale: &dyn Locale) -> String {
+        self.to_message().localize(locale)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.localize(&English))
+    }
+}
+
+// FixIt is a suggested fix for an error, replacing the source of span with
+// replacement. An empty span suggests inserting replacement.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FixIt {
+    pub span: Span,
+    pub replacement: String,
+}
+
+// LoxError is an error reported by one of the stages of running a program.
+// Scan, parse and resolve errors carry the span and lexeme of the source
+// they were reported at, and parse errors an optional FixIt; runtime errors
+// wrap the RuntimeError.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LoxError {
+    Scan {
+        span: Span,
+        lexeme: String,
+        message: Message,
+    },
+    Parse {
+        span: Span,
+        lexeme: String,
+        message: Message,
+        fix: Option<FixIt>,
+    },
+    Resolve {
+        span: Span,
+        lexeme: String,
+        message: Message,
+    },
+    Runtime(RuntimeError),
+}
+
+impl LoxError {
+    // span returns the span of the source the error was reported at.
+    pub fn span(&self) -> Span {
+        match self {
+            LoxError::Scan { span, .. }
+            | LoxError::Parse { span, .. }
+            | LoxError::Resolve { span, .. } => *span,
+            LoxError::Runtime(err) => err.token.span,
+        }
+    }
+
+    // lexeme returns the source text the error was reported at. The lexeme is
+    // empty for errors reported at the end of the source.
+    pub fn lexeme(&self) -> &str {
+        match self {
+            LoxError::Scan { lexeme, .. }
+            | LoxError::Parse { lexeme, .. }
+            | LoxError::Resolve { lexeme, .. } => lexeme,
+            LoxError::Runtime(err) => &err.token.lexeme,
+        }
+    }
+
+    // message returns the message of the error, in English.
+    pub fn message(&self) -> &str {
+        self.diagnostic().as_str()
+    }
+
+    // code returns the code of the message of the error in the message
+    // catalog, or None for thrown values and errors of native functions
+    // defined by embedders.
+    pub fn code(&self) -> Option<&'static str> {
+        self.diagnostic().code()
+    }
+
+    fn diagnostic(&self) -> &Message {
+        match self {
+            LoxError::Scan { message, .. }
+            | LoxError::Parse { message, .. }
+            | LoxError::Resolve { message, .. } => message,
+            LoxError::Runtime(err) => &err.message,
+        }
+    }
+
+    // line returns the line the error was reported at.
+    pub fn line(&self) -> u64 {
+        self.span().start.line
+    }
+
+    // fix returns the suggested fix for the error, if any.
+    pub fn fix(&self) -> Option<&FixIt> {
+        match self {
+            LoxError::Parse { fix, .. } => fix.as_ref(),
+            _ => None,
+        }
+    }
+
+    // to_message returns the error as displayed, as a message that can be
+    // localized.
+    pub fn to_message(&self) -> Message {
+        let line = Arg::from(self.line().to_string());
+        match self {
+            LoxError::Scan { message, .. } => {
+                messages::ERROR.with_args(vec![line, message.clone().into()])
+            }
+            LoxError::Parse {
+                lexeme, message, ..
+            }
+            | LoxError::Resolve {
+                lexeme, message, ..
+            } => {
+                if lexeme.is_empty() {
+                    messages::ERROR_AT_END.with_args(vec![line, message.clone().into()])
+                } else {
+                    let lexeme = Arg::from(lexeme.clone());
+                    messages::ERROR_AT.with_args(vec![line, lexeme, message.clone().into()])
+                }
+            }
+            LoxError::Runtime(err) => err.to_message(),
+        }
+    }
+
+    // localize returns the error as displayed, in locale.
+    pub fn localize(&self, locale: &dyn Locale) -> String {
+        self.to_message().localize(locale)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(err: RuntimeError) -> Self {
+        LoxError::Runtime(err)
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.localize(&English))
+    }
+}
+
+impl std::error::Error for LoxError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Catalog;
+    use crate::span::Position;
+
+    fn make_span(line: u64) -> Span {
+        Span::new(Position::new(line, 1, 0), Position::new(line, 2, 1))
+    }
+
+    #[test]
+    fn test_display() {
+        let err = LoxError::Scan {
+            span: make_span(1),
+            lexeme: "~".to_owned(),
+            message: messages::UNEXPECTED_CHARACTER.with(&[&'~']),
+        };
+        assert_eq!(err.to_string(), "[line 1] Error: Unexpected character '~'.");
+        let err = LoxError::Parse {
+            span: make_span(2),
+            lexeme: "=".to_owned(),
+            message: messages::INVALID_ASSIGNMENT_TARGET.into(),
+            fix: None,
+        };
+        assert_eq!(
+            err.to_string(),
+            "[line 2] Error at '=': Invalid assignment target."
+        );
+        let err = LoxError::Parse {
+            span: make_span(3),
+            lexeme: String::new(),
+            message: messages::EXPECT_SEMICOLON_AFTER_VALUE.into(),
+            fix: None,
+        };
+        assert_eq!(
+            err.to_string(),
+            "[line 3] Error at end: Expect ';' after value."
+        );
+    }
+
+    #[test]
+    fn test_localize() {
+        let mut catalog = Catalog::new();
+        catalog.insert("D002", "[ligne {0}] Erreur à '{1}' : {2}");
+        catalog.insert("P045", "Cible d'affectation invalide.");
+        let err = LoxError::Parse {
+            span: make_span(2),
+            lexeme: "=".to_owned(),
+            message: messages::INVALID_ASSIGNMENT_TARGET.into(),
+            fix: None,
+        };
+        assert_eq!(err.code(), Some("P045"));
+        assert_eq!(
+            err.localize(&catalog),
+            "[ligne 2] Erreur à '=' : Cible d'affectation invalide."
+        );
+    }
+}
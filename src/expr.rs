@@ -1,13 +1,40 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use super::stmt::FunctionStmt;
 use super::token::{self, Token};
 
 pub enum Expr {
+    Assign(AssignExpr),
     Binary(BinaryExpr),
+    Call(CallExpr),
+    Function(FunctionExpr),
+    Get(GetExpr),
     Grouping(GroupingExpr),
+    Increment(IncrementExpr),
+    Index(IndexExpr),
+    List(ListExpr),
     Literal(LiteralExpr),
+    Logical(LogicalExpr),
+    Map(MapExpr),
+    Set(SetExpr),
+    SetIndex(SetIndexExpr),
+    Super(SuperExpr),
+    This(ThisExpr),
+    Throw(ThrowExpr),
     Unary(UnaryExpr),
+    Variable(VariableExpr),
 }
 
 impl Expr {
+    pub fn make_assign(name: Token, value: Expr) -> Expr {
+        Expr::Assign(AssignExpr {
+            name,
+            value: Box::new(value),
+            depth: Cell::new(None),
+        })
+    }
+
     pub fn make_binary(left: Expr, operator: Token, right: Expr) -> Expr {
         Expr::Binary(BinaryExpr {
             left: Box::new(left),
@@ -16,14 +43,107 @@ impl Expr {
         })
     }
 
+    pub fn make_call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        Expr::Call(CallExpr {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    pub fn make_function(function: Rc<FunctionStmt>) -> Expr {
+        Expr::Function(FunctionExpr { function })
+    }
+
+    pub fn make_get(object: Expr, name: Token) -> Expr {
+        Expr::Get(GetExpr {
+            object: Box::new(object),
+            name,
+        })
+    }
+
     pub fn make_grouping(expression: Expr) -> Expr {
         Expr::Grouping(GroupingExpr {
             expression: Box::new(expression),
         })
     }
 
+    pub fn make_increment(operator: Token, target: Expr, prefix: bool) -> Expr {
+        Expr::Increment(IncrementExpr {
+            operator,
+            target: Box::new(target),
+            prefix,
+        })
+    }
+
+    pub fn make_index(object: Expr, bracket: Token, index: Expr) -> Expr {
+        Expr::Index(IndexExpr {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+        })
+    }
+
+    pub fn make_list(bracket: Token, elements: Vec<Expr>) -> Expr {
+        Expr::List(ListExpr { bracket, elements })
+    }
+
     pub fn make_literal(value: token::Literal) -> Expr {
-        Expr::Literal(LiteralExpr { value })
+        Expr::Literal(LiteralExpr {
+            value,
+            constant: Cell::new(None),
+        })
+    }
+
+    pub fn make_logical(left: Expr, operator: Token, right: Expr) -> Expr {
+        Expr::Logical(LogicalExpr {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    pub fn make_set(object: Expr, name: Token, value: Expr) -> Expr {
+        Expr::Set(SetExpr {
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        })
+    }
+
+    pub fn make_map(brace: Token, entries: Vec<(Expr, Expr)>) -> Expr {
+        Expr::Map(MapExpr { brace, entries })
+    }
+
+    pub fn make_set_index(object: Expr, bracket: Token, index: Expr, value: Expr) -> Expr {
+        Expr::SetIndex(SetIndexExpr {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            value: Box::new(value),
+        })
+    }
+
+    pub fn make_super(keyword: Token, method: Token) -> Expr {
+        Expr::Super(SuperExpr {
+            keyword,
+            method,
+            depth: Cell::new(None),
+        })
+    }
+
+    pub fn make_this(keyword: Token) -> Expr {
+        Expr::This(ThisExpr {
+            keyword,
+            depth: Cell::new(None),
+        })
+    }
+
+    pub fn make_throw(keyword: Token, value: Expr) -> Expr {
+        Expr::Throw(ThrowExpr {
+            keyword,
+            value: Box::new(value),
+        })
     }
 
     pub fn make_unary(operator: Token, expression: Expr) -> Expr {
@@ -32,6 +152,95 @@ impl Expr {
             expression: Box::new(expression),
         })
     }
+
+    pub fn make_variable(name: Token) -> Expr {
+        Expr::Variable(VariableExpr {
+            name,
+            depth: Cell::new(None),
+        })
+    }
+}
+
+// Dropping an expression iteratively rather than recursively, so that
+// dropping a deeply nested expression cannot overflow the stack.
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut expr) = stack.pop() {
+            // expr is dropped without children at the end of each iteration.
+            expr.take_children(&mut stack);
+        }
+    }
+}
+
+impl Expr {
+    // take_children moves the child expressions of the expression to stack,
+    // leaving leaf expressions in their place.
+    fn take_children(&mut self, stack: &mut Vec<Expr>) {
+        fn take(expr: &mut Box<Expr>, stack: &mut Vec<Expr>) {
+            if let Expr::Literal(_) = **expr {
+                return;
+            }
+            let leaf = Expr::make_literal(token::Literal::Nil);
+            stack.push(std::mem::replace(&mut **expr, leaf));
+        }
+
+        match self {
+            Expr::Assign(expr) => take(&mut expr.value, stack),
+            Expr::Binary(expr) => {
+                take(&mut expr.left, stack);
+                take(&mut expr.right, stack);
+            }
+            Expr::Call(expr) => {
+                take(&mut expr.callee, stack);
+                stack.append(&mut expr.arguments);
+            }
+            Expr::Get(expr) => take(&mut expr.object, stack),
+            Expr::Grouping(expr) => take(&mut expr.expression, stack),
+            Expr::Increment(expr) => take(&mut expr.target, stack),
+            Expr::Index(expr) => {
+                take(&mut expr.object, stack);
+                take(&mut expr.index, stack);
+            }
+            Expr::List(expr) => stack.append(&mut expr.elements),
+            Expr::Map(expr) => {
+                for (key, value) in expr.entries.drain(..) {
+                    stack.push(key);
+                    stack.push(value);
+                }
+            }
+            Expr::Logical(expr) => {
+                take(&mut expr.left, stack);
+                take(&mut expr.right, stack);
+            }
+            Expr::Set(expr) => {
+                take(&mut expr.object, stack);
+                take(&mut expr.value, stack);
+            }
+            Expr::SetIndex(expr) => {
+                take(&mut expr.object, stack);
+                take(&mut expr.index, stack);
+                take(&mut expr.value, stack);
+            }
+            Expr::Throw(expr) => take(&mut expr.value, stack),
+            Expr::Unary(expr) => take(&mut expr.expression, stack),
+            Expr::Function(_)
+            | Expr::Literal(_)
+            | Expr::Super(_)
+            | Expr::This(_)
+            | Expr::Variable(_) => {}
+        }
+    }
+}
+
+pub struct AssignExpr {
+    pub name: Token,
+    pub value: Box<Expr>,
+    // depth is the number of scopes between the expression and the scope
+    // declaring the variable, as resolved by the Resolver. None means the
+    // variable is global.
+    pub depth: Cell<Option<usize>>,
 }
 
 pub struct BinaryExpr {
@@ -40,12 +249,109 @@ pub struct BinaryExpr {
     pub right: Box<Expr>,
 }
 
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    // paren is the closing parenthesis of the call, used to report errors
+    // for the call.
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+// FunctionExpr is an anonymous function, "fun (a, b) { ... }". The name of
+// its declaration is the "fun" keyword, renamed "lambda".
+pub struct FunctionExpr {
+    pub function: Rc<FunctionStmt>,
+}
+
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
 pub struct GroupingExpr {
     pub expression: Box<Expr>,
 }
 
+// IncrementExpr is a "++" or "--" applied to target, before (prefix) or
+// after (postfix) it. The value of a prefix expression is the updated value
+// of target, that of a postfix expression its value before the update.
+pub struct IncrementExpr {
+    pub operator: Token,
+    // target is the operand, which must be a variable or a property when the
+    // expression is evaluated.
+    pub target: Box<Expr>,
+    pub prefix: bool,
+}
+
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    // bracket is the closing bracket of the index, used to report errors
+    // for the expression.
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+pub struct ListExpr {
+    // bracket is the opening bracket of the list.
+    pub bracket: Token,
+    pub elements: Vec<Expr>,
+}
+
 pub struct LiteralExpr {
     pub value: token::Literal,
+    // constant is the index of value in the constant table of the function
+    // the expression is in, as canonicalized by the Resolver. It is set in
+    // addition to value, see Constants.
+    pub constant: Cell<Option<usize>>,
+}
+
+pub struct LogicalExpr {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+pub struct MapExpr {
+    // brace is the opening brace of the map, used to report errors for
+    // invalid keys.
+    pub brace: Token,
+    // entries are the keys and values of the map, in source order.
+    pub entries: Vec<(Expr, Expr)>,
+}
+
+// SetIndexExpr is an assignment to an element of a list, "object[index] =
+// value". bracket is as for IndexExpr.
+pub struct SetIndexExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+pub struct SuperExpr {
+    pub keyword: Token,
+    pub method: Token,
+    // depth is as for VariableExpr, for the implicit "super" variable.
+    pub depth: Cell<Option<usize>>,
+}
+
+pub struct ThisExpr {
+    pub keyword: Token,
+    // depth is as for VariableExpr, for the implicit "this" variable.
+    pub depth: Cell<Option<usize>>,
+}
+
+// ThrowExpr throws value, unwinding to the innermost enclosing try
+// statement. It never evaluates to a value.
+pub struct ThrowExpr {
+    pub keyword: Token,
+    pub value: Box<Expr>,
 }
 
 pub struct UnaryExpr {
@@ -53,13 +359,36 @@ pub struct UnaryExpr {
     pub expression: Box<Expr>,
 }
 
+pub struct VariableExpr {
+    pub name: Token,
+    // depth is the number of scopes between the expression and the scope
+    // declaring the variable, as resolved by the Resolver. None means the
+    // variable is global.
+    pub depth: Cell<Option<usize>>,
+}
+
 pub trait Visitor {
     type Result;
 
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::Result;
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::Result;
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::Result;
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) -> Self::Result;
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Self::Result;
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Self::Result;
+    fn visit_increment_expr(&mut self, expr: &IncrementExpr) -> Self::Result;
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Self::Result;
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> Self::Result;
     fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Self::Result;
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Self::Result;
+    fn visit_map_expr(&mut self, expr: &MapExpr) -> Self::Result;
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> Self::Result;
+    fn visit_set_index_expr(&mut self, expr: &SetIndexExpr) -> Self::Result;
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> Self::Result;
+    fn visit_this_expr(&mut self, expr: &ThisExpr) -> Self::Result;
+    fn visit_throw_expr(&mut self, expr: &ThrowExpr) -> Self::Result;
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::Result;
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Self::Result;
 }
 
 pub trait AcceptsVisitor {
@@ -70,10 +399,25 @@ impl AcceptsVisitor for Expr {
     fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Result {
         use Expr::*;
         match *self {
+            Assign(ref expr) => visitor.visit_assign_expr(expr),
             Binary(ref expr) => visitor.visit_binary_expr(expr),
+            Call(ref expr) => visitor.visit_call_expr(expr),
+            Function(ref expr) => visitor.visit_function_expr(expr),
+            Get(ref expr) => visitor.visit_get_expr(expr),
             Grouping(ref expr) => visitor.visit_grouping_expr(expr),
+            Increment(ref expr) => visitor.visit_increment_expr(expr),
+            Index(ref expr) => visitor.visit_index_expr(expr),
+            List(ref expr) => visitor.visit_list_expr(expr),
             Literal(ref expr) => visitor.visit_literal_expr(expr),
+            Logical(ref expr) => visitor.visit_logical_expr(expr),
+            Map(ref expr) => visitor.visit_map_expr(expr),
+            Set(ref expr) => visitor.visit_set_expr(expr),
+            SetIndex(ref expr) => visitor.visit_set_index_expr(expr),
+            Super(ref expr) => visitor.visit_super_expr(expr),
+            This(ref expr) => visitor.visit_this_expr(expr),
+            Throw(ref expr) => visitor.visit_throw_expr(expr),
             Unary(ref expr) => visitor.visit_unary_expr(expr),
+            Variable(ref expr) => visitor.visit_variable_expr(expr),
         }
     }
 }
@@ -89,3 +433,27 @@ impl AcceptsVisitor for GroupingExpr {
         visitor.visit_grouping_expr(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{Position, Span};
+    use crate::token::{Lexeme, TokenType};
+
+    #[test]
+    fn test_drop_deep_expression() {
+        let minus = Token {
+            token_type: TokenType::Minus,
+            lexeme: Lexeme::from("-"),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), Position::new(1, 2, 1)),
+            literal: None,
+            error: false,
+        };
+        let mut expr = Expr::make_literal(token::Literal::Number(1.0));
+        for _ in 0..1_000_000 {
+            expr = Expr::make_grouping(Expr::make_unary(minus.clone(), expr));
+        }
+        drop(expr);
+    }
+}
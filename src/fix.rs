@@ -0,0 +1,324 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use super::error::FixIt;
+use super::messages::Message;
+use super::parser::{Dialect, Parser};
+use super::resolver::Resolver;
+use super::scanner::Scanner;
+use super::span::Span;
+
+// MAX_PASSES is the maximum number of times fix_source collects and applies
+// fixes. Applying fixes can reveal more, e.g. a program only resolves, and
+// so gets its unused variables reported, once its syntax errors are fixed.
+const MAX_PASSES: usize = 8;
+
+// DIFF_CONTEXT is the number of unchanged lines shown around each change in
+// a diff.
+const DIFF_CONTEXT: usize = 3;
+
+// Fix is a machine-applicable FixIt suggested by a diagnostic, together
+// with the line and message of the diagnostic.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Fix {
+    pub line: u64,
+    pub message: String,
+    pub fix: FixIt,
+}
+
+// collect_fixes scans, parses and resolves source, returning the fixes
+// suggested by the errors and warnings reported, ordered by their position
+// in source. Programs that fail to parse are not resolved, so only their
+// parse fixes are returned.
+pub fn collect_fixes(source: &str, scanner: &Scanner, dialect: Dialect) -> Vec<Fix> {
+    // The WarningCallback must be 'static, so the fixes are shared with it.
+    let fixes = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&fixes);
+    let record_fix = move |span: Span, msg: &Message, fix: Option<&FixIt>| {
+        if let Some(fix) = fix {
+            recorded.borrow_mut().push(Fix {
+                line: span.start.line,
+                message: msg.to_string(),
+                fix: fix.clone(),
+            });
+        }
+    };
+    let (tokens, errors) = scanner.scan_tokens(source);
+    if errors.is_empty() {
+        let parser = Parser::new()
+            .with_dialect(dialect)
+            .with_unicode_identifiers(scanner.unicode_identifiers())
+            .with_warning_cb(&record_fix);
+        match parser.parse(tokens) {
+            Ok(program) => {
+                // Resolve errors suggest no fixes.
+                let _ = Resolver::new()
+                    .with_warning_cb(&record_fix)
+                    .resolve(&program);
+            }
+            Err(errors) => {
+                for err in errors {
+                    record_fix(err.span(), &err.message().into(), err.fix());
+                }
+            }
+        }
+    }
+    let mut fixes = fixes.take();
+    fixes.sort_by_key(|fix| (fix.fix.span.start.offset, fix.fix.span.end.offset));
+    fixes
+}
+
+// apply applies fixes, which must be ordered by position, to source. A fix
+// overlapping an earlier fix is skipped. Removing a declaration that is
+// alone on its line removes the whole line. apply returns the fixed source
+// and the fixes that were applied.
+pub fn apply(source: &str, fixes: &[Fix]) -> (String, Vec<Fix>) {
+    let mut applied: Vec<Fix> = Vec::new();
+    let mut edits = Vec::new();
+    let mut end = 0;
+    for fix in fixes {
+        let mut range = fix.fix.span.bytes();
+        if range.start < end || range.end > source.len() {
+            continue;
+        }
+        if fix.fix.replacement.is_empty() {
+            range = whole_lines(source, range);
+        }
+        end = range.end;
+        edits.push((range, fix.fix.replacement.as_str()));
+        applied.push(fix.clone());
+    }
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (range, replacement) in edits {
+        fixed.push_str(&source[copied..range.start]);
+        fixed.push_str(replacement);
+        copied = range.end;
+    }
+    fixed.push_str(&source[copied..]);
+    (fixed, applied)
+}
+
+// whole_lines extends range to cover the lines it is on, including the
+// line break, if the rest of those lines is only whitespace.
+fn whole_lines(source: &str, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[range.end..]
+        .find('\n')
+        .map_or(source.len(), |i| range.end + i + 1);
+    let before = &source[line_start..range.start];
+    let after = &source[range.end..line_end];
+    if before.trim().is_empty() && after.trim().is_empty() {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+// fix_source repeatedly collects and applies the fixes of source until no
+// more are suggested, returning the fixed source and all fixes applied.
+pub fn fix_source(source: &str, scanner: &Scanner, dialect: Dialect) -> (String, Vec<Fix>) {
+    let mut source = source.to_owned();
+    let mut applied = Vec::new();
+    for _ in 0..MAX_PASSES {
+        let fixes = collect_fixes(&source, scanner, dialect);
+        if fixes.is_empty() {
+            break;
+        }
+        let (fixed, pass) = apply(&source, &fixes);
+        source = fixed;
+        applied.extend(pass);
+    }
+    (source, applied)
+}
+
+// DiffOp is an operation of a line based edit script.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum DiffOp<'s> {
+    Keep(&'s str),
+    Delete(&'s str),
+    Insert(&'s str),
+}
+
+// diff returns a unified diff from old to new, the contents of the file at
+// path before and after being fixed, or an empty string if they are equal.
+pub fn diff(path: &str, old: &str, new: &str) -> String {
+    let ops = diff_lines(old, new);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Keep(_)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    // Group the changes into hunks, merging changes whose context overlaps.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(DIFF_CONTEXT);
+        let end = (i + DIFF_CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    for (start, end) in hunks {
+        // The line numbers of the hunk are those of the first line in each
+        // file, counting the lines before it.
+        let (mut old_line, mut new_line) = (0, 0);
+        for op in &ops[..start] {
+            match op {
+                DiffOp::Keep(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                DiffOp::Delete(_) => old_line += 1,
+                DiffOp::Insert(_) => new_line += 1,
+            }
+        }
+        let hunk = &ops[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(_)))
+            .count();
+        // An empty range is numbered by the line before it.
+        let first = |line: usize, count: usize| if count == 0 { line } else { line + 1 };
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            first(old_line, old_count),
+            old_count,
+            first(new_line, new_count),
+            new_count
+        );
+        for op in hunk {
+            let (prefix, line) = match op {
+                DiffOp::Keep(line) => (' ', line),
+                DiffOp::Delete(line) => ('-', line),
+                DiffOp::Insert(line) => ('+', line),
+            };
+            let _ = writeln!(out, "{}{}", prefix, line);
+        }
+    }
+    out
+}
+
+// diff_lines returns an edit script from the lines of old to those of new,
+// keeping their longest common subsequence.
+fn diff_lines<'s>(old: &'s str, new: &'s str) -> Vec<DiffOp<'s>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j] is the length of the longest common subsequence of old[i..]
+    // and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Keep(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffOp::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| DiffOp::Delete(line)));
+    ops.extend(new[j..].iter().map(|line| DiffOp::Insert(line)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix_standard(source: &str) -> (String, Vec<String>) {
+        let (fixed, fixes) = fix_source(source, &Scanner::new(), Dialect::Standard);
+        let messages = fixes.into_iter().map(|fix| fix.message).collect();
+        (fixed, messages)
+    }
+
+    #[test]
+    fn test_fix_missing_semicolons() {
+        let (fixed, messages) = fix_standard("print 1\nprint 2\nprint 3;\n");
+        assert_eq!(fixed, "print 1;\nprint 2;\nprint 3;\n");
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn test_fix_assignment_condition() {
+        let (fixed, _) = fix_standard("var a = 1;\nif (a = 2) print a;\n");
+        assert_eq!(fixed, "var a = 1;\nif (a == 2) print a;\n");
+    }
+
+    #[test]
+    fn test_fix_unused_locals() {
+        let source = "fun f() {\n  var a = 1\n  var b = g(); var c;\n  return 2;\n}\n";
+        let (fixed, messages) = fix_standard(source);
+        // The semicolon is fixed in the first pass, the unused variables in
+        // the second. b has an initializer with side effects, so it is kept.
+        assert_eq!(fixed, "fun f() {\n  var b = g(); \n  return 2;\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "Expect ';' after variable declaration.",
+                "Local variable 'a' is never used.",
+                "Local variable 'c' is never used.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_skips_overlapping_fixes() {
+        let fix = |start: usize, end: usize, replacement: &str| Fix {
+            line: 1,
+            message: String::new(),
+            fix: FixIt {
+                span: Span::new(
+                    crate::span::Position::new(1, start as u64 + 1, start),
+                    crate::span::Position::new(1, end as u64 + 1, end),
+                ),
+                replacement: replacement.to_owned(),
+            },
+        };
+        let fixes = vec![fix(0, 2, "x"), fix(1, 3, "y"), fix(4, 4, "z")];
+        let (fixed, applied) = apply("abcd", &fixes);
+        assert_eq!(fixed, "xcdz");
+        assert_eq!(applied, vec![fixes[0].clone(), fixes[2].clone()]);
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a.lox", "a\nb\n", "a\nb\n"), "");
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n12\n";
+        assert_eq!(
+            diff("a.lox", old, new),
+            "--- a/a.lox\n+++ b/a.lox\n\
+             @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+             @@ -8,5 +8,4 @@\n 8\n 9\n 10\n-11\n 12\n"
+        );
+    }
+}
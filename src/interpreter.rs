@@ -0,0 +1,3026 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::environment::Environment;
+use super::error::{LoxError, RuntimeError};
+use super::expr::*;
+use super::messages::{self, Message};
+use super::parser::{Dialect, Parser};
+use super::prelude::Prelude;
+use super::resolver::Resolver;
+use super::scanner::Scanner;
+use super::span::{Position, Span};
+use super::stdlib::{self, Stdlib};
+use super::stmt::*;
+use super::token::{Lexeme, Literal, Token, TokenType};
+use super::trace::{self, Event, Trace};
+use super::value::Value;
+
+// Callable is implemented by the values that can be called, such as
+// functions and classes.
+pub trait Callable {
+    // arity returns the number of arguments the callable expects.
+    fn arity(&self) -> usize;
+
+    // call calls the callable with arguments, which is guaranteed to have
+    // arity number of values.
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+}
+
+// LoxFunction is a function declared in a Lox program, together with the
+// environment it was declared in (its closure).
+pub struct LoxFunction {
+    declaration: Rc<FunctionStmt>,
+    closure: Rc<RefCell<Environment>>,
+
+    // is_initializer is true if the function is the init method of a class,
+    // which always returns the instance being initialized.
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionStmt>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.declaration.name.lexeme
+    }
+
+    // is_getter returns whether the function is a getter, called on access
+    // of its property.
+    pub fn is_getter(&self) -> bool {
+        self.declaration.getter
+    }
+
+    // bind returns a copy of the method with "this" bound to this, the
+    // instance of a method, or the class of a class method.
+    pub fn bind(&self, this: Value) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        environment.define("this", this);
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    // this returns the instance bound to the (bound) method.
+    fn this(&self) -> Value {
+        let this = self.closure.borrow().lookup("this");
+        this.expect("initializer without a bound instance")
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            interpreter.record(|| Event::Define {
+                line: param.span.start.line,
+                global: false,
+                name: param.lexeme.to_string(),
+                value: trace::describe(&argument),
+            });
+            environment.define(&param.lexeme, argument);
+        }
+        let environment = Rc::new(RefCell::new(environment));
+        match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(err)) => Err(err),
+            Err(Unwind::Break) | Err(Unwind::Continue) => {
+                unreachable!("loop control outside of a loop")
+            }
+        }
+    }
+}
+
+// Functions are compared by identity.
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Debug is implemented manually, as the closure may (indirectly) refer back
+// to the function itself.
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+// NativeCallback implements a NativeFunction. It is called with the
+// arguments of a call, and returns the value of the call, or the message of
+// the runtime error raised by the call. Messages not in the message catalog
+// can be made from strings, e.g. Err("No such file.".into()).
+pub type NativeCallback = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Message>;
+
+// NativeFunction is a function implemented in Rust, callable from Lox like
+// any other function.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    // min_arity is the least number of arguments the function accepts, see
+    // with_min_arity.
+    min_arity: usize,
+    function: Box<NativeCallback>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: usize, function: F) -> Self
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Message> + 'static,
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            arity,
+            min_arity: arity,
+            function: Box::new(function),
+        }
+    }
+
+    // with_min_arity makes the function also accept fewer arguments than its
+    // arity, down to min_arity. The trailing arguments are then optional,
+    // and the function is called with only the arguments given.
+    pub fn with_min_arity(mut self, min_arity: usize) -> Self {
+        self.min_arity = min_arity;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn min_arity(&self) -> usize {
+        self.min_arity
+    }
+
+    // call calls the native function with arguments, which is expected to
+    // have between min_arity and arity number of values.
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Message> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+// Native functions are compared by identity, as for LoxFunction.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+// LoxClass is a class declared in a Lox program. Calling a class creates a
+// new instance of it.
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    class_methods: HashMap<String, Rc<LoxFunction>>,
+
+    // fields are the declared fields of the class, initialized on each new
+    // instance before init is called.
+    fields: Vec<Rc<VarStmt>>,
+    // field_closure is the environment the field initializers are evaluated
+    // in, i.e. the environment the class was declared in.
+    field_closure: Option<Rc<RefCell<Environment>>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name: name.to_owned(),
+            superclass,
+            methods,
+            class_methods: HashMap::new(),
+            fields: Vec::new(),
+            field_closure: None,
+        }
+    }
+
+    // with_class_methods declares the class methods of the class, called on
+    // the class itself.
+    pub fn with_class_methods(mut self, class_methods: HashMap<String, Rc<LoxFunction>>) -> Self {
+        self.class_methods = class_methods;
+        self
+    }
+
+    // with_fields declares fields on the class, with initializers evaluated
+    // in closure.
+    pub fn with_fields(
+        mut self,
+        fields: Vec<Rc<VarStmt>>,
+        closure: Rc<RefCell<Environment>>,
+    ) -> Self {
+        self.fields = fields;
+        self.field_closure = Some(closure);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // find_method returns the method name of the class, looking in the
+    // superclass chain if the class does not itself define the method.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    // find_class_method returns the class method name of the class, looking
+    // in the superclass chain as for find_method.
+    pub fn find_class_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.class_methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref()?.find_class_method(name),
+        }
+    }
+
+    // get returns the property name of class, which is one of its class
+    // methods, bound to class.
+    pub fn get(class: &Rc<LoxClass>, name: &Token) -> Result<Value, RuntimeError> {
+        match class.find_class_method(&name.lexeme) {
+            Some(method) => {
+                let method = method.bind(Value::Class(Rc::clone(class)));
+                Ok(Value::Function(Rc::new(method)))
+            }
+            None => {
+                let msg = messages::UNDEFINED_PROPERTY.with(&[&name.lexeme]);
+                Err(RuntimeError::new(name, msg))
+            }
+        }
+    }
+
+    // initialize_fields sets the declared fields of instance to their
+    // initial values, starting with the fields declared by the superclasses
+    // so that a subclass can redeclare a field with a different value.
+    fn initialize_fields(
+        &self,
+        interpreter: &mut Interpreter,
+        instance: &LoxInstance,
+    ) -> Result<(), RuntimeError> {
+        if let Some(ref superclass) = self.superclass {
+            superclass.initialize_fields(interpreter, instance)?;
+        }
+        let closure = match self.field_closure {
+            Some(ref closure) => closure,
+            None => return Ok(()),
+        };
+        for field in &self.fields {
+            let value = match field.initializer {
+                Some(ref initializer) => {
+                    interpreter.evaluate_in(initializer, Rc::clone(closure))?
+                }
+                None => Value::Nil,
+            };
+            interpreter.set_field(instance, &field.name, value);
+        }
+        Ok(())
+    }
+}
+
+impl Callable for LoxClass {
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let instance = Rc::new(LoxInstance::new(Rc::clone(&self)));
+        self.initialize_fields(interpreter, &instance)?;
+        if let Some(init) = self.find_method("init") {
+            let init = init.bind(Value::Instance(Rc::clone(&instance)));
+            Rc::new(init).call(interpreter, arguments)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+// LoxInstance is an instance of a LoxClass, holding the instance's fields.
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: RefCell<HashMap<String, Value>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn class(&self) -> &LoxClass {
+        &self.class
+    }
+
+    // get returns the property name of instance: either a field, or a
+    // method of its class bound to instance. Fields shadow methods.
+    pub fn get(instance: &Rc<LoxInstance>, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = instance.fields.borrow().get(name.lexeme.as_str()) {
+            return Ok(value.clone());
+        }
+        match instance.class.find_method(&name.lexeme) {
+            Some(method) => {
+                let method = method.bind(Value::Instance(Rc::clone(instance)));
+                Ok(Value::Function(Rc::new(method)))
+            }
+            None => {
+                let msg = messages::UNDEFINED_PROPERTY.with(&[&name.lexeme]);
+                Err(RuntimeError::new(name, msg))
+            }
+        }
+    }
+
+    // set sets the field name of the instance to value.
+    pub fn set(&self, name: &Token, value: Value) {
+        self.fields
+            .borrow_mut()
+            .insert(name.lexeme.to_string(), value);
+    }
+}
+
+// LoxModule is a module loaded by an import statement, whose properties are
+// the globals of its program.
+pub struct LoxModule {
+    name: String,
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl LoxModule {
+    pub fn new(name: &str, globals: Rc<RefCell<Environment>>) -> Self {
+        LoxModule {
+            name: name.to_owned(),
+            globals,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // get returns the global name of the module.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.globals
+            .borrow()
+            .lookup_at(0, &name.lexeme)
+            .ok_or_else(|| {
+                let msg = messages::UNDEFINED_MODULE_PROPERTY.with(&[&name.lexeme, &self.name]);
+                RuntimeError::new(name, msg)
+            })
+    }
+}
+
+// Modules are compared by identity, as for instances.
+impl PartialEq for LoxModule {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+// Classes and instances are compared by identity, and their Debug output is
+// their Lox representation, as for LoxFunction.
+impl PartialEq for LoxClass {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+// LoxList is a list of values, as created by a list literal. Like
+// instances, lists are mutable and shared by reference.
+pub struct LoxList {
+    elements: RefCell<Vec<Value>>,
+}
+
+impl LoxList {
+    pub fn new(elements: Vec<Value>) -> Self {
+        LoxList {
+            elements: RefCell::new(elements),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    // get returns the element at index, or None if index is out of range.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.elements.borrow().get(index).cloned()
+    }
+
+    // set sets the element at index to value, returning false if index is
+    // out of range.
+    pub fn set(&self, index: usize, value: Value) -> bool {
+        match self.elements.borrow_mut().get_mut(index) {
+            Some(element) => {
+                *element = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn push(&self, value: Value) {
+        self.elements.borrow_mut().push(value);
+    }
+
+    pub fn pop(&self) -> Option<Value> {
+        self.elements.borrow_mut().pop()
+    }
+
+    // elements returns a copy of the elements of the list.
+    pub fn elements(&self) -> Vec<Value> {
+        self.elements.borrow().clone()
+    }
+
+    // write writes the list as "[1, "a", [2]]". enclosing are the lists
+    // and maps being written that the list is nested in, see write_element.
+    fn write(&self, f: &mut fmt::Formatter, enclosing: &mut Vec<*const ()>) -> fmt::Result {
+        let id = self as *const LoxList as *const ();
+        if enclosing.contains(&id) {
+            return write!(f, "[...]");
+        }
+        enclosing.push(id);
+        write!(f, "[")?;
+        for (i, element) in self.elements.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, element, enclosing)?;
+        }
+        enclosing.pop();
+        write!(f, "]")
+    }
+}
+
+// write_element writes value as an element of a list or map. Strings are
+// quoted, and a list or map nested in itself is written as "[...]" or
+// "{...}" where it recurs.
+fn write_element(
+    f: &mut fmt::Formatter,
+    value: &Value,
+    enclosing: &mut Vec<*const ()>,
+) -> fmt::Result {
+    match value {
+        Value::List(list) => list.write(f, enclosing),
+        Value::Map(map) => map.write(f, enclosing),
+        Value::String(s) => write!(f, "\"{}\"", s),
+        _ => write!(f, "{}", value),
+    }
+}
+
+// Lists are compared by identity, as for instances.
+impl PartialEq for LoxList {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+impl fmt::Debug for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// MapKey is the key of an entry of a LoxMap. Only nil, booleans, numbers
+// and strings can be keys, as their equality does not depend on identity.
+// Keys are ordered by type in that order, then by value.
+#[derive(Debug, Clone)]
+enum MapKey {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl MapKey {
+    // new returns the key for value, or an error message if value cannot be
+    // a key.
+    fn new(value: &Value) -> Result<Self, Message> {
+        match value {
+            Value::Nil => Ok(MapKey::Nil),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            // 0 and -0 are equal, so they must be the same key.
+            Value::Number(n) if *n == 0.0 => Ok(MapKey::Number(0.0)),
+            Value::Number(n) => Ok(MapKey::Number(*n)),
+            Value::String(s) => Ok(MapKey::String(s.to_string())),
+            _ => Err(messages::INVALID_MAP_KEY.into()),
+        }
+    }
+
+    // rank returns the position of the type of the key in the key order.
+    fn rank(&self) -> u8 {
+        match self {
+            MapKey::Nil => 0,
+            MapKey::Bool(_) => 1,
+            MapKey::Number(_) => 2,
+            MapKey::String(_) => 3,
+        }
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Bool(a), MapKey::Bool(b)) => a.cmp(b),
+            (MapKey::Number(a), MapKey::Number(b)) => a.total_cmp(b),
+            (MapKey::String(a), MapKey::String(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+// LoxMap is a map from keys to values, as created by a map literal. Like
+// lists, maps are mutable and shared by reference. The entries of a map are
+// ordered by key, see MapKey.
+pub struct LoxMap {
+    // entries maps each key to the key as given, and its value.
+    entries: RefCell<BTreeMap<MapKey, (Value, Value)>>,
+}
+
+#[allow(clippy::new_without_default)]
+impl LoxMap {
+    pub fn new() -> Self {
+        LoxMap {
+            entries: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    // get returns the value of key, or None if the map has no entry for
+    // key. The error is the message of a runtime error for a key that
+    // cannot be a map key, as for the other methods.
+    pub fn get(&self, key: &Value) -> Result<Option<Value>, Message> {
+        let key = MapKey::new(key)?;
+        Ok(self
+            .entries
+            .borrow()
+            .get(&key)
+            .map(|(_, value)| value.clone()))
+    }
+
+    // insert sets the value of key to value. The key of an existing entry
+    // is kept, e.g. setting -0 after 0 leaves the key as 0.
+    pub fn insert(&self, key: Value, value: Value) -> Result<(), Message> {
+        let map_key = MapKey::new(&key)?;
+        self.entries
+            .borrow_mut()
+            .entry(map_key)
+            .and_modify(|entry| entry.1 = value.clone())
+            .or_insert((key, value));
+        Ok(())
+    }
+
+    // remove removes the entry of key, returning its value if there was
+    // one.
+    pub fn remove(&self, key: &Value) -> Result<Option<Value>, Message> {
+        let key = MapKey::new(key)?;
+        Ok(self
+            .entries
+            .borrow_mut()
+            .remove(&key)
+            .map(|(_, value)| value))
+    }
+
+    // entries returns a copy of the keys and values of the map, in order.
+    pub fn entries(&self) -> Vec<(Value, Value)> {
+        self.entries.borrow().values().cloned().collect()
+    }
+
+    // write writes the map as "{"a": 1, 2: [3]}", as for LoxList::write.
+    fn write(&self, f: &mut fmt::Formatter, enclosing: &mut Vec<*const ()>) -> fmt::Result {
+        let id = self as *const LoxMap as *const ();
+        if enclosing.contains(&id) {
+            return write!(f, "{{...}}");
+        }
+        enclosing.push(id);
+        write!(f, "{{")?;
+        for (i, (key, value)) in self.entries.borrow().values().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, key, enclosing)?;
+            write!(f, ": ")?;
+            write_element(f, value, enclosing)?;
+        }
+        enclosing.pop();
+        write!(f, "}}")
+    }
+}
+
+// Maps are compared by identity, as for lists.
+impl PartialEq for LoxMap {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+impl fmt::Debug for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Unwind is the reason the execution of a statement stopped early.
+pub enum Unwind {
+    // Return is a return statement unwinding to the enclosing function
+    // call, with the returned value.
+    Return(Value),
+    // Break is a break statement unwinding to the enclosing loop, which
+    // stops.
+    Break,
+    // Continue is a continue statement unwinding to the enclosing loop,
+    // which goes on with its next iteration.
+    Continue,
+    // Error is a runtime error, aborting the program.
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+// Callee is what Interpreter::call calls: a global of the program by name,
+// or a callable value, e.g. one returned by the program.
+pub enum Callee<'a> {
+    Name(&'a str),
+    Value(Value),
+}
+
+impl<'a> From<&'a str> for Callee<'a> {
+    fn from(name: &'a str) -> Self {
+        Callee::Name(name)
+    }
+}
+
+impl From<Value> for Callee<'_> {
+    fn from(value: Value) -> Self {
+        Callee::Value(value)
+    }
+}
+
+// StackFrame is a frame of the call stack of a running program, as
+// inspected with Interpreter::stack, e.g. by a debugger or to log the state
+// of a program on error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StackFrame {
+    // function is the name of the function called, or "script" for the
+    // top level of the program.
+    pub function: String,
+    // call_site is the span of the closing parenthesis of the call, or None
+    // for the top level of the program.
+    pub call_site: Option<Span>,
+    // locals are the local variables visible in the frame, and their
+    // values. Variables of inner scopes come first, and shadow those of
+    // outer scopes, which are not included.
+    pub locals: Vec<(String, Value)>,
+}
+
+// Frame is the interpreter's record of a call in progress.
+struct Frame {
+    function: String,
+    call_site: Span,
+    // caller_environment is the environment current when the call was made,
+    // i.e. the innermost scope of the calling frame.
+    caller_environment: Rc<RefCell<Environment>>,
+}
+
+// Capabilities are the host facilities that the native functions of an
+// Interpreter may use. Natives check them through
+// Interpreter::capabilities.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Capabilities {
+    // clock allows reading the current time.
+    pub clock: bool,
+    // imports allows import statements to read the files they import.
+    pub imports: bool,
+}
+
+impl Capabilities {
+    // all returns the capabilities granting every host facility.
+    pub fn all() -> Self {
+        Capabilities {
+            clock: true,
+            imports: true,
+        }
+    }
+
+    // none returns the capabilities granting no host facility.
+    pub fn none() -> Self {
+        Capabilities {
+            clock: false,
+            imports: false,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::all()
+    }
+}
+
+// DEFAULT_MAX_CALL_DEPTH is the default limit on the number of calls in
+// progress, see InterpreterBuilder::max_call_depth.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 128;
+
+// InterpreterBuilder configures and creates an Interpreter. Each option
+// defaults to that of Interpreter::new, so only the options that differ need
+// to be given.
+pub struct InterpreterBuilder {
+    output: Box<dyn Write>,
+    dialect: Dialect,
+    max_steps: Option<u64>,
+    max_call_depth: usize,
+    capabilities: Capabilities,
+    deterministic: bool,
+    record_trace: bool,
+    stdlib: Stdlib,
+    module_dir: PathBuf,
+    scanner: Scanner<'static>,
+}
+
+#[allow(clippy::new_without_default)]
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        InterpreterBuilder {
+            output: Box::new(io::stdout()),
+            dialect: Dialect::Standard,
+            max_steps: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            capabilities: Capabilities::default(),
+            deterministic: false,
+            record_trace: false,
+            stdlib: Stdlib::default(),
+            module_dir: PathBuf::new(),
+            scanner: Scanner::new(),
+        }
+    }
+
+    // writer makes print statements write to writer instead of stdout.
+    pub fn writer<W: Write + 'static>(mut self, writer: W) -> Self {
+        self.output = Box::new(writer);
+        self
+    }
+
+    // dialect makes the interpreter run programs of dialect, as parsed by a
+    // Parser of the same dialect.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // max_steps limits the number of statements the interpreter executes.
+    // The limit is checked at each loop iteration and call, raising a
+    // runtime error once it is exceeded, so that runaway programs stop.
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    // max_call_depth limits the number of calls in progress. A call beyond
+    // the limit raises a "Stack overflow." runtime error, rather than
+    // overflowing the stack of the thread running the interpreter. Defaults
+    // to DEFAULT_MAX_CALL_DEPTH, which fits the stack of the main thread.
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    // capabilities sets the host facilities the natives may use.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    // deterministic makes the natives avoid host state that varies between
+    // runs, such as the current time, so that running a program always
+    // gives the same result.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    // record_trace makes the interpreter record a Trace of the state changes
+    // of the programs it runs, see Interpreter::trace.
+    pub fn record_trace(mut self, record_trace: bool) -> Self {
+        self.record_trace = record_trace;
+        self
+    }
+
+    // stdlib selects the groups of standard library natives to define. All
+    // groups are defined by default.
+    pub fn stdlib(mut self, stdlib: Stdlib) -> Self {
+        self.stdlib = stdlib;
+        self
+    }
+
+    // module_dir sets the directory that the paths imported by the programs
+    // run are relative to, e.g. that of the script run. The paths imported
+    // by a module are relative to the directory of the module. Defaults to
+    // the current directory.
+    pub fn module_dir<P: Into<PathBuf>>(mut self, module_dir: P) -> Self {
+        self.module_dir = module_dir.into();
+        self
+    }
+
+    // scanner sets the Scanner that scans the modules imported, which
+    // should be configured as the one scanning the programs run, e.g. to
+    // allow unicode identifiers. Defaults to Scanner::new.
+    pub fn scanner(mut self, scanner: Scanner<'static>) -> Self {
+        self.scanner = scanner;
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            output: self.output,
+            frames: Vec::new(),
+            error_stack: None,
+            steps: 0,
+            max_steps: self.max_steps,
+            max_call_depth: self.max_call_depth,
+            capabilities: self.capabilities,
+            deterministic: self.deterministic,
+            dialect: self.dialect,
+            module_dir: self.module_dir,
+            scanner: self.scanner,
+            modules: HashMap::new(),
+            importing: Vec::new(),
+            trace: if self.record_trace {
+                Some(Trace::new())
+            } else {
+                None
+            },
+        };
+        interpreter.define_clock();
+        for native in stdlib::natives(self.stdlib) {
+            interpreter.define_native(native);
+        }
+        if self.dialect == Dialect::PrintFunctions {
+            interpreter.define_print_functions();
+        }
+        interpreter
+    }
+}
+
+// Interpreter executes a program by walking its abstract syntax tree.
+pub struct Interpreter {
+    // globals is the outermost environment, holding the variables that the
+    // Resolver did not resolve to a local scope.
+    globals: Rc<RefCell<Environment>>,
+
+    // environment is the environment of the scope currently executing.
+    environment: Rc<RefCell<Environment>>,
+
+    // output is where print statements write to.
+    output: Box<dyn Write>,
+
+    // frames are the calls in progress, innermost last.
+    frames: Vec<Frame>,
+
+    // error_stack is the stack at the point the last runtime error was
+    // raised, if any.
+    error_stack: Option<Vec<StackFrame>>,
+
+    // steps is the number of statements executed so far.
+    steps: u64,
+
+    // max_steps is the optional limit on steps, see
+    // InterpreterBuilder::max_steps.
+    max_steps: Option<u64>,
+
+    // max_call_depth is the limit on the number of frames, see
+    // InterpreterBuilder::max_call_depth.
+    max_call_depth: usize,
+
+    capabilities: Capabilities,
+    deterministic: bool,
+    dialect: Dialect,
+
+    // module_dir is the directory paths imported by programs are relative
+    // to, see InterpreterBuilder::module_dir.
+    module_dir: PathBuf,
+
+    // scanner scans the modules imported, see InterpreterBuilder::scanner.
+    scanner: Scanner<'static>,
+
+    // modules are the modules imported so far, by their canonical path, so
+    // that each module is only loaded once.
+    modules: HashMap<PathBuf, Rc<LoxModule>>,
+
+    // importing is the canonical path of each module being loaded, and the
+    // path it was imported as, innermost last.
+    importing: Vec<(PathBuf, String)>,
+
+    // trace is the recorded Trace, if recording was enabled with
+    // InterpreterBuilder::record_trace.
+    trace: Option<Trace>,
+}
+
+#[allow(clippy::new_without_default)]
+impl Interpreter {
+    // new creates a new interpreter, printing to stdout. Use builder to
+    // configure the interpreter instead.
+    pub fn new() -> Self {
+        InterpreterBuilder::new().build()
+    }
+
+    // builder returns an InterpreterBuilder, to configure a new interpreter.
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::new()
+    }
+
+    // capabilities returns the host facilities the natives may use.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    // is_deterministic returns true if the natives must avoid host state
+    // that varies between runs.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    // set_module_dir sets the directory that the paths imported by the
+    // programs run afterwards are relative to, see
+    // InterpreterBuilder::module_dir.
+    pub fn set_module_dir<P: Into<PathBuf>>(&mut self, module_dir: P) {
+        self.module_dir = module_dir.into();
+    }
+
+    // trace returns the state changes recorded so far, across all programs
+    // run, or None if the interpreter is not recording.
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    // record records the event returned by event in the trace, if the
+    // interpreter is recording. The event is only created when recording.
+    fn record<F: FnOnce() -> Event>(&mut self, event: F) {
+        if let Some(ref mut trace) = self.trace {
+            trace.push(event());
+        }
+    }
+
+    // define defines name as value in the current environment.
+    fn define(&mut self, name: &Token, value: Value) {
+        self.define_binding(name, value, false);
+    }
+
+    // define_binding defines name as value in the current environment, as a
+    // constant if constant is true.
+    fn define_binding(&mut self, name: &Token, value: Value, constant: bool) {
+        let global = self.environment.borrow().enclosing().is_none();
+        self.record(|| Event::Define {
+            line: name.span.start.line,
+            global,
+            name: name.lexeme.to_string(),
+            value: trace::describe(&value),
+        });
+        let mut environment = self.environment.borrow_mut();
+        if constant {
+            environment.define_constant(&name.lexeme, value);
+        } else {
+            environment.define(&name.lexeme, value);
+        }
+    }
+
+    // set_field sets the field name of instance to value.
+    fn set_field(&mut self, instance: &LoxInstance, name: &Token, value: Value) {
+        self.record(|| Event::SetField {
+            line: name.span.start.line,
+            object: format!("{:?}", instance),
+            name: name.lexeme.to_string(),
+            value: trace::describe(&value),
+        });
+        instance.set(name, value);
+    }
+
+    // define_clock defines the clock native, returning the number of
+    // seconds since the Unix epoch. A deterministic interpreter's clock
+    // always returns 0, and calling clock without the clock capability is a
+    // runtime error.
+    fn define_clock(&mut self) {
+        self.define_native(NativeFunction::new("clock", 0, |interpreter, _| {
+            if !interpreter.capabilities().clock {
+                return Err(messages::CLOCK_NOT_GRANTED.into());
+            }
+            if interpreter.is_deterministic() {
+                return Ok(Value::Number(0.0));
+            }
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| Message::from(messages::CLOCK_BEFORE_EPOCH))?;
+            Ok(Value::Number(now.as_secs_f64()))
+        }));
+    }
+
+    // define_print_functions defines the print and println natives of the
+    // PrintFunctions dialect.
+    fn define_print_functions(&mut self) {
+        self.define_native(NativeFunction::new("print", 1, |interpreter, arguments| {
+            write!(interpreter.output, "{}", arguments[0]).expect("failed to write output");
+            Ok(Value::Nil)
+        }));
+        // println without an argument prints an empty line.
+        self.define_native(
+            NativeFunction::new("println", 1, |interpreter, arguments| {
+                match arguments.first() {
+                    Some(value) => writeln!(interpreter.output, "{}", value),
+                    None => writeln!(interpreter.output),
+                }
+                .expect("failed to write output");
+                Ok(Value::Nil)
+            })
+            .with_min_arity(0),
+        );
+    }
+
+    // define_native defines native as a global function, replacing any
+    // global of the same name. Embedders use it to extend the language with
+    // their own natives.
+    pub fn define_native(&mut self, native: NativeFunction) {
+        let name = native.name().to_owned();
+        self.globals
+            .borrow_mut()
+            .define(&name, Value::NativeFn(Rc::new(native)));
+    }
+
+    // interpret executes the statements of program in order, stopping at
+    // the first runtime error. program is expected to have been resolved
+    // by the Resolver.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "execute", skip_all, err(Display))
+    )]
+    pub fn interpret(&mut self, program: &[Stmt]) -> Result<(), RuntimeError> {
+        self.error_stack = None;
+        for stmt in program {
+            match self.execute(stmt) {
+                Ok(()) => {}
+                // A return outside of any function ends the program.
+                Err(Unwind::Return(_)) => return Ok(()),
+                Err(Unwind::Break) | Err(Unwind::Continue) => {
+                    unreachable!("loop control outside of a loop")
+                }
+                Err(Unwind::Error(err)) => {
+                    self.record_error_stack();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // stack returns the current call stack, outermost frame first. The
+    // first frame is always the top level of the program.
+    pub fn stack(&self) -> Vec<StackFrame> {
+        // The locals of a frame are in the environment that was current when
+        // it made the next call, or the current one for the innermost frame.
+        let environments = self
+            .frames
+            .iter()
+            .map(|frame| &frame.caller_environment)
+            .chain(std::iter::once(&self.environment));
+        let calls = self
+            .frames
+            .iter()
+            .map(|frame| (frame.function.clone(), Some(frame.call_site)));
+        std::iter::once((String::from("script"), None))
+            .chain(calls)
+            .zip(environments)
+            .map(|((function, call_site), environment)| StackFrame {
+                function,
+                call_site,
+                locals: self.locals(environment),
+            })
+            .collect()
+    }
+
+    // error_stack returns the call stack at the point where the last runtime
+    // error was raised, or None if the last program run raised no error.
+    // Unlike stack, this is still available after the error has unwound the
+    // stack, for post-mortem inspection.
+    pub fn error_stack(&self) -> Option<&[StackFrame]> {
+        self.error_stack.as_deref()
+    }
+
+    // record_error_stack records the current stack as the error stack, unless
+    // one has already been recorded for the error being raised.
+    fn record_error_stack(&mut self) {
+        if self.error_stack.is_none() {
+            self.error_stack = Some(self.stack());
+        }
+    }
+
+    // locals returns the bindings of environment and its enclosing
+    // environments, up to but not including the globals.
+    fn locals(&self, environment: &Rc<RefCell<Environment>>) -> Vec<(String, Value)> {
+        let mut locals: Vec<(String, Value)> = Vec::new();
+        let mut environment = Rc::clone(environment);
+        // The outermost environment holds the globals of the frame, which
+        // are not locals.
+        while environment.borrow().enclosing().is_some() {
+            for (name, value) in environment.borrow().bindings() {
+                if !locals.iter().any(|(local, _)| *local == name) {
+                    locals.push((name, value));
+                }
+            }
+            let enclosing = match environment.borrow().enclosing() {
+                Some(enclosing) => enclosing,
+                None => break,
+            };
+            environment = enclosing;
+        }
+        locals
+    }
+
+    // call calls callee with arguments, returning the result of the call.
+    // This allows a host to call functions, e.g. event handlers, defined by
+    // a program that has already been run. Errors that are not raised by
+    // the function itself, such as calling an undefined name, are reported
+    // at line 0, as there is no call in the source to report them at.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "execute", skip_all, err(Display))
+    )]
+    pub fn call<'a, C: Into<Callee<'a>>>(
+        &mut self,
+        callee: C,
+        arguments: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        self.error_stack = None;
+        let (name, callee) = match callee.into() {
+            Callee::Name(name) => {
+                let value = self.globals.borrow().get(&Self::host_token(name))?;
+                (name.to_owned(), value)
+            }
+            Callee::Value(value) => (value.to_string(), value),
+        };
+        self.call_value(&Self::host_token(&name), callee, arguments.to_vec())
+    }
+
+    // host_token returns a token for name, for errors of calls made by the
+    // host rather than the program.
+    fn host_token(name: &str) -> Token {
+        let position = Position::new(0, 0, 0);
+        Token {
+            token_type: TokenType::Identifier,
+            lexeme: Lexeme::from(name),
+            symbol: None,
+            span: Span::new(position, position),
+            literal: None,
+            error: false,
+        }
+    }
+
+    // call_value calls callee with arguments, reporting errors at paren.
+    fn call_value(
+        &mut self,
+        paren: &Token,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let (name, function): (String, Rc<dyn Callable>) = match callee {
+            Value::Function(function) => (function.name().to_owned(), function),
+            Value::Class(class) => (class.name().to_owned(), class),
+            // Native functions report errors as a message, raised here at the
+            // call.
+            Value::NativeFn(native) => {
+                Self::check_native_arity(paren, &native, arguments.len())?;
+                return native
+                    .call(self, arguments)
+                    .map_err(|msg| RuntimeError::new(paren, msg));
+            }
+            _ => {
+                return Err(RuntimeError::new(paren, messages::NOT_CALLABLE));
+            }
+        };
+        Self::check_arity(paren, function.arity(), arguments.len())?;
+        self.check_steps(paren)?;
+        if self.frames.len() >= self.max_call_depth {
+            return Err(RuntimeError::new(paren, messages::STACK_OVERFLOW));
+        }
+        self.record(|| Event::Call {
+            line: paren.span.start.line,
+            function: name.clone(),
+            arguments: arguments.iter().map(trace::describe).collect(),
+        });
+        self.frames.push(Frame {
+            function: name,
+            call_site: paren.span,
+            caller_environment: Rc::clone(&self.environment),
+        });
+        let result = function.call(self, arguments);
+        let frame = self.frames.pop().expect("no frame of the call");
+        // The call also ends when an error unwinds out of it, which a try
+        // statement outside of the call may catch.
+        match result {
+            Ok(ref value) => self.record(|| Event::Return {
+                line: paren.span.start.line,
+                function: frame.function,
+                value: trace::describe(value),
+            }),
+            Err(_) => self.record(|| Event::Unwind {
+                line: paren.span.start.line,
+                function: frame.function,
+            }),
+        }
+        result
+    }
+
+    // get_property returns the property name of object, an instance, class
+    // or module. A getter is called, returning its value.
+    fn get_property(&mut self, object: Value, name: &Token) -> Result<Value, RuntimeError> {
+        let value = match object {
+            Value::Instance(instance) => LoxInstance::get(&instance, name)?,
+            Value::Class(class) => LoxClass::get(&class, name)?,
+            Value::Module(module) => return module.get(name),
+            _ => {
+                return Err(RuntimeError::new(name, messages::NOT_AN_OBJECT));
+            }
+        };
+        self.call_getter(name, value)
+    }
+
+    // call_getter returns the value of calling method, accessed as the
+    // property name, if it is a getter, or else method itself.
+    fn call_getter(&mut self, name: &Token, method: Value) -> Result<Value, RuntimeError> {
+        match method {
+            Value::Function(ref function) if function.is_getter() => {
+                self.call_value(name, method, Vec::new())
+            }
+            _ => Ok(method),
+        }
+    }
+
+    // load_prelude runs prelude in the interpreter, defining its functions,
+    // classes and variables as globals of the programs run afterwards.
+    // Loading a prelude only runs it, as it is already compiled.
+    pub fn load_prelude(&mut self, prelude: &Prelude) -> Result<(), RuntimeError> {
+        self.interpret(prelude.program())
+    }
+
+    // execute_block executes statements in environment, restoring the
+    // current environment afterwards, also when unwinding.
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        // The innermost scope of an error is only known before unwinding.
+        if let Err(Unwind::Error(_)) = result {
+            self.record_error_stack();
+        }
+        self.environment = previous;
+        result
+    }
+
+    // import returns the module named name at the path of the string token
+    // path, loading the module unless it has already been imported. The
+    // program of a module runs with globals of its own, where only the
+    // native functions are defined.
+    fn import(&mut self, path: &Token, name: &Token) -> Result<Rc<LoxModule>, RuntimeError> {
+        if !self.capabilities.imports {
+            return Err(RuntimeError::new(path, messages::IMPORTS_NOT_GRANTED));
+        }
+        let written = match path.literal {
+            Some(Literal::String(ref written)) => written.to_string(),
+            _ => unreachable!("import of a non-string path"),
+        };
+        let dir = match self.importing.last() {
+            Some((importer, _)) => importer.parent().unwrap_or(Path::new("")).to_path_buf(),
+            None => self.module_dir.clone(),
+        };
+        let cannot_import = |err: io::Error| {
+            RuntimeError::new(path, messages::CANNOT_IMPORT.with(&[&written, &err]))
+        };
+        let file = dir.join(&written).canonicalize().map_err(cannot_import)?;
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(Rc::clone(module));
+        }
+        if let Some(start) = self
+            .importing
+            .iter()
+            .position(|(importer, _)| *importer == file)
+        {
+            let cycle: Vec<String> = self.importing[start..]
+                .iter()
+                .map(|(_, written)| written)
+                .chain(std::iter::once(&written))
+                .map(|written| format!("\"{}\"", written))
+                .collect();
+            let msg = messages::IMPORT_CYCLE.with(&[&cycle.join(" -> ")]);
+            return Err(RuntimeError::new(path, msg));
+        }
+        let source = fs::read_to_string(&file).map_err(cannot_import)?;
+        let program = self.compile_module(&source).map_err(|errors| {
+            let errors: Vec<Message> = errors.iter().map(LoxError::to_message).collect();
+            let msg =
+                messages::ERROR_IN_MODULE.with_args(vec![written.clone().into(), errors.into()]);
+            RuntimeError::new(path, msg)
+        })?;
+
+        let mut globals = Environment::new();
+        for (name, value) in self.globals.borrow().bindings() {
+            if let Value::NativeFn(_) = value {
+                globals.define(&name, value);
+            }
+        }
+        let globals = Rc::new(RefCell::new(globals));
+        self.importing.push((file.clone(), written));
+        let previous = std::mem::replace(&mut self.environment, Rc::clone(&globals));
+        let result = program.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = previous;
+        self.importing.pop();
+        match result {
+            Ok(()) | Err(Unwind::Return(_)) => {}
+            Err(Unwind::Error(err)) => return Err(err),
+            Err(Unwind::Break) | Err(Unwind::Continue) => {
+                unreachable!("loop control outside of a loop")
+            }
+        }
+        let module = Rc::new(LoxModule::new(&name.lexeme, globals));
+        self.modules.insert(file, Rc::clone(&module));
+        Ok(module)
+    }
+
+    // compile_module scans, parses and resolves the source of a module.
+    fn compile_module(&self, source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let (tokens, errors) = self.scanner.scan_tokens(source);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let program = Parser::new()
+            .with_dialect(self.dialect)
+            .with_unicode_identifiers(self.scanner.unicode_identifiers())
+            .parse(tokens)?;
+        Resolver::new().resolve(&program)?;
+        Ok(program)
+    }
+
+    // steps returns the number of statements executed by the interpreter
+    // so far, across all programs it has run.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // check_steps raises a runtime error at token if the interpreter has
+    // executed more than its maximum number of steps.
+    fn check_steps(&self, token: &Token) -> Result<(), RuntimeError> {
+        match self.max_steps {
+            Some(max_steps) if self.steps > max_steps => Err(RuntimeError::uncatchable(
+                token,
+                messages::STEP_LIMIT_EXCEEDED,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.steps += 1;
+        stmt.accept(self)
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        expr.accept(self)
+    }
+
+    // evaluate_in evaluates expr in environment, restoring the current
+    // environment afterwards.
+    fn evaluate_in(
+        &mut self,
+        expr: &Expr,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Value, RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.evaluate(expr);
+        self.environment = previous;
+        result
+    }
+
+    // look_up_variable returns the value of the variable name, resolved to
+    // depth.
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RuntimeError> {
+        match depth {
+            Some(depth) => self.environment.borrow().get_at(depth, name),
+            // The globals are those of the program or module the code is
+            // part of.
+            None => Environment::globals(&self.environment).borrow().get(name),
+        }
+    }
+
+    // assign_variable assigns value to the variable name, resolved to depth
+    // as for look_up_variable.
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        depth: Option<usize>,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        match depth {
+            Some(depth) => self
+                .environment
+                .borrow_mut()
+                .assign_at(depth, name, value.clone())?,
+            None => Environment::globals(&self.environment)
+                .borrow_mut()
+                .assign(name, value.clone())?,
+        }
+        self.record(|| Event::Assign {
+            line: name.span.start.line,
+            global: depth.is_none(),
+            name: name.lexeme.to_string(),
+            value: trace::describe(&value),
+        });
+        Ok(())
+    }
+
+    // check_arity returns an error at paren if a call passes a different
+    // number of arguments than the arity of the function called.
+    fn check_arity(paren: &Token, arity: usize, arguments: usize) -> Result<(), RuntimeError> {
+        if arguments == arity {
+            return Ok(());
+        }
+        let msg = messages::WRONG_ARITY.with(&[&arity, &arguments]);
+        Err(RuntimeError::new(paren, msg))
+    }
+
+    // check_native_arity is as check_arity, but for a native function, which
+    // may have optional arguments.
+    fn check_native_arity(
+        paren: &Token,
+        native: &NativeFunction,
+        arguments: usize,
+    ) -> Result<(), RuntimeError> {
+        if native.min_arity() == native.arity() {
+            return Self::check_arity(paren, native.arity(), arguments);
+        }
+        if (native.min_arity()..=native.arity()).contains(&arguments) {
+            return Ok(());
+        }
+        let msg =
+            messages::WRONG_ARITY_RANGE.with(&[&native.min_arity(), &native.arity(), &arguments]);
+        Err(RuntimeError::new(paren, msg))
+    }
+
+    fn number_operand(operator: &Token, operand: Value) -> Result<f64, RuntimeError> {
+        match operand {
+            Value::Number(n) => Ok(n),
+            _ => Err(RuntimeError::new(operator, messages::OPERAND_NOT_NUMBER)),
+        }
+    }
+
+    fn number_operands(
+        operator: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Ok((l, r)),
+            _ => Err(RuntimeError::new(operator, messages::OPERANDS_NOT_NUMBERS)),
+        }
+    }
+
+    // get_index returns the element of object at index, where object must
+    // be a list or a map. Errors are reported at bracket.
+    fn get_index(object: &Value, index: &Value, bracket: &Token) -> Result<Value, RuntimeError> {
+        match object {
+            Value::List(list) => {
+                let index = Self::list_index(list, index, bracket)?;
+                Ok(list.get(index).expect("list index checked"))
+            }
+            Value::Map(map) => match map.get(index) {
+                Ok(Some(value)) => Ok(value),
+                Ok(None) => {
+                    let msg = messages::UNDEFINED_KEY.with(&[&trace::describe(index)]);
+                    Err(RuntimeError::new(bracket, msg))
+                }
+                Err(msg) => Err(RuntimeError::new(bracket, msg)),
+            },
+            _ => Err(RuntimeError::new(bracket, messages::NOT_INDEXABLE)),
+        }
+    }
+
+    // set_index sets the element of object at index to value, as for
+    // get_index. Setting a map element adds an entry for index if needed.
+    fn set_index(
+        object: &Value,
+        index: Value,
+        value: Value,
+        bracket: &Token,
+    ) -> Result<(), RuntimeError> {
+        match object {
+            Value::List(list) => {
+                let index = Self::list_index(list, &index, bracket)?;
+                list.set(index, value);
+                Ok(())
+            }
+            Value::Map(map) => map
+                .insert(index, value)
+                .map_err(|msg| RuntimeError::new(bracket, msg)),
+            _ => Err(RuntimeError::new(bracket, messages::NOT_INDEXABLE)),
+        }
+    }
+
+    // list_index returns index as an index of list, which must be an integer
+    // in range.
+    fn list_index(list: &LoxList, index: &Value, bracket: &Token) -> Result<usize, RuntimeError> {
+        let index = match *index {
+            Value::Number(n) if n.fract() == 0.0 => n,
+            _ => return Err(RuntimeError::new(bracket, messages::LIST_INDEX_NOT_INTEGER)),
+        };
+        if index < 0.0 || index >= list.len() as f64 {
+            return Err(RuntimeError::new(
+                bracket,
+                messages::LIST_INDEX_OUT_OF_RANGE,
+            ));
+        }
+        Ok(index as usize)
+    }
+}
+
+impl Visitor for Interpreter {
+    type Result = Result<Value, RuntimeError>;
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(&expr.value)?;
+        self.assign_variable(&expr.name, expr.depth.get(), value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Result<Value, RuntimeError> {
+        use TokenType::*;
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+        let op = &expr.operator;
+        let value = match op.token_type {
+            Plus => match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                (Value::String(l), Value::String(r)) => Value::String(l.concat(&r)),
+                _ => {
+                    return Err(RuntimeError::new(op, messages::OPERANDS_NOT_ADDABLE));
+                }
+            },
+            Minus => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Number(l - r)
+            }
+            Star => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Number(l * r)
+            }
+            Slash => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Number(l / r)
+            }
+            Percent => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Number(l % r)
+            }
+            StarStar => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Number(l.powf(r))
+            }
+            Greater => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Bool(l > r)
+            }
+            GreaterEqual => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Bool(l >= r)
+            }
+            Less => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Bool(l < r)
+            }
+            LessEqual => {
+                let (l, r) = Self::number_operands(op, left, right)?;
+                Value::Bool(l <= r)
+            }
+            EqualEqual => Value::Bool(left == right),
+            BangEqual => Value::Bool(left != right),
+            Comma => right,
+            _ => unreachable!("invalid binary operator {:?}", op.token_type),
+        };
+        Ok(value)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(&expr.callee)?;
+        let mut arguments = Vec::with_capacity(expr.arguments.len());
+        for argument in &expr.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+        self.call_value(&expr.paren, callee, arguments)
+    }
+
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) -> Result<Value, RuntimeError> {
+        let function = LoxFunction::new(
+            Rc::clone(&expr.function),
+            Rc::clone(&self.environment),
+            false,
+        );
+        Ok(Value::Function(Rc::new(function)))
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        self.get_property(object, &expr.name)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<Value, RuntimeError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_increment_expr(&mut self, expr: &IncrementExpr) -> Result<Value, RuntimeError> {
+        let delta = match expr.operator.token_type {
+            TokenType::PlusPlus => 1.0,
+            TokenType::MinusMinus => -1.0,
+            _ => unreachable!("invalid increment operator {:?}", expr.operator.token_type),
+        };
+        let operator = &expr.operator;
+        let (old, new) = match *expr.target {
+            Expr::Variable(ref variable) => {
+                let depth = variable.depth.get();
+                let value = self.look_up_variable(&variable.name, depth)?;
+                let old = Self::number_operand(operator, value)?;
+                self.assign_variable(&variable.name, depth, Value::Number(old + delta))?;
+                (old, old + delta)
+            }
+            Expr::Get(ref get) => {
+                // The object is evaluated once, to both get and set the
+                // property.
+                let instance = match self.evaluate(&get.object)? {
+                    Value::Instance(instance) => instance,
+                    _ => {
+                        return Err(RuntimeError::new(&get.name, messages::NOT_AN_INSTANCE));
+                    }
+                };
+                let value = self.get_property(Value::Instance(Rc::clone(&instance)), &get.name)?;
+                let old = Self::number_operand(operator, value)?;
+                self.set_field(&instance, &get.name, Value::Number(old + delta));
+                (old, old + delta)
+            }
+            Expr::Index(ref index) => {
+                // As for properties, the object and index are evaluated
+                // once.
+                let object = self.evaluate(&index.object)?;
+                let i = self.evaluate(&index.index)?;
+                let value = Self::get_index(&object, &i, &index.bracket)?;
+                let old = Self::number_operand(operator, value)?;
+                Self::set_index(&object, i, Value::Number(old + delta), &index.bracket)?;
+                (old, old + delta)
+            }
+            _ => {
+                let msg = messages::INVALID_INCREMENT_TARGET.with(&[&operator.lexeme]);
+                return Err(RuntimeError::new(operator, msg));
+            }
+        };
+        Ok(Value::Number(if expr.prefix { new } else { old }))
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        Self::get_index(&object, &index, &expr.bracket)
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> Result<Value, RuntimeError> {
+        let mut elements = Vec::with_capacity(expr.elements.len());
+        for element in &expr.elements {
+            elements.push(self.evaluate(element)?);
+        }
+        Ok(Value::List(Rc::new(LoxList::new(elements))))
+    }
+
+    fn visit_map_expr(&mut self, expr: &MapExpr) -> Result<Value, RuntimeError> {
+        let map = LoxMap::new();
+        for (key, value) in &expr.entries {
+            let key_value = self.evaluate(key)?;
+            let value = self.evaluate(value)?;
+            map.insert(key_value, value)
+                .map_err(|msg| RuntimeError::new(&expr.brace, msg))?;
+        }
+        Ok(Value::Map(Rc::new(map)))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Result<Value, RuntimeError> {
+        Ok(Value::from(expr.value.clone()))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(&expr.left)?;
+        let short_circuit = match expr.operator.token_type {
+            TokenType::Or => left.is_truthy(),
+            _ => !left.is_truthy(),
+        };
+        if short_circuit {
+            return Ok(left);
+        }
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> Result<Value, RuntimeError> {
+        let instance = match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => instance,
+            _ => {
+                return Err(RuntimeError::new(&expr.name, messages::NOT_AN_INSTANCE));
+            }
+        };
+        let value = self.evaluate(&expr.value)?;
+        self.set_field(&instance, &expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_set_index_expr(&mut self, expr: &SetIndexExpr) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+        Self::set_index(&object, index, value.clone(), &expr.bracket)?;
+        Ok(value)
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> Result<Value, RuntimeError> {
+        let depth = match expr.depth.get() {
+            Some(depth) => depth,
+            // The Resolver rejects super outside of a subclass, so this is
+            // only reachable for programs that were not resolved.
+            None => {
+                let msg = messages::SUPER_OUTSIDE_SUBCLASS;
+                return Err(RuntimeError::new(&expr.keyword, msg));
+            }
+        };
+        // "this" is always bound in the scope just inside the one binding
+        // "super".
+        let (superclass, this) = {
+            let environment = self.environment.borrow();
+            let superclass = match environment.lookup_at(depth, "super") {
+                Some(Value::Class(class)) => class,
+                _ => unreachable!("'super' resolved to a non-class"),
+            };
+            let this = environment.lookup_at(depth - 1, "this");
+            (superclass, this.expect("'super' bound without 'this'"))
+        };
+        let name = &expr.method;
+        // super.name is the method of the superclass, bound to this, so
+        // that it can be called later like any other method value. In a
+        // class method, this is the class, and super.name a class method of
+        // the superclass. Fields belong to the instance rather than to a
+        // class, so otherwise super.name is the field of this.
+        let method = match this {
+            Value::Class(_) => superclass.find_class_method(&name.lexeme),
+            _ => superclass.find_method(&name.lexeme),
+        };
+        if let Some(method) = method {
+            let method = Value::Function(Rc::new(method.bind(this)));
+            return self.call_getter(name, method);
+        }
+        if let Value::Instance(ref instance) = this {
+            if let Some(value) = instance.fields.borrow().get(name.lexeme.as_str()) {
+                return Ok(value.clone());
+            }
+        }
+        let msg = messages::UNDEFINED_PROPERTY.with(&[&name.lexeme]);
+        Err(RuntimeError::new(name, msg))
+    }
+
+    fn visit_this_expr(&mut self, expr: &ThisExpr) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.keyword, expr.depth.get())
+    }
+
+    fn visit_throw_expr(&mut self, expr: &ThrowExpr) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(&expr.value)?;
+        Err(RuntimeError::throw(&expr.keyword, value))
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(&expr.expression)?;
+        match expr.operator.token_type {
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Minus => {
+                let n = Self::number_operand(&expr.operator, right)?;
+                Ok(Value::Number(-n))
+            }
+            // The conversion of an interpolated value to a string.
+            TokenType::Interpolation => Ok(Value::String(right.to_string().as_str().into())),
+            _ => unreachable!("invalid unary operator {:?}", expr.operator.token_type),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.name, expr.depth.get())
+    }
+}
+
+impl StmtVisitor for Interpreter {
+    type Result = Result<(), Unwind>;
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<(), Unwind> {
+        let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+        self.execute_block(&stmt.statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> Result<(), Unwind> {
+        let superclass = match stmt.superclass {
+            Some(ref expr) => match self.evaluate(expr)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    let token = match *expr {
+                        Expr::Variable(ref variable) => &variable.name,
+                        _ => &stmt.name,
+                    };
+                    let msg = messages::SUPERCLASS_NOT_CLASS;
+                    return Err(RuntimeError::new(token, msg).into());
+                }
+            },
+            None => None,
+        };
+
+        // The methods of a subclass close over an extra environment, binding
+        // "super" to the superclass.
+        let mut closure = Rc::clone(&self.environment);
+        if let Some(ref superclass) = superclass {
+            let mut environment = Environment::new_enclosed(closure);
+            environment.define("super", Value::Class(Rc::clone(superclass)));
+            closure = Rc::new(RefCell::new(environment));
+        }
+
+        let mut methods = HashMap::new();
+        for method in &stmt.methods {
+            let name = &method.name.lexeme;
+            let is_initializer = name == "init";
+            let closure = Rc::clone(&closure);
+            let function = LoxFunction::new(Rc::clone(method), closure, is_initializer);
+            methods.insert(name.to_string(), Rc::new(function));
+        }
+        let mut class_methods = HashMap::new();
+        for method in &stmt.class_methods {
+            let function = LoxFunction::new(Rc::clone(method), Rc::clone(&closure), false);
+            class_methods.insert(method.name.lexeme.to_string(), Rc::new(function));
+        }
+        let fields = stmt.fields.clone();
+        let class = LoxClass::new(&stmt.name.lexeme, superclass, methods)
+            .with_class_methods(class_methods)
+            .with_fields(fields, Rc::clone(&self.environment));
+        self.define(&stmt.name, Value::Class(Rc::new(class)));
+        Ok(())
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &ContinueStmt) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Result<(), Unwind> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> Result<(), Unwind> {
+        let function = LoxFunction::new(Rc::clone(stmt), Rc::clone(&self.environment), false);
+        self.define(&stmt.name, Value::Function(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<(), Unwind> {
+        if self.evaluate(&stmt.condition)?.is_truthy() {
+            self.execute(&stmt.then_branch)
+        } else if let Some(ref else_branch) = stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<(), Unwind> {
+        let module = self.import(&stmt.path, &stmt.name)?;
+        self.define(&stmt.name, Value::Module(module));
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<(), Unwind> {
+        let value = self.evaluate(&stmt.expression)?;
+        writeln!(self.output, "{}", value).expect("failed to write output");
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<(), Unwind> {
+        let value = match stmt.value {
+            Some(ref value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> Result<(), Unwind> {
+        let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+        let err = match self.execute_block(&stmt.body, Rc::new(RefCell::new(environment))) {
+            Err(Unwind::Error(err)) if err.catchable => err,
+            result => return result,
+        };
+        // The error is handled, so there is no stack left to inspect.
+        self.error_stack = None;
+        let value = match err.thrown {
+            Some(value) => value,
+            None => Value::String(err.message.as_str().into()),
+        };
+        self.record(|| Event::Define {
+            line: stmt.name.span.start.line,
+            global: false,
+            name: stmt.name.lexeme.to_string(),
+            value: trace::describe(&value),
+        });
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+        environment.define(&stmt.name.lexeme, value);
+        self.execute_block(&stmt.handler, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Result<(), Unwind> {
+        let value = match stmt.initializer {
+            Some(ref initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.define_binding(&stmt.name, value, stmt.constant);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Result<(), Unwind> {
+        while self.evaluate(&stmt.condition)?.is_truthy() {
+            self.check_steps(&stmt.keyword)?;
+            match self.execute(&stmt.body) {
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+            if let Some(ref increment) = stmt.increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Catalog;
+    use std::sync::atomic::{self, AtomicUsize};
+    use std::{env, process};
+
+    // SharedOutput is a Write collecting the output of an Interpreter, that
+    // can still be read after the Interpreter took ownership of it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // run interprets source, returning what it printed, or the message of
+    // the runtime error that stopped it.
+    fn run(source: &str) -> Result<String, String> {
+        let (tokens, errors) = Scanner::new().scan_tokens(source);
+        assert_eq!(errors, Vec::new());
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::builder().writer(output.clone()).build();
+        interpreter
+            .interpret(&program)
+            .map_err(|err| err.message.to_string())?;
+        let printed = output.0.borrow().clone();
+        Ok(String::from_utf8(printed).unwrap())
+    }
+
+    // write_files writes the files, given by their path and source, to a new
+    // directory, returning the directory.
+    fn write_files(files: &[(&str, &str)]) -> PathBuf {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let run = RUNS.fetch_add(1, atomic::Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("loxrust-{}-{}", process::id(), run));
+        for (path, source) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    // run_with_files is as run, but with the files written to a new
+    // directory that the program imports from, see write_files.
+    fn run_with_files(source: &str, files: &[(&str, &str)]) -> Result<String, String> {
+        let dir = write_files(files);
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::builder()
+            .writer(output.clone())
+            .module_dir(&dir)
+            .build();
+        let result = interpreter.interpret(&program);
+        fs::remove_dir_all(&dir).unwrap();
+        result.map_err(|err| err.message.to_string())?;
+        let printed = output.0.borrow().clone();
+        Ok(String::from_utf8(printed).unwrap())
+    }
+
+    #[test]
+    fn test_imports() {
+        let files = [
+            (
+                "lib/counter.lox",
+                "import \"log.lox\";
+                 var count = 0;
+                 fun next() { count++; return count; }
+                 log.info(\"loaded\");",
+            ),
+            ("lib/log.lox", "fun info(msg) { print \"info: \" + msg; }"),
+        ];
+        let source = "
+            import \"lib/counter.lox\";
+            import \"lib/counter.lox\" as again;
+            var count = 10;
+            counter.next();
+            print again.next();
+            print count;
+            print counter;
+            print counter.count;";
+        let expected = "info: loaded\n2\n10\n<module counter>\n2\n";
+        assert_eq!(run_with_files(source, &files), Ok(expected.to_owned()));
+        let msg = "Undefined property 'info' of module 'counter'.";
+        let source = "import \"lib/counter.lox\"; counter.info;";
+        assert_eq!(run_with_files(source, &files), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let files = [
+            ("a.lox", "import \"b.lox\";"),
+            ("b.lox", "import \"a.lox\";"),
+            ("bad.lox", "var;"),
+        ];
+        let msg = "Import cycle: \"a.lox\" -> \"b.lox\" -> \"a.lox\".";
+        assert_eq!(
+            run_with_files("import \"a.lox\";", &files),
+            Err(msg.to_owned())
+        );
+        let msg = "Error in module \"bad.lox\":\n[line 1] Error at ';': Expect variable name.";
+        assert_eq!(
+            run_with_files("import \"bad.lox\";", &files),
+            Err(msg.to_owned())
+        );
+        let err = run_with_files("import \"missing.lox\";", &files).unwrap_err();
+        assert!(err.starts_with("Cannot import \"missing.lox\": "));
+
+        let (tokens, _) = Scanner::new().scan_tokens("import \"a.lox\";");
+        let program = Parser::new().parse(tokens).unwrap();
+        let mut interpreter = Interpreter::builder()
+            .capabilities(Capabilities::none())
+            .build();
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(err.message, "The imports capability is not granted.");
+    }
+
+    #[test]
+    fn test_import_uses_scanner() {
+        let dir = write_files(&[("unicode.lox", "var größe = 1;"), ("bad.lox", "var;")]);
+        let (tokens, _) = Scanner::new().scan_tokens("import \"unicode.lox\"; import \"bad.lox\";");
+        let program = Parser::new().parse(tokens).unwrap();
+        let mut interpreter = Interpreter::builder()
+            .writer(io::sink())
+            .module_dir(&dir)
+            .scanner(Scanner::new().with_unicode_identifiers(true))
+            .build();
+        let err = interpreter.interpret(&program).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        // The errors of the module are localized with the error importing it.
+        let mut catalog = Catalog::new();
+        catalog.insert("E013", "Erreur dans le module \"{0}\" :\n{1}");
+        catalog.insert("P021", "Nom de variable attendu.");
+        assert_eq!(
+            err.localize(&catalog),
+            "Erreur dans le module \"bad.lox\" :\n\
+             [line 1] Error at ';': Nom de variable attendu.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn test_print_functions_dialect() {
+        let source =
+            "print(1); print(\"a\"); println(nil); println(); println(print); print(println);";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let parser = Parser::new().with_dialect(Dialect::PrintFunctions);
+        let program = parser.parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::builder()
+            .writer(output.clone())
+            .dialect(Dialect::PrintFunctions)
+            .build();
+        interpreter.interpret(&program).unwrap();
+        let printed = output.0.borrow().clone();
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "1anil\n\n<native fn>\n<native fn>"
+        );
+
+        let (tokens, _) = Scanner::new().scan_tokens("print();");
+        let program = parser.parse(tokens).unwrap();
+        let mut interpreter = Interpreter::builder()
+            .dialect(Dialect::PrintFunctions)
+            .build();
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(err.message, "Expected 1 arguments but got 0.");
+
+        let (tokens, _) = Scanner::new().scan_tokens("println(1, 2);");
+        let program = parser.parse(tokens).unwrap();
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(err.message, "Expected 0 to 1 arguments but got 2.");
+    }
+
+    #[test]
+    fn test_clock() {
+        let source = "var start = clock(); print clock() >= start; print start > 0; print clock;";
+        assert_eq!(run(source), Ok("true\ntrue\n<native fn>\n".to_owned()));
+
+        let (tokens, _) = Scanner::new().scan_tokens("print clock();");
+        let program = Parser::new().parse(tokens).unwrap();
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::builder()
+            .writer(output.clone())
+            .deterministic(true)
+            .build();
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(output.0.borrow().as_slice(), b"0\n");
+
+        let mut interpreter = Interpreter::builder()
+            .capabilities(Capabilities::none())
+            .build();
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(err.message, "The clock capability is not granted.");
+    }
+
+    #[test]
+    fn test_define_native() {
+        let (tokens, _) = Scanner::new().scan_tokens("print twice(21);");
+        let program = Parser::new().parse(tokens).unwrap();
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::builder().writer(output.clone()).build();
+        interpreter.define_native(NativeFunction::new(
+            "twice",
+            1,
+            |_, arguments| match arguments[0] {
+                Value::Number(n) => Ok(Value::Number(n * 2.0)),
+                _ => Err("Argument must be a number.".into()),
+            },
+        ));
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(output.0.borrow().as_slice(), b"42\n");
+    }
+
+    #[test]
+    fn test_call() {
+        let source = "
+            var calls = 0;
+            fun add(a, b) { calls = calls + 1; return a + b; }
+            fun counter() { fun count() { return calls; } return count; }
+            class Point { init(x) { this.x = x; } }
+            var n = 1;";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(&program).unwrap();
+
+        let args = [Value::from(1.0), Value::from(2.0)];
+        assert_eq!(interpreter.call("add", &args), Ok(Value::Number(3.0)));
+        let args = [Value::from("a"), Value::from("b")];
+        assert_eq!(interpreter.call("add", &args), Ok(Value::from("ab")));
+        // Callable values returned by the program can be called too.
+        let count = interpreter.call("counter", &[]).unwrap();
+        assert_eq!(interpreter.call(count, &[]), Ok(Value::Number(2.0)));
+        match interpreter.call("Point", &[Value::from(1.0)]) {
+            Ok(Value::Instance(instance)) => assert_eq!(instance.class().name(), "Point"),
+            _ => panic!("expected an instance"),
+        }
+
+        let err = interpreter.call("missing", &[]).unwrap_err();
+        assert_eq!(err.message, "Undefined variable 'missing'.");
+        assert_eq!(err.token.span.start.line, 0);
+        let err = interpreter.call("n", &[]).unwrap_err();
+        assert_eq!(err.message, "Can only call functions and classes.");
+        let err = interpreter.call("add", &[]).unwrap_err();
+        assert_eq!(err.message, "Expected 2 arguments but got 0.");
+        // Errors raised by the function are reported where they occur.
+        let err = interpreter
+            .call("add", &[Value::Nil, Value::Nil])
+            .unwrap_err();
+        assert_eq!(err.message, "Operands must be two numbers or two strings.");
+        assert_eq!(err.token.span.start.line, 3);
+    }
+
+    #[test]
+    fn test_stack() {
+        let source = "
+            fun outer(a) { var b = 2; { var a = 3; return inner(a); } }
+            fun inner(c) { inspect(); return c / nil; }
+            var global = outer(1);";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+
+        let inspected: Rc<RefCell<Vec<StackFrame>>> = Rc::default();
+        let mut interpreter = Interpreter::new();
+        let stack = Rc::clone(&inspected);
+        interpreter.define_native(NativeFunction::new("inspect", 0, move |interpreter, _| {
+            *stack.borrow_mut() = interpreter.stack();
+            Ok(Value::Nil)
+        }));
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(err.message, "Operands must be numbers.");
+
+        let calls = |stack: &[StackFrame]| -> Vec<(String, Option<u64>)> {
+            stack
+                .iter()
+                .map(|frame| {
+                    let line = frame.call_site.map(|span| span.start.line);
+                    (frame.function.clone(), line)
+                })
+                .collect()
+        };
+        let expected = vec![
+            (String::from("script"), None),
+            (String::from("outer"), Some(4)),
+            (String::from("inner"), Some(2)),
+        ];
+        assert_eq!(calls(&inspected.borrow()), expected);
+        assert_eq!(inspected.borrow()[0].locals, vec![]);
+        // Inner scopes shadow outer ones.
+        let locals = vec![
+            (String::from("a"), Value::Number(3.0)),
+            (String::from("b"), Value::Number(2.0)),
+        ];
+        assert_eq!(inspected.borrow()[1].locals, locals);
+        let locals = vec![(String::from("c"), Value::Number(3.0))];
+        assert_eq!(inspected.borrow()[2].locals, locals);
+
+        // The stack at the error is kept after it has unwound.
+        assert_eq!(interpreter.error_stack(), Some(&inspected.borrow()[..]));
+        assert_eq!(interpreter.stack().len(), 1);
+
+        interpreter.interpret(&[]).unwrap();
+        assert_eq!(interpreter.error_stack(), None);
+    }
+
+    #[test]
+    fn test_load_prelude() {
+        let prelude = Prelude::compile(
+            "var count = 0;
+            fun next() { count = count + 1; return count; }
+            class Greeter { greet(name) { return \"hi \" + name; } }",
+        )
+        .unwrap();
+        let source = "print next(); print next(); print Greeter().greet(\"a\");";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+
+        // Each interpreter gets its own globals from the shared prelude.
+        for _ in 0..2 {
+            let output = SharedOutput::default();
+            let mut interpreter = Interpreter::builder().writer(output.clone()).build();
+            interpreter.load_prelude(&prelude).unwrap();
+            interpreter.interpret(&program).unwrap();
+            let printed = output.0.borrow().clone();
+            assert_eq!(String::from_utf8(printed).unwrap(), "1\n2\nhi a\n");
+        }
+
+        let prelude = Prelude::compile("var a = -nil;").unwrap();
+        let err = Interpreter::new().load_prelude(&prelude).unwrap_err();
+        assert_eq!(err.message, "Operand must be a number.");
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(run("print 1 + 2 * 3;"), Ok("7\n".to_owned()));
+        assert_eq!(run("print \"a\" + \"b\";"), Ok("ab\n".to_owned()));
+        assert_eq!(run("print -(1) < 2 == !nil;"), Ok("true\n".to_owned()));
+        assert_eq!(run("print nil or \"x\";"), Ok("x\n".to_owned()));
+        assert_eq!(run("print false and 1;"), Ok("false\n".to_owned()));
+        assert_eq!(
+            run("var a = 1; print (a = a + 1, a * 10);"),
+            Ok("20\n".to_owned())
+        );
+        assert_eq!(run("print 7 % 3;"), Ok("1\n".to_owned()));
+        assert_eq!(run("print -7 % 3;"), Ok("-1\n".to_owned()));
+        assert_eq!(run("print 2 ** 3 ** 2;"), Ok("512\n".to_owned()));
+        assert_eq!(run("print -2 ** 2;"), Ok("-4\n".to_owned()));
+        assert_eq!(run("print 2 ** -1;"), Ok("0.5\n".to_owned()));
+    }
+
+    #[test]
+    fn test_operand_errors() {
+        let msg = "Operands must be two numbers or two strings.";
+        assert_eq!(run("print 1 + \"a\";"), Err(msg.to_owned()));
+        let msg = "Operands must be numbers.";
+        assert_eq!(run("print 1 < nil;"), Err(msg.to_owned()));
+        assert_eq!(run("print \"a\" % 2;"), Err(msg.to_owned()));
+        assert_eq!(run("print 2 ** nil;"), Err(msg.to_owned()));
+        let msg = "Operand must be a number.";
+        assert_eq!(run("print -true;"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_variables_and_scopes() {
+        let source = "var a = 1; { var a = 2; print a; a = 3; print a; } print a;";
+        assert_eq!(run(source), Ok("2\n3\n1\n".to_owned()));
+        let msg = "Undefined variable 'b'.";
+        assert_eq!(run("b = 1;"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "for (var i = 0; i < 3; i = i + 1) if (i != 1) print i; else print \"one\";";
+        assert_eq!(run(source), Ok("0\none\n2\n".to_owned()));
+    }
+
+    #[test]
+    fn test_steps() {
+        let (tokens, _) = Scanner::new().scan_tokens("var i = 0; while (i < 2) { i = i + 1; }");
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let mut interpreter = Interpreter::builder().writer(io::sink()).build();
+        interpreter.interpret(&program).unwrap();
+        // The declaration and the loop, plus each iteration's block and
+        // assignment.
+        assert_eq!(interpreter.steps(), 6);
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let run_limited = |source: &str| {
+            let (tokens, _) = Scanner::new().scan_tokens(source);
+            let program = Parser::new().parse(tokens).unwrap();
+            Resolver::new().resolve(&program).unwrap();
+            let mut interpreter = Interpreter::builder()
+                .writer(io::sink())
+                .max_steps(100)
+                .build();
+            interpreter
+                .interpret(&program)
+                .map_err(|err| err.message.to_string())
+        };
+        let msg = "Step limit exceeded.";
+        assert_eq!(run_limited("while (true) {}"), Err(msg.to_owned()));
+        assert_eq!(run_limited("fun f() { f(); } f();"), Err(msg.to_owned()));
+        assert_eq!(run_limited("for (var i = 0; i < 10; i = i + 1) {}"), Ok(()));
+        // Exceeding the limit can not be caught.
+        let source = "while (true) { try { while (true) {} } catch (e) {} }";
+        assert_eq!(run_limited(source), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let msg = "Stack overflow.";
+        assert_eq!(run("fun f() { f(); } f();"), Err(msg.to_owned()));
+        let source = "class A { init() { A(); } } A();";
+        assert_eq!(run(source), Err(msg.to_owned()));
+        // Unlike exceeding the step limit, overflowing the stack can be
+        // caught, and the stack unwinds to the handler.
+        let source = "fun f(n) { f(n + 1); }\n\
+                      try { f(0); } catch (e) { print e; }\n\
+                      fun g(n) { if (n > 0) return g(n - 1); return \"done\"; }\n\
+                      print g(100);";
+        assert_eq!(run(source), Ok("Stack overflow.\ndone\n".to_owned()));
+    }
+
+    #[test]
+    fn test_record_trace() {
+        let source = "var a = \"x\";\nclass C {}\nfun f(n) { var c = C(); c.n = n; a = n; return n; }\nf(1);";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let mut interpreter = Interpreter::builder()
+            .writer(io::sink())
+            .record_trace(true)
+            .build();
+        interpreter.interpret(&program).unwrap();
+        let events: Vec<(u64, String)> = interpreter
+            .trace()
+            .unwrap()
+            .events()
+            .iter()
+            .map(|event| (event.line(), event.to_string()))
+            .collect();
+        let expected = vec![
+            (1, "define a = \"x\""),
+            (2, "define C = C"),
+            (3, "define f = <fn f>"),
+            (4, "call f(1)"),
+            (3, "define n = 1"),
+            (3, "call C()"),
+            (3, "C returned C instance"),
+            (3, "define c = C instance"),
+            (3, "C instance.n = 1"),
+            (3, "a = 1"),
+            (4, "f returned 1"),
+        ];
+        let expected: Vec<(u64, String)> = expected
+            .into_iter()
+            .map(|(line, event)| (line, event.to_owned()))
+            .collect();
+        assert_eq!(events, expected);
+        assert!(Interpreter::new().trace().is_none());
+    }
+
+    #[test]
+    fn test_record_trace_throw() {
+        let source = "fun f() { throw \"boom\"; }\ntry { f(); } catch (e) { print e; }\nvar a = 1;";
+        let (tokens, _) = Scanner::new().scan_tokens(source);
+        let program = Parser::new().parse(tokens).unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let mut interpreter = Interpreter::builder()
+            .writer(io::sink())
+            .record_trace(true)
+            .build();
+        interpreter.interpret(&program).unwrap();
+        let trace = interpreter.trace().unwrap();
+        let events: Vec<String> = trace.events().iter().map(Event::to_string).collect();
+        let expected = vec![
+            "define f = <fn f>",
+            "call f()",
+            "f unwound",
+            "define e = \"boom\"",
+            "define a = 1",
+        ];
+        assert_eq!(events, expected);
+        // The catch variable is in the frame of the script, not of f.
+        let state = trace.state(trace.events().len());
+        assert_eq!(state.frames.len(), 1);
+        assert_eq!(
+            state.frames[0].variables,
+            vec![(String::from("e"), String::from("\"boom\""))]
+        );
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        let source = "for (var i = 0; i < 5; i = i + 1) { if (i == 1) continue; if (i == 3) break; print i; }";
+        assert_eq!(run(source), Ok("0\n2\n".to_owned()));
+        let source = "var i = 0; while (true) { i = i + 1; if (i < 3) continue; break; } print i;";
+        assert_eq!(run(source), Ok("3\n".to_owned()));
+        // break only stops the innermost loop.
+        let source = "for (var i = 0; i < 2; i = i + 1) while (true) { print i; break; }";
+        assert_eq!(run(source), Ok("0\n1\n".to_owned()));
+    }
+
+    #[test]
+    fn test_functions() {
+        let source = "fun add(a, b) { return a + b; } print add(1, 2); print add;";
+        assert_eq!(run(source), Ok("3\n<fn add>\n".to_owned()));
+        let source = "fun f() { print 1; } print f();";
+        assert_eq!(run(source), Ok("1\nnil\n".to_owned()));
+    }
+
+    #[test]
+    fn test_recursion() {
+        let source = "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+                      print fib(10);";
+        assert_eq!(run(source), Ok("55\n".to_owned()));
+    }
+
+    #[test]
+    fn test_closures() {
+        let source = "
+            fun makeCounter() {
+                var i = 0;
+                fun count() { i = i + 1; return i; }
+                return count;
+            }
+            var a = makeCounter();
+            var b = makeCounter();
+            print a(); print a(); print b();";
+        assert_eq!(run(source), Ok("1\n2\n1\n".to_owned()));
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let source = "
+            fun twice(f, x) { return f(f(x)); }
+            print twice(fun (n) { return n * 2; }, 3);
+            fun makeCounter() {
+                var i = 0;
+                return fun () { i = i + 1; return i; };
+            }
+            var count = makeCounter();
+            count();
+            print count();
+            print fun () {};
+            fun () { print \"called\"; }();";
+        assert_eq!(run(source), Ok("12\n2\n<fn lambda>\ncalled\n".to_owned()));
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let msg = "Can only call functions and classes.";
+        assert_eq!(run("\"f\"();"), Err(msg.to_owned()));
+        let msg = "Expected 2 arguments but got 1.";
+        assert_eq!(run("fun f(a, b) {} f(1);"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_classes() {
+        let source = "
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                sum() { return this.x + this.y; }
+            }
+            var p = Point(1, 2);
+            print p.sum();
+            p.x = 10;
+            var sum = p.sum;
+            print sum();
+            print Point;
+            print p;";
+        assert_eq!(run(source), Ok("3\n12\nPoint\nPoint instance\n".to_owned()));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let source = "
+            class Counter { init() { this.n = 0; } add(d) { this.n += d; } }
+            var c = Counter();
+            c.add(2);
+            c.n *= 5;
+            var s = \"a\";
+            s += \"b\";
+            print c.n;
+            print s;";
+        assert_eq!(run(source), Ok("10\nab\n".to_owned()));
+        let msg = "Operands must be numbers.";
+        assert_eq!(run("var a = nil; a -= 1;"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let source = "
+            var name = \"Lox\";
+            class A {}
+            print \"hello ${name}!\";
+            print \"${1 + 1} ${nil} ${A()} ${\"${true}\"}\";";
+        assert_eq!(
+            run(source),
+            Ok("hello Lox!\n2 nil A instance true\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let source = "
+            var xs = [1, \"a\", [nil]];
+            print xs;
+            print xs[1];
+            xs[0] = 10;
+            xs[0] += 5;
+            print xs[0]++;
+            print xs[0];
+            var ys = xs;
+            ys[2][0] = true;
+            print xs;
+            print xs == ys;
+            print [] == [];";
+        assert_eq!(
+            run(source),
+            Ok("[1, \"a\", [nil]]\na\n15\n16\n[16, \"a\", [true]]\ntrue\nfalse\n".to_owned())
+        );
+        let msg = "List index out of range.";
+        assert_eq!(run("[1][1];"), Err(msg.to_owned()));
+        assert_eq!(run("[1][-1] = 2;"), Err(msg.to_owned()));
+        let msg = "List index must be an integer.";
+        assert_eq!(run("[1][0.5];"), Err(msg.to_owned()));
+        assert_eq!(run("[1][\"0\"];"), Err(msg.to_owned()));
+        let msg = "Only lists and maps can be indexed.";
+        assert_eq!(run("var a = 1; a[0];"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_maps() {
+        let source = "
+            var m = {\"b\": 1, \"a\": [2], 3: nil, true: \"t\", nil: 0};
+            print m;
+            print m[\"a\"];
+            m[\"c\"] = 5;
+            m[\"c\"] += 1;
+            m[\"c\"]++;
+            print m[\"c\"];
+            m[0] = 1;
+            m[-0] = 2;
+            print m[0];
+            print len(m);
+            print {} == {};";
+        let expected = "{nil: 0, true: \"t\", 3: nil, \"a\": [2], \"b\": 1}\n[2]\n7\n2\n7\nfalse\n";
+        assert_eq!(run(source), Ok(expected.to_owned()));
+        let msg = "Undefined key \"a\".";
+        assert_eq!(run("print {}[\"a\"];"), Err(msg.to_owned()));
+        let msg = "Map keys must be strings, numbers, booleans or nil.";
+        assert_eq!(run("print {[]: 1};"), Err(msg.to_owned()));
+        assert_eq!(run("var m = {}; m[m] = 1;"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let source = "
+            fun check(n) {
+                if (n < 0) throw {\"negative\": n};
+                return n;
+            }
+            try {
+                print check(1);
+                print check(-2);
+                print \"unreachable\";
+            } catch (e) {
+                print e[\"negative\"];
+            }
+            try { [1][5]; } catch (e) { print e; }
+            fun f() {
+                for (var i = 0; i < 3; i++) {
+                    try { if (i == 1) return i; } catch (e) {}
+                }
+            }
+            print f();
+            try {
+                try { throw 1; } catch (e) { throw e + 1; }
+            } catch (e) {
+                print e;
+            }";
+        let expected = "1\n-2\nList index out of range.\n1\n2\n";
+        assert_eq!(run(source), Ok(expected.to_owned()));
+        assert_eq!(run("throw \"oops\";"), Err("oops".to_owned()));
+    }
+
+    #[test]
+    fn test_print_recursive_list() {
+        let source = "var a = [1]; push(a, a); push(a, [a]); print a;";
+        assert_eq!(run(source), Ok("[1, [...], [[...]]]\n".to_owned()));
+        let source = "var m = {}; m[1] = [m]; print m;";
+        assert_eq!(run(source), Ok("{1: [{...}]}\n".to_owned()));
+    }
+
+    #[test]
+    fn test_increment() {
+        let source = "
+            class Counter { init() { this.n = 0; } }
+            var c = Counter();
+            var i = 1;
+            print i++;
+            print ++i;
+            print i--;
+            print --i;
+            fun f() { var j = 5; j++; return j; }
+            print f();
+            print ++c.n;
+            print c.n++;
+            print c.n;
+            for (var k = 0; k < 2; k++) print k;";
+        assert_eq!(run(source), Ok("1\n3\n3\n1\n6\n1\n1\n2\n0\n1\n".to_owned()));
+        let msg = "Operand must be a number.";
+        assert_eq!(run("var a = \"a\"; a++;"), Err(msg.to_owned()));
+        let msg = "Operand of '++' must be a variable, property or element.";
+        assert_eq!(run("++1;"), Err(msg.to_owned()));
+        let msg = "Operand of '--' must be a variable, property or element.";
+        assert_eq!(run("var a = 1; (a)--;"), Err(msg.to_owned()));
+        let msg = "Only instances have fields.";
+        assert_eq!(run("var a = 1; a.b++;"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_initializer_returns_instance() {
+        let source = "
+            class A { init() { this.a = 1; return; } }
+            var a = A();
+            print a.init() == a;
+            print A().a;";
+        assert_eq!(run(source), Ok("true\n1\n".to_owned()));
+    }
+
+    #[test]
+    fn test_fields_shadow_methods() {
+        let source = "class A { f() { return 1; } } var a = A(); a.f = 2; print a.f;";
+        assert_eq!(run(source), Ok("2\n".to_owned()));
+    }
+
+    #[test]
+    fn test_property_errors() {
+        let msg = "Undefined property 'b'.";
+        assert_eq!(run("class A {} A().b;"), Err(msg.to_owned()));
+        let msg = "Only instances have properties.";
+        assert_eq!(run("1 .b;"), Err(msg.to_owned()));
+        let msg = "Only instances have fields.";
+        assert_eq!(run("var a = \"a\"; a.b = 1;"), Err(msg.to_owned()));
+        let msg = "Expected 1 arguments but got 0.";
+        assert_eq!(run("class A { init(a) {} } A();"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let source = "
+            class A {
+                init(n) { this.n = n; }
+                name() { return \"A\"; }
+                describe() { return this.name() + \" \" + this.n; }
+            }
+            class B < A {
+                init() { super.init(\"b\"); }
+                name() { return \"B<\" + super.name(); }
+            }
+            class C < B {}
+            print C().describe();";
+        assert_eq!(run(source), Ok("B<A b\n".to_owned()));
+    }
+
+    #[test]
+    fn test_super_property() {
+        // A super method can be passed around as a value, and stays bound to
+        // the superclass method and instance it was taken from.
+        let source = "
+            class A {
+                init() { this.n = \"a\"; }
+                name() { return \"A \" + this.n; }
+            }
+            class B < A {
+                name() { return \"B\"; }
+                superName() { return super.name; }
+            }
+            var f = B().superName();
+            print f();";
+        assert_eq!(run(source), Ok("A a\n".to_owned()));
+
+        let source = "
+            class A { var x = 1; }
+            class B < A { f() { this.y = 2; return super.x + super.y; } }
+            print B().f();";
+        assert_eq!(run(source), Ok("3\n".to_owned()));
+    }
+
+    #[test]
+    fn test_inheritance_errors() {
+        let msg = "Superclass must be a class.";
+        assert_eq!(run("var A = 1; class B < A {}"), Err(msg.to_owned()));
+        let msg = "Undefined property 'g'.";
+        let source = "class A {} class B < A { f() { super.g(); } } B().f();";
+        assert_eq!(run(source), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_class_methods() {
+        let source = "
+            class Math {
+                class square(n) { return n * n; }
+                class describe() { return \"class ${this}\"; }
+            }
+            class More < Math {
+                class cube(n) { return n * this.square(n); }
+                class describe() { return \"more \" + super.describe(); }
+            }
+            print Math.square(3);
+            print More.cube(2);
+            print More.describe();
+            var square = More.square;
+            print square(4);";
+        assert_eq!(run(source), Ok("9\n8\nmore class More\n16\n".to_owned()));
+        // Class methods are not methods of the instances, nor the other way
+        // around.
+        let msg = "Undefined property 'square'.";
+        let source = "class Math { class square(n) { return n * n; } } Math().square(2);";
+        assert_eq!(run(source), Err(msg.to_owned()));
+        let msg = "Undefined property 'f'.";
+        assert_eq!(run("class A { f() {} } A.f();"), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_getters() {
+        let source = "
+            class Circle {
+                init(radius) { this.radius = radius; }
+                area { return 3 * this.radius * this.radius; }
+                class unit { return Circle(1); }
+            }
+            class Ring < Circle {
+                area { return super.area - 1; }
+            }
+            var c = Circle(2);
+            print c.area;
+            c.radius = 3;
+            print c.area;
+            print Circle.unit.area;
+            print Ring(2).area;";
+        assert_eq!(run(source), Ok("12\n27\n3\n11\n".to_owned()));
+        let source = "class A { n { return \"n\"; } } A().n();";
+        assert_eq!(
+            run(source),
+            Err("Can only call functions and classes.".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let source = "
+            const greeting = \"hi\";
+            fun greet(name) { const message = greeting + \" \" + name; return message; }
+            print greet(\"you\");";
+        assert_eq!(run(source), Ok("hi you\n".to_owned()));
+        // Assigning to a global constant in a function declared before it is
+        // only detected when run.
+        let source = "
+            fun reset() { limit = 0; }
+            const limit = 10;
+            reset();";
+        let msg = "Cannot assign to constant 'limit'.";
+        assert_eq!(run(source), Err(msg.to_owned()));
+    }
+
+    #[test]
+    fn test_class_fields() {
+        let source = "
+            var origin = 0;
+            class Point {
+                var x = origin;
+                var y;
+                init(x) { print this.x; this.x = x; }
+            }
+            var p = Point(1);
+            print p.x;
+            print p.y;
+            origin = 2;
+            print Point(3).x;";
+        assert_eq!(run(source), Ok("0\n1\nnil\n2\n3\n".to_owned()));
+    }
+
+    #[test]
+    fn test_class_fields_inherited() {
+        // Subclass fields are initialized after, and so override, those of
+        // the superclass.
+        let source = "
+            class A { var a = \"A\"; var b = \"A\"; }
+            class B < A { var b = \"B\"; }
+            var b = B();
+            print b.a + b.b;";
+        assert_eq!(run(source), Ok("AB\n".to_owned()));
+    }
+
+    #[test]
+    fn test_class_field_errors() {
+        let source = "class C { var f = missing; } C();";
+        assert_eq!(run(source), Err("Undefined variable 'missing'.".to_owned()));
+    }
+
+    #[test]
+    fn test_closures_bind_lexically() {
+        // The closure keeps referring to the global a, also after a local a
+        // is declared in the block the closure is called from.
+        let source = "
+            var a = \"global\";
+            {
+                fun show() { print a; }
+                show();
+                var a = \"block\";
+                show();
+            }";
+        assert_eq!(run(source), Ok("global\nglobal\n".to_owned()));
+    }
+
+    #[test]
+    fn test_return_restores_environment() {
+        let source = "var a = \"global\"; fun f() { var a = \"local\"; return a; } f(); print a;";
+        assert_eq!(run(source), Ok("global\n".to_owned()));
+    }
+}
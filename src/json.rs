@@ -0,0 +1,599 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::error::LoxError;
+use super::expr::*;
+use super::interpreter::StackFrame;
+use super::span::{Position, Span};
+use super::stmt::*;
+use super::token::{self, Token};
+
+// SCHEMA_VERSION is the version of the JSON AST schema produced by
+// AstJsonPrinter. It is incremented whenever the schema changes in a way
+// that is not backwards compatible. Version 2 added statements, the
+// Program root and the bodies of functions.
+pub const SCHEMA_VERSION: u32 = 2;
+
+// AstJsonPrinter converts the AST to a stable JSON representation, meant
+// for external consumers such as visualizers or grading scripts.
+//
+// The document has the form {"version": SCHEMA_VERSION, "root": <node>},
+// where the root of a program is a node of kind "Program". Each node is an
+// object with a "kind" and kind specific members; child nodes are listed in
+// "children", in source order. The members of classes ("fields", "methods"
+// and "class_methods") and the blocks of try statements ("body" and
+// "handler") are listed by role instead.
+pub struct AstJsonPrinter {}
+
+#[allow(clippy::new_without_default)]
+impl AstJsonPrinter {
+    pub fn new() -> Self {
+        AstJsonPrinter {}
+    }
+
+    pub fn print(&mut self, expr: &Expr) -> String {
+        let root = expr.accept(self);
+        Self::document(&root)
+    }
+
+    // print_program prints the document of program.
+    pub fn print_program(&mut self, program: &[Stmt]) -> String {
+        let statements = self.statements(program);
+        let root = format!(r#"{{"kind":"Program","children":[{}]}}"#, statements);
+        Self::document(&root)
+    }
+
+    fn document(root: &str) -> String {
+        format!(r#"{{"version":{},"root":{}}}"#, SCHEMA_VERSION, root)
+    }
+
+    // statements returns the nodes of statements, comma separated.
+    fn statements(&mut self, statements: &[Stmt]) -> String {
+        let nodes: Vec<String> = statements.iter().map(|s| s.accept(self)).collect();
+        nodes.join(",")
+    }
+}
+
+// RunReport is the outcome of running a script, as printed by "loxrust run
+// --json-output" for CI systems and autograders to evaluate.
+//
+// The report has the form {"status": <exit status>, "errors": [<error>],
+// "stack": [<frame>], "output_bytes": <n>, "steps": <n>, "elapsed_ms": <n>},
+// where stack is the call stack of a runtime error, innermost frame last.
+pub struct RunReport {
+    // status is the exit status of the run, 0 on success.
+    pub status: i32,
+    // errors are the compile errors, or the runtime error, of the run.
+    pub errors: Vec<LoxError>,
+    // stack is the call stack at the runtime error, if any.
+    pub stack: Vec<StackFrame>,
+    // output_bytes is the number of bytes printed by the program.
+    pub output_bytes: usize,
+    // steps is the number of statements executed.
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl RunReport {
+    pub fn to_json(&self) -> String {
+        let errors: Vec<String> = self.errors.iter().map(json_error).collect();
+        let stack: Vec<String> = self.stack.iter().map(json_stack_frame).collect();
+        format!(
+            r#"{{"status":{},"errors":[{}],"stack":[{}],"output_bytes":{},"steps":{},"elapsed_ms":{:.3}}}"#,
+            self.status,
+            errors.join(","),
+            stack.join(","),
+            self.output_bytes,
+            self.steps,
+            self.elapsed.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+fn json_error(err: &LoxError) -> String {
+    let kind = match err {
+        LoxError::Scan { .. } => "scan",
+        LoxError::Parse { .. } => "parse",
+        LoxError::Resolve { .. } => "resolve",
+        LoxError::Runtime(_) => "runtime",
+    };
+    let fix = match err.fix() {
+        Some(fix) => format!(
+            r#"{{"span":{},"replacement":{}}}"#,
+            json_span(fix.span),
+            json_string(&fix.replacement)
+        ),
+        None => String::from("null"),
+    };
+    format!(
+        r#"{{"kind":"{}","message":{},"lexeme":{},"span":{},"fix":{}}}"#,
+        kind,
+        json_string(err.message()),
+        json_string(err.lexeme()),
+        json_span(err.span()),
+        fix
+    )
+}
+
+fn json_stack_frame(frame: &StackFrame) -> String {
+    let call_site = match frame.call_site {
+        Some(span) => json_span(span),
+        None => String::from("null"),
+    };
+    format!(
+        r#"{{"function":{},"call_site":{}}}"#,
+        json_string(&frame.function),
+        call_site
+    )
+}
+
+// json_string returns s as a quoted and escaped JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_span(span: Span) -> String {
+    format!(
+        r#"{{"start":{},"end":{}}}"#,
+        json_position(span.start),
+        json_position(span.end)
+    )
+}
+
+fn json_position(position: Position) -> String {
+    format!(
+        r#"{{"line":{},"column":{},"offset":{}}}"#,
+        position.line, position.column, position.offset
+    )
+}
+
+fn json_token(token: &Token) -> String {
+    format!(
+        r#"{{"lexeme":{},"span":{}}}"#,
+        json_string(&token.lexeme),
+        json_span(token.span)
+    )
+}
+
+impl Visitor for AstJsonPrinter {
+    type Result = String;
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> String {
+        format!(
+            r#"{{"kind":"Assign","name":{},"children":[{}]}}"#,
+            json_token(&expr.name),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
+        format!(
+            r#"{{"kind":"Binary","operator":{},"children":[{},{}]}}"#,
+            json_token(&expr.operator),
+            expr.left.accept(self),
+            expr.right.accept(self)
+        )
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> String {
+        let mut children = vec![expr.callee.accept(self)];
+        for argument in &expr.arguments {
+            children.push(argument.accept(self));
+        }
+        format!(r#"{{"kind":"Call","children":[{}]}}"#, children.join(","))
+    }
+
+    // The children of a function are the statements of its body.
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) -> String {
+        let params: Vec<String> = expr.function.params.iter().map(json_token).collect();
+        format!(
+            r#"{{"kind":"Function","params":[{}],"children":[{}]}}"#,
+            params.join(","),
+            self.statements(&expr.function.body)
+        )
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> String {
+        format!(
+            r#"{{"kind":"Get","name":{},"children":[{}]}}"#,
+            json_token(&expr.name),
+            expr.object.accept(self)
+        )
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
+        format!(
+            r#"{{"kind":"Grouping","children":[{}]}}"#,
+            expr.expression.accept(self)
+        )
+    }
+
+    fn visit_increment_expr(&mut self, expr: &IncrementExpr) -> String {
+        format!(
+            r#"{{"kind":"Increment","operator":{},"prefix":{},"children":[{}]}}"#,
+            json_token(&expr.operator),
+            expr.prefix,
+            expr.target.accept(self)
+        )
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> String {
+        format!(
+            r#"{{"kind":"Index","children":[{},{}]}}"#,
+            expr.object.accept(self),
+            expr.index.accept(self)
+        )
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> String {
+        let elements: Vec<String> = expr.elements.iter().map(|e| e.accept(self)).collect();
+        format!(r#"{{"kind":"List","children":[{}]}}"#, elements.join(","))
+    }
+
+    // The children of a map are its keys and values, alternating.
+    fn visit_map_expr(&mut self, expr: &MapExpr) -> String {
+        let mut children = Vec::new();
+        for (key, value) in &expr.entries {
+            children.push(key.accept(self));
+            children.push(value.accept(self));
+        }
+        format!(r#"{{"kind":"Map","children":[{}]}}"#, children.join(","))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
+        use token::Literal;
+        let value = match expr.value {
+            Literal::Number(n) => format!("{}", n),
+            Literal::String(ref s) => json_string(s),
+            Literal::Bool(b) => format!("{}", b),
+            Literal::Nil => String::from("null"),
+        };
+        format!(r#"{{"kind":"Literal","value":{}}}"#, value)
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        format!(
+            r#"{{"kind":"Logical","operator":{},"children":[{},{}]}}"#,
+            json_token(&expr.operator),
+            expr.left.accept(self),
+            expr.right.accept(self)
+        )
+    }
+
+    fn visit_set_index_expr(&mut self, expr: &SetIndexExpr) -> String {
+        format!(
+            r#"{{"kind":"SetIndex","children":[{},{},{}]}}"#,
+            expr.object.accept(self),
+            expr.index.accept(self),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> String {
+        format!(
+            r#"{{"kind":"Set","name":{},"children":[{},{}]}}"#,
+            json_token(&expr.name),
+            expr.object.accept(self),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> String {
+        format!(
+            r#"{{"kind":"Super","keyword":{},"method":{}}}"#,
+            json_token(&expr.keyword),
+            json_token(&expr.method)
+        )
+    }
+
+    fn visit_this_expr(&mut self, expr: &ThisExpr) -> String {
+        format!(
+            r#"{{"kind":"This","keyword":{}}}"#,
+            json_token(&expr.keyword)
+        )
+    }
+
+    fn visit_throw_expr(&mut self, expr: &ThrowExpr) -> String {
+        format!(
+            r#"{{"kind":"Throw","keyword":{},"children":[{}]}}"#,
+            json_token(&expr.keyword),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
+        format!(
+            r#"{{"kind":"Unary","operator":{},"children":[{}]}}"#,
+            json_token(&expr.operator),
+            expr.expression.accept(self)
+        )
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        format!(r#"{{"kind":"Variable","name":{}}}"#, json_token(&expr.name))
+    }
+}
+
+impl StmtVisitor for AstJsonPrinter {
+    type Result = String;
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> String {
+        format!(
+            r#"{{"kind":"Block","children":[{}]}}"#,
+            self.statements(&stmt.statements)
+        )
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) -> String {
+        format!(
+            r#"{{"kind":"Break","keyword":{}}}"#,
+            json_token(&stmt.keyword)
+        )
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> String {
+        let superclass = match stmt.superclass {
+            Some(ref superclass) => superclass.accept(self),
+            None => String::from("null"),
+        };
+        let fields: Vec<String> = stmt.fields.iter().map(|f| self.visit_var_stmt(f)).collect();
+        let methods: Vec<String> = stmt.methods.iter().map(|m| m.accept(self)).collect();
+        let class_methods: Vec<String> =
+            stmt.class_methods.iter().map(|m| m.accept(self)).collect();
+        format!(
+            r#"{{"kind":"Class","name":{},"superclass":{},"fields":[{}],"methods":[{}],"class_methods":[{}]}}"#,
+            json_token(&stmt.name),
+            superclass,
+            fields.join(","),
+            methods.join(","),
+            class_methods.join(",")
+        )
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) -> String {
+        format!(
+            r#"{{"kind":"Continue","keyword":{}}}"#,
+            json_token(&stmt.keyword)
+        )
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> String {
+        format!(
+            r#"{{"kind":"Expression","children":[{}]}}"#,
+            stmt.expression.accept(self)
+        )
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> String {
+        let params: Vec<String> = stmt.params.iter().map(json_token).collect();
+        format!(
+            r#"{{"kind":"FunctionDecl","name":{},"params":[{}],"getter":{},"children":[{}]}}"#,
+            json_token(&stmt.name),
+            params.join(","),
+            stmt.getter,
+            self.statements(&stmt.body)
+        )
+    }
+
+    // The children of an if statement are its condition, then branch and
+    // else branch, if any.
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> String {
+        let mut children = vec![stmt.condition.accept(self), stmt.then_branch.accept(self)];
+        if let Some(ref else_branch) = stmt.else_branch {
+            children.push(else_branch.accept(self));
+        }
+        format!(r#"{{"kind":"If","children":[{}]}}"#, children.join(","))
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> String {
+        format!(
+            r#"{{"kind":"Import","path":{},"name":{}}}"#,
+            json_token(&stmt.path),
+            json_token(&stmt.name)
+        )
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> String {
+        format!(
+            r#"{{"kind":"Print","children":[{}]}}"#,
+            stmt.expression.accept(self)
+        )
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> String {
+        let children: Vec<String> = stmt.value.iter().map(|v| v.accept(self)).collect();
+        format!(
+            r#"{{"kind":"Return","keyword":{},"children":[{}]}}"#,
+            json_token(&stmt.keyword),
+            children.join(",")
+        )
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> String {
+        format!(
+            r#"{{"kind":"Try","keyword":{},"body":[{}],"name":{},"handler":[{}]}}"#,
+            json_token(&stmt.keyword),
+            self.statements(&stmt.body),
+            json_token(&stmt.name),
+            self.statements(&stmt.handler)
+        )
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> String {
+        let children: Vec<String> = stmt.initializer.iter().map(|i| i.accept(self)).collect();
+        format!(
+            r#"{{"kind":"Var","name":{},"constant":{},"children":[{}]}}"#,
+            json_token(&stmt.name),
+            stmt.constant,
+            children.join(",")
+        )
+    }
+
+    // The children of a while statement are its condition, body and the
+    // increment of a for loop, if any.
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> String {
+        let mut children = vec![stmt.condition.accept(self), stmt.body.accept(self)];
+        if let Some(ref increment) = stmt.increment {
+            children.push(increment.accept(self));
+        }
+        format!(
+            r#"{{"kind":"While","keyword":{},"children":[{}]}}"#,
+            json_token(&stmt.keyword),
+            children.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use token::{Lexeme, Literal, TokenType};
+
+    fn make_token(token_type: TokenType, lexeme: &str) -> Token {
+        let end = Position::new(1, 1 + lexeme.len() as u64, lexeme.len());
+        Token {
+            token_type,
+            lexeme: Lexeme::from(lexeme),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), end),
+            literal: None,
+            error: false,
+        }
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn test_run_report() {
+        let report = RunReport {
+            status: 70,
+            errors: vec![LoxError::Parse {
+                span: make_token(TokenType::Minus, "-").span,
+                lexeme: String::from("-"),
+                message: messages::EXPECT_EXPRESSION.into(),
+                fix: None,
+            }],
+            stack: vec![StackFrame {
+                function: String::from("script"),
+                call_site: None,
+                locals: Vec::new(),
+            }],
+            output_bytes: 4,
+            steps: 2,
+            elapsed: Duration::from_micros(1500),
+        };
+        let span = concat!(
+            r#"{"start":{"line":1,"column":1,"offset":0},"#,
+            r#""end":{"line":1,"column":2,"offset":1}}"#
+        );
+        let expected = format!(
+            concat!(
+                r#"{{"status":70,"errors":[{{"kind":"parse","#,
+                r#""message":"Expect expression.","lexeme":"-","span":{span},"fix":null}}],"#,
+                r#""stack":[{{"function":"script","call_site":null}}],"#,
+                r#""output_bytes":4,"steps":2,"elapsed_ms":1.500}}"#
+            ),
+            span = span
+        );
+        assert_eq!(report.to_json(), expected);
+    }
+
+    #[test]
+    fn test_visit_literal_expr() {
+        let mut printer = AstJsonPrinter::new();
+        let ex = Expr::make_literal(Literal::Number(2.5));
+        assert_eq!(ex.accept(&mut printer), r#"{"kind":"Literal","value":2.5}"#);
+        let ex = Expr::make_literal(Literal::String("a\"".into()));
+        assert_eq!(
+            ex.accept(&mut printer),
+            r#"{"kind":"Literal","value":"a\""}"#
+        );
+    }
+
+    #[test]
+    fn test_print() {
+        let mut printer = AstJsonPrinter::new();
+        let ex = Expr::make_binary(
+            Expr::make_unary(
+                make_token(TokenType::Minus, "-"),
+                Expr::make_literal(Literal::Number(1.0)),
+            ),
+            make_token(TokenType::Star, "*"),
+            Expr::make_grouping(Expr::make_literal(Literal::Number(2.0))),
+        );
+
+        let span = concat!(
+            r#"{"start":{"line":1,"column":1,"offset":0},"#,
+            r#""end":{"line":1,"column":2,"offset":1}}"#
+        );
+        let expected = format!(
+            concat!(
+                r#"{{"version":2,"root":{{"kind":"Binary","#,
+                r#""operator":{{"lexeme":"*","span":{span}}},"children":["#,
+                r#"{{"kind":"Unary","operator":{{"lexeme":"-","span":{span}}},"#,
+                r#""children":[{{"kind":"Literal","value":1}}]}},"#,
+                r#"{{"kind":"Grouping","children":[{{"kind":"Literal","value":2}}]}}"#,
+                r#"]}}}}"#
+            ),
+            span = span
+        );
+        assert_eq!(printer.print(&ex), expected);
+    }
+
+    #[test]
+    fn test_print_program() {
+        let (tokens, _) = Scanner::new().scan_tokens("fun f(a) { return a; }\nwhile (true) break;");
+        let program = Parser::new().parse(tokens).unwrap();
+        let token = |lexeme: &str, line: u64, column: u64, offset: usize| {
+            let end = (column + lexeme.len() as u64, offset + lexeme.len());
+            format!(
+                r#"{{"lexeme":"{}","span":{{"start":{{"line":{line},"column":{},"offset":{}}},"end":{{"line":{line},"column":{},"offset":{}}}}}}}"#,
+                lexeme,
+                column,
+                offset,
+                end.0,
+                end.1,
+                line = line
+            )
+        };
+        let expected = format!(
+            concat!(
+                r#"{{"version":2,"root":{{"kind":"Program","children":["#,
+                r#"{{"kind":"FunctionDecl","name":{f},"params":[{a}],"getter":false,"children":["#,
+                r#"{{"kind":"Return","keyword":{ret},"children":["#,
+                r#"{{"kind":"Variable","name":{a2}}}]}}]}},"#,
+                r#"{{"kind":"While","keyword":{wh},"children":["#,
+                r#"{{"kind":"Literal","value":true}},"#,
+                r#"{{"kind":"Break","keyword":{brk}}}]}}"#,
+                r#"]}}}}"#
+            ),
+            f = token("f", 1, 5, 4),
+            a = token("a", 1, 7, 6),
+            ret = token("return", 1, 12, 11),
+            a2 = token("a", 1, 19, 18),
+            wh = token("while", 2, 1, 23),
+            brk = token("break", 2, 14, 36),
+        );
+        assert_eq!(AstJsonPrinter::new().print_program(&program), expected);
+    }
+}
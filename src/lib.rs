@@ -1,6 +1,28 @@
-pub type ErrorCallback = Fn(u64, &str) -> ();
-
+pub mod const_eval;
+pub mod constants;
+pub mod environment;
+pub mod error;
 pub mod expr;
+pub mod fix;
+pub mod interpreter;
+pub mod json;
+pub mod messages;
+pub mod observer;
+pub mod parser;
+pub mod prelude;
 pub mod print;
+pub mod references;
+pub mod rename;
+pub mod resolver;
+pub mod runner;
 pub mod scanner;
+pub mod source;
+pub mod span;
+pub mod stdlib;
+pub mod stmt;
+pub mod string;
+pub mod symbol;
 pub mod token;
+pub mod trace;
+pub mod transcript;
+pub mod value;
@@ -1,56 +1,808 @@
 use std::env;
-use std::fs::File;
-use std::io;
-use std::io::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal};
+use std::path::Path;
 use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use loxrust::scanner::Scanner;
+use loxrust::error::{FixIt, LoxError};
+use loxrust::fix;
+use loxrust::interpreter::Interpreter;
+use loxrust::json::{AstJsonPrinter, RunReport};
+use loxrust::messages::{self, Catalog, Message};
+use loxrust::parser::{Dialect, Parser};
+use loxrust::print::AstPrinter;
+use loxrust::references;
+use loxrust::resolver::Resolver;
+use loxrust::runner;
+use loxrust::scanner::{IncrementalScanner, Scanner};
+use loxrust::source;
+use loxrust::span::{self, Span};
+use loxrust::stmt::Stmt;
+use loxrust::token::Token;
+use loxrust::trace::Trace;
+
+use console::Console;
+use editor::{Input, LineEditor, PlainEditor, TerminalEditor};
+
+mod console;
+mod editor;
+mod replay;
+mod xtest;
+
+// ReplErrorPolicy controls what the REPL does when a line has an error.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum ReplErrorPolicy {
+    // Reset discards the failed line and continues with the next one.
+    Reset,
+    // Abort ends the session, exiting with a non-zero status. This is
+    // useful when driving the REPL from a script, e.g. in CI.
+    Abort,
+}
+
+// RunError is the kind of error that stopped running some source.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum RunError {
+    // Compile is an error found before running the program, while scanning,
+    // parsing or resolving it.
+    Compile,
+    // Runtime is an error raised while running the program.
+    Runtime,
+}
+
+impl RunError {
+    // exit_code returns the status to exit with for the error. The codes
+    // are those used by jlox, from sysexits.h.
+    fn exit_code(self) -> i32 {
+        match self {
+            RunError::Compile => 65,
+            RunError::Runtime => 70,
+        }
+    }
+}
+
+// Options are the options given on the command line.
+#[derive(Debug)]
+struct Options {
+    stack_size: Option<usize>,
+    repl_policy: ReplErrorPolicy,
+    unicode_identifiers: bool,
+    dialect: Dialect,
+    // record is the optional path to record a transcript of the REPL
+    // session to.
+    record: Option<String>,
+    // max_steps is the optional limit on the number of statements executed.
+    max_steps: Option<u64>,
+    // json_output is true if a JSON RunReport is printed after running a
+    // script.
+    json_output: bool,
+    // trace is the optional path to record a Trace of running a script to,
+    // for "loxrust replay".
+    trace: Option<String>,
+    // catalog translates the diagnostics, as loaded from the file given by
+    // --messages. It is empty by default, leaving them in English.
+    catalog: Arc<Catalog>,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        println!("Usage: loxrust [script]");
-        process::exit(1);
-    } else if args.len() == 2 {
-        run_file(&args[1]).unwrap();
-    } else {
-        run_prompt().unwrap();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("xtest") {
+        run_xtest(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("grammar") {
+        print_grammar(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("fix") {
+        fix_file(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("replay") {
+        replay_trace(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("references") {
+        print_references(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("messages") {
+        print_messages(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("ast") {
+        print_ast(&args[1..]);
+    }
+    // "repl" explicitly starts the REPL, which is otherwise started when no
+    // script is given.
+    let is_repl = args.first().map(String::as_str) == Some("repl");
+    if is_repl {
+        args.remove(0);
+    }
+    // "run" explicitly runs a script, which must then be given.
+    let is_run = args.first().map(String::as_str) == Some("run");
+    if is_run {
+        args.remove(0);
+    }
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
+    };
+    if args.len() > 1 || (is_repl && !args.is_empty()) || (is_run && args.is_empty()) {
+        print_usage_and_exit();
+    }
+    if options.record.is_some() && !args.is_empty() {
+        eprintln!("--record is only supported in the REPL.");
+        print_usage_and_exit();
+    }
+    if options.json_output && args.is_empty() {
+        eprintln!("--json-output is only supported when running a script.");
+        print_usage_and_exit();
+    }
+    if options.trace.is_some() && args.is_empty() {
+        eprintln!("--trace is only supported when running a script.");
+        print_usage_and_exit();
+    }
+    let script = args.pop();
+    let stack_size = options.stack_size;
+    let run_main = move || {
+        let result = match script {
+            Some(path) => run_file(&path, &options),
+            None => run_prompt(&options),
+        };
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            process::exit(74);
+        }
+    };
+    match stack_size {
+        Some(stack_size) => runner::run_with_stack_size(stack_size, run_main).unwrap(),
+        None => run_main(),
     }
 }
 
-fn run_file(path: &str) -> io::Result<()> {
-    let mut file = File::open(path)?;
-    let mut buf = String::new();
-    file.read_to_string(&mut buf)?;
-    let had_error = run(&buf);
-    if had_error {
-        // TODO:
-        panic!("had_error!")
-    } else {
-        Ok(())
+fn print_usage_and_exit() -> ! {
+    println!(
+        "Usage: loxrust [--stack-size bytes] [--repl-on-error reset|abort] \
+         [--unicode-identifiers] [--dialect standard|print-functions] \
+         [--max-steps n] [--messages path] [script]"
+    );
+    println!("       loxrust run [--json-output] [--trace path] [options] script");
+    println!("       loxrust repl [--record path] [options]");
+    println!("       loxrust grammar [--dialect standard|print-functions]");
+    println!("       loxrust fix [--dry-run] [options] script");
+    println!("       loxrust replay trace");
+    println!("       loxrust references [options] script line:column");
+    println!("       loxrust messages");
+    println!("       loxrust ast [--json] [options] script");
+    println!("       loxrust xtest --reference=<path> <corpus>...");
+    process::exit(1);
+}
+
+// run_xtest runs the xtest reference comparison and exits the process with
+// a status reflecting whether all scripts matched the reference.
+fn run_xtest(args: &[String]) -> ! {
+    match xtest::run(args) {
+        Ok(true) => process::exit(0),
+        Ok(false) => process::exit(1),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
     }
 }
 
-fn run_prompt() -> io::Result<()> {
+// print_grammar prints the grammar accepted by the parser, in the dialect
+// given by args, and exits the process.
+fn print_grammar(args: &[String]) -> ! {
+    let mut args = args.to_vec();
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
+    };
+    if !args.is_empty() {
+        print_usage_and_exit();
+    }
+    print!("{}", Parser::new().with_dialect(options.dialect).grammar());
+    process::exit(0);
+}
+
+// fix_file applies the fixes suggested by the diagnostics of the script
+// given by args, rewriting it in place, and exits the process. With
+// "--dry-run" the script is left untouched and the changes are printed as a
+// unified diff instead.
+fn fix_file(args: &[String]) -> ! {
+    let mut args = args.to_vec();
+    let dry_run = take_flag(&mut args, "--dry-run");
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
+    };
+    if args.len() != 1 {
+        print_usage_and_exit();
+    }
+    let path = &args[0];
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{}': {}", path, err);
+            process::exit(74);
+        }
+    };
+    let scanner = Scanner::new().with_unicode_identifiers(options.unicode_identifiers);
+    let (fixed, fixes) = fix::fix_source(&source, &scanner, options.dialect);
+    for fix in &fixes {
+        eprintln!("[line {}] Fixed: {}", fix.line, fix.message);
+    }
+    if dry_run {
+        print!("{}", fix::diff(path, &source, &fixed));
+    } else if fixed != source {
+        if let Err(err) = fs::write(path, fixed) {
+            eprintln!("Could not write '{}': {}", path, err);
+            process::exit(74);
+        }
+    }
+    process::exit(0);
+}
+
+// replay_trace runs the replay viewer on the trace file given by args,
+// reading commands from stdin, and exits the process.
+fn replay_trace(args: &[String]) -> ! {
+    if args.len() != 1 {
+        print_usage_and_exit();
+    }
+    let trace = match fs::read_to_string(&args[0]) {
+        Ok(text) => Trace::parse(&text),
+        Err(err) => Err(format!("Could not read '{}': {}", args[0], err)),
+    };
+    let trace = match trace {
+        Ok(trace) => trace,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(74);
+        }
+    };
     let stdin = io::stdin();
+    if let Err(err) = replay::run(&trace, stdin.lock(), &mut io::stdout()) {
+        eprintln!("{}", err);
+        process::exit(74);
+    }
+    process::exit(0);
+}
+
+// print_references prints the references to the variable at the position
+// "line:column" of the script given by args, one per line as its span and
+// kind, e.g. "3:1-3:2 write", and exits the process.
+fn print_references(args: &[String]) -> ! {
+    let mut args = args.to_vec();
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
+    };
+    if args.len() != 2 {
+        print_usage_and_exit();
+    }
+    let source = match fs::read_to_string(&args[0]) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{}': {}", args[0], err);
+            process::exit(74);
+        }
+    };
+    let position = args[1]
+        .split_once(':')
+        .and_then(|(line, column)| Some((line.parse().ok()?, column.parse().ok()?)));
+    let offset = match position.and_then(|(line, column)| span::offset_of(&source, line, column)) {
+        Some(offset) => offset,
+        None => {
+            eprintln!("Invalid position '{}'.", args[1]);
+            print_usage_and_exit();
+        }
+    };
+    let scanner = Scanner::new().with_unicode_identifiers(options.unicode_identifiers);
+    match references::find_references(&source, offset, &scanner, options.dialect) {
+        Ok(references) => {
+            for reference in references {
+                println!("{} {}", reference.span, reference.kind);
+            }
+            process::exit(0);
+        }
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{}", err.localize(options.catalog.as_ref()));
+            }
+            process::exit(RunError::Compile.exit_code());
+        }
+    }
+}
+
+// print_ast prints the syntax tree of the script given by args, and exits
+// the process. With "--json" the tree is printed as the versioned JSON
+// document of AstJsonPrinter, for external tools, and otherwise as
+// s-expressions, one statement per line.
+fn print_ast(args: &[String]) -> ! {
+    let mut args = args.to_vec();
+    let json = take_flag(&mut args, "--json");
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            print_usage_and_exit();
+        }
+    };
+    if args.len() != 1 {
+        print_usage_and_exit();
+    }
+    let source = match fs::read(&args[0]) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{}': {}", args[0], err);
+            process::exit(74);
+        }
+    };
+    let scanner = Scanner::new().with_unicode_identifiers(options.unicode_identifiers);
+    let (tokens, mut errors) = scanner.scan_bytes(&source);
+    if errors.is_empty() {
+        let parser = Parser::new()
+            .with_dialect(options.dialect)
+            .with_unicode_identifiers(options.unicode_identifiers);
+        match parser.parse(tokens) {
+            Ok(program) if json => println!("{}", AstJsonPrinter::new().print_program(&program)),
+            Ok(program) => println!("{}", AstPrinter::new().print_program(&program)),
+            Err(parse_errors) => errors = parse_errors,
+        }
+    }
+    for err in &errors {
+        eprintln!("{}", err.localize(options.catalog.as_ref()));
+    }
+    if !errors.is_empty() {
+        process::exit(RunError::Compile.exit_code());
+    }
+    process::exit(0);
+}
+
+// print_messages prints the catalog of diagnostics in English, in the format
+// read by --messages, as a starting point for translating them, and exits
+// the process.
+fn print_messages(args: &[String]) -> ! {
+    if !args.is_empty() {
+        print_usage_and_exit();
+    }
+    print!("{}", Catalog::english());
+    process::exit(0);
+}
+
+// MAIN_STACK_SIZE is the size in bytes of the stack of the main thread, as
+// commonly configured on Linux.
+const MAIN_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// STACK_BYTES_PER_CALL is the stack size in bytes reserved for each Lox call
+// when limiting the call depth. A call uses less than half of this in a
+// debug build, leaving room for deeply nested expressions within calls.
+const STACK_BYTES_PER_CALL: usize = 16 * 1024;
+
+impl Options {
+    // max_call_depth returns the limit on the call depth that fits the stack
+    // the program runs on, so that deep recursion raises a runtime error
+    // rather than overflowing the stack.
+    fn max_call_depth(&self) -> usize {
+        self.stack_size.unwrap_or(MAIN_STACK_SIZE) / STACK_BYTES_PER_CALL
+    }
+}
+
+// parse_options removes the supported options from args, returning them.
+fn parse_options(args: &mut Vec<String>) -> Result<Options, String> {
+    let stack_size = match take_option(args, "--stack-size")? {
+        Some(value) => match value.parse() {
+            Ok(bytes) => Some(bytes),
+            Err(_) => return Err(format!("Invalid stack size '{}'.", value)),
+        },
+        None => None,
+    };
+    let repl_policy = match take_option(args, "--repl-on-error")?.as_deref() {
+        None | Some("reset") => ReplErrorPolicy::Reset,
+        Some("abort") => ReplErrorPolicy::Abort,
+        Some(value) => return Err(format!("Invalid REPL error policy '{}'.", value)),
+    };
+    let unicode_identifiers = take_flag(args, "--unicode-identifiers");
+    let dialect = match take_option(args, "--dialect")?.as_deref() {
+        None | Some("standard") => Dialect::Standard,
+        Some("print-functions") => Dialect::PrintFunctions,
+        Some(value) => return Err(format!("Invalid dialect '{}'.", value)),
+    };
+    let record = take_option(args, "--record")?;
+    let max_steps = match take_option(args, "--max-steps")? {
+        Some(value) => match value.parse() {
+            Ok(max_steps) => Some(max_steps),
+            Err(_) => return Err(format!("Invalid maximum number of steps '{}'.", value)),
+        },
+        None => None,
+    };
+    let json_output = take_flag(args, "--json-output");
+    let trace = take_option(args, "--trace")?;
+    let catalog = match take_option(args, "--messages")? {
+        Some(path) => {
+            let text = fs::read_to_string(&path)
+                .map_err(|err| format!("Could not read '{}': {}", path, err))?;
+            Catalog::parse(&text).map_err(|msg| format!("Invalid messages '{}': {}", path, msg))?
+        }
+        None => Catalog::new(),
+    };
+    Ok(Options {
+        stack_size,
+        repl_policy,
+        unicode_identifiers,
+        dialect,
+        record,
+        max_steps,
+        json_output,
+        trace,
+        catalog: Arc::new(catalog),
+    })
+}
+
+// take_flag removes the flag name from args, returning true if the flag was
+// present.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+// take_option removes a "<name> <value>" option from args, returning the
+// value if the option was present.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let pos = match args.iter().position(|arg| arg == name) {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    if pos + 1 >= args.len() {
+        return Err(format!("Missing value for {}.", name));
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Ok(Some(value))
+}
+
+fn run_file(path: &str, options: &Options) -> io::Result<()> {
+    // A script that cannot be read exits with EX_NOINPUT from sysexits.h.
+    let buf = match File::open(path).and_then(|file| source::read_source_bytes(file, None)) {
+        Ok(buf) => buf,
+        Err(err) => {
+            eprintln!("Could not read '{}': {}", path, err);
+            process::exit(66);
+        }
+    };
+    let mut session = Session::new(options, Console::new());
+    // The paths imported by the script are relative to its directory.
+    if let Some(dir) = Path::new(path).parent() {
+        session.interpreter.set_module_dir(dir);
+    }
+    let start = Instant::now();
+    let status = match session.run(&buf, false) {
+        Ok(()) => 0,
+        Err(err) => err.exit_code(),
+    };
+    if options.json_output {
+        println!("{}", session.report(status, start.elapsed()).to_json());
+    }
+    // The trace is written also when the script fails, as that is when it
+    // is most useful.
+    if let (Some(path), Some(trace)) = (&options.trace, session.interpreter.trace()) {
+        if let Err(err) = fs::write(path, trace.to_string()) {
+            eprintln!("Could not write '{}': {}", path, err);
+            process::exit(74);
+        }
+    }
+    if status != 0 {
+        process::exit(status);
+    }
+    Ok(())
+}
+
+fn run_prompt(options: &Options) -> io::Result<()> {
+    let mut console = Console::new();
+    if options.record.is_some() {
+        console = console.with_transcript();
+    }
+    let mut session = Session::new(options, console);
+    let stdin = io::stdin();
+    let aborted = if stdin.is_terminal() {
+        repl(&mut TerminalEditor::new()?, &mut session)?
+    } else {
+        repl(&mut PlainEditor::new(stdin.lock()), &mut session)?
+    };
+    if let (Some(path), Some(transcript)) = (&options.record, session.console.transcript()) {
+        if let Err(err) = fs::write(path, transcript.to_string()) {
+            eprintln!("Could not write '{}': {}", path, err);
+            process::exit(74);
+        }
+    }
+    match aborted {
+        Some(err) => process::exit(err.exit_code()),
+        None => Ok(()),
+    }
+}
+
+// repl runs a REPL session, reading lines from editor until the end of
+// input. Input that ends inside an unclosed parenthesis or brace is
+// continued on the next line. repl returns the error that ended the session
+// early if the REPL error policy is to abort, or None if all of input was
+// read.
+fn repl(editor: &mut dyn LineEditor, session: &mut Session) -> io::Result<Option<RunError>> {
+    // input scans the input read so far of the statement being entered, a
+    // line at a time.
+    let mut input = IncrementalScanner::new(session.scanner());
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
-        let mut buf = String::new();
-        stdin.read_line(&mut buf)?;
-        let _had_error = run(buf.trim_end());
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        let at_eof = match editor.read_line(prompt)? {
+            Input::Line(buf) => {
+                let line = buf.trim_end();
+                session.console.input(line);
+                input.feed(line);
+                input.feed("\n");
+                false
+            }
+            // The input being entered was cancelled, start over.
+            Input::Interrupted => {
+                input = IncrementalScanner::new(session.scanner());
+                continue;
+            }
+            Input::Eof => true,
+        };
+        // Input that is still incomplete at the end of input is run anyway,
+        // to report its errors.
+        if !at_eof && session.is_incomplete(&input) {
+            continue;
+        }
+        if !input.is_empty() {
+            let (tokens, errors) = input.finish();
+            let result = session.run_tokens(tokens, errors, true);
+            if let Err(err) = result {
+                if session.options.repl_policy == ReplErrorPolicy::Abort {
+                    return Ok(Some(err));
+                }
+            }
+        }
+        if at_eof {
+            return Ok(None);
+        }
     }
 }
 
-fn run(source: &str) -> bool {
-    fn print_error(line: u64, msg: &str) {
-        eprintln!("[line {}] Error: {}", line, msg);
+// Session is the state kept while running a script, or across the lines of
+// a REPL session.
+struct Session<'a> {
+    options: &'a Options,
+    console: Console,
+    // interpreter is kept for the whole session, so that e.g. a variable
+    // defined on one line of the REPL can be used on the next.
+    interpreter: Interpreter,
+    // errors are the errors reported by the last run.
+    errors: Vec<LoxError>,
+}
+
+impl<'a> Session<'a> {
+    fn new(options: &'a Options, console: Console) -> Self {
+        let mut builder = Interpreter::builder()
+            .writer(console.output())
+            .dialect(options.dialect)
+            .scanner(Scanner::new().with_unicode_identifiers(options.unicode_identifiers))
+            .record_trace(options.trace.is_some());
+        if let Some(max_steps) = options.max_steps {
+            builder = builder.max_steps(max_steps);
+        }
+        let interpreter = builder.max_call_depth(options.max_call_depth()).build();
+        Session {
+            options,
+            console,
+            interpreter,
+            errors: Vec::new(),
+        }
+    }
+
+    // scanner returns a Scanner configured by the session options.
+    fn scanner(&self) -> Scanner<'static> {
+        Scanner::new().with_unicode_identifiers(self.options.unicode_identifiers)
+    }
+
+    // parser returns a Parser configured by the session options.
+    fn parser(&self) -> Parser<'_> {
+        Parser::new()
+            .with_dialect(self.options.dialect)
+            .with_unicode_identifiers(self.options.unicode_identifiers)
+    }
+
+    // is_incomplete returns true if the input scanned by input, entered in
+    // the REPL, is the start of a statement continuing on the next line,
+    // such as an unclosed block or a string spanning lines.
+    fn is_incomplete(&self, input: &IncrementalScanner) -> bool {
+        input.is_pending()
+            || (input.errors().is_empty() && self.parser().is_incomplete(&input.tokens()))
+    }
+
+    // run runs source, printing to the console. is_repl is true if source is
+    // input entered in the REPL. source may contain invalid UTF-8, which is
+    // reported as an error.
+    fn run(&mut self, source: &[u8], is_repl: bool) -> Result<(), RunError> {
+        let (tokens, errors) = self.scanner().scan_bytes(source);
+        self.run_tokens(tokens, errors, is_repl)
+    }
+
+    // run_tokens is as run, but for source already scanned into tokens and
+    // errors.
+    fn run_tokens(
+        &mut self,
+        tokens: Vec<Token>,
+        errors: Vec<LoxError>,
+        is_repl: bool,
+    ) -> Result<(), RunError> {
+        let (result, errors) = match self.compile(tokens, errors, is_repl) {
+            Ok(program) => match self.interpreter.interpret(&program) {
+                Ok(()) => (Ok(()), Vec::new()),
+                Err(err) => (Err(RunError::Runtime), vec![LoxError::from(err)]),
+            },
+            Err(errors) => (Err(RunError::Compile), errors),
+        };
+        for err in &errors {
+            self.console
+                .diagnostic(&err.localize(self.options.catalog.as_ref()));
+        }
+        self.errors = errors;
+        result
+    }
+
+    // compile parses and resolves tokens into a program, printing warnings
+    // to the console, or returns the compile errors.
+    fn compile(
+        &self,
+        tokens: Vec<Token>,
+        errors: Vec<LoxError>,
+        is_repl: bool,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let console = self.console.clone();
+        let catalog = Arc::clone(&self.options.catalog);
+        let print_warning = move |span: Span, msg: &Message, _: Option<&FixIt>| {
+            let warning =
+                messages::WARNING.with(&[&span.start.line, &msg.localize(catalog.as_ref())]);
+            console.diagnostic(&warning.localize(catalog.as_ref()));
+        };
+        let parser = self.parser().with_warning_cb(&print_warning);
+        // The REPL also accepts bare expressions, printing their value.
+        let program = if is_repl {
+            parser.parse_repl(tokens)?
+        } else {
+            parser.parse(tokens)?
+        };
+        Resolver::new()
+            .with_warning_cb(&print_warning)
+            .resolve(&program)?;
+        Ok(program)
+    }
+
+    // report returns the RunReport of the last run, which exited with
+    // status after running for elapsed.
+    fn report(&self, status: i32, elapsed: Duration) -> RunReport {
+        let is_runtime_error = status == RunError::Runtime.exit_code();
+        let stack = match self.interpreter.error_stack() {
+            Some(stack) if is_runtime_error => stack.to_vec(),
+            _ => Vec::new(),
+        };
+        RunReport {
+            status,
+            errors: self.errors.clone(),
+            stack,
+            output_bytes: self.console.output_len(),
+            steps: self.interpreter.steps(),
+            elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loxrust::transcript::Transcript;
+
+    // replay replays the input of the transcript text in a REPL session,
+    // returning the transcript of the replayed session.
+    fn replay(text: &str) -> Transcript {
+        let recorded = Transcript::parse(text).unwrap();
+        let input: String = recorded.inputs().map(|l| format!("{}\n", l)).collect();
+        let options = Options {
+            stack_size: None,
+            repl_policy: ReplErrorPolicy::Reset,
+            unicode_identifiers: false,
+            dialect: Dialect::Standard,
+            record: None,
+            max_steps: None,
+            json_output: false,
+            trace: None,
+            catalog: Arc::default(),
+        };
+        let console = Console::new().with_transcript().with_quiet();
+        let mut session = Session::new(&options, console);
+        repl(&mut PlainEditor::new(input.as_bytes()), &mut session).unwrap();
+        session.console.transcript().unwrap()
+    }
+
+    #[test]
+    fn test_repl_transcript() {
+        let text = r#"
+# Output and diagnostics are recorded after the input producing them.
+> print 1 + 2;
+| 3
+> 1 + 2
+| 3
+> 1 + 2;
+> print "a"; print "b";
+| a
+| b
+> print -"a";
+! Operand must be a number.
+! [line 1]
+> print 1 +;
+! [line 1] Error at ';': Expect expression.
+> var a = 1;
+> fun add(b) { return a + b; }
+> add(2)
+| 3
+> a = "a";
+> add(2)
+! Operands must be two numbers or two strings.
+! [line 1]
+> print 1 < 2 < 3;
+! [line 1] Warning: Chained comparison compares a boolean to a value; combine the comparisons with 'and' instead.
+! Operands must be numbers.
+! [line 1]
+"#;
+        assert_eq!(replay(text), Transcript::parse(text).unwrap());
     }
 
-    let scanner = Scanner::new(Some(&print_error));
-    let (had_error, tokens) = scanner.scan_tokens(source);
-    for token in tokens {
-        println!("{:?}", token);
+    #[test]
+    fn test_repl_multi_line_input() {
+        let text = r#"
+# Input inside an unclosed brace or parenthesis continues on the next line.
+> fun add(a, b) {
+>   return a + b;
+> }
+> print add(
+>   1, 2);
+| 3
+# Errors are reported once the input is complete, on the line it is on.
+> {
+>   print -"a";
+> }
+! Operand must be a number.
+! [line 2]
+> print (1 +;
+! [line 1] Error at ';': Expect expression.
+# Strings and block comments may span lines.
+> print "a
+> b";
+| a
+| b
+> /* a
+> */ print 1;
+| 1
+# Input still incomplete at the end of input is run anyway.
+> {
+! [line 2] Error at end: Expect '}' after block.
+"#;
+        assert_eq!(replay(text), Transcript::parse(text).unwrap());
     }
-    had_error
 }
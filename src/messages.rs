@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// MessageId identifies a message of the catalog by its code, which is stable
+// across versions, together with its English template. A template refers to
+// the arguments of the message as {0}, {1} and so on. Other braces are
+// literal.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct MessageId {
+    pub code: &'static str,
+    pub template: &'static str,
+}
+
+impl MessageId {
+    const fn new(code: &'static str, template: &'static str) -> Self {
+        MessageId { code, template }
+    }
+
+    // with returns the message with args.
+    pub fn with(self, args: &[&dyn fmt::Display]) -> Message {
+        self.with_args(args.iter().map(|arg| Arg::from(arg.to_string())).collect())
+    }
+
+    // with_args is as with, but with args that may be messages themselves.
+    pub fn with_args(self, args: Vec<Arg>) -> Message {
+        let text = render(self.template, &Arg::localize_all(&args, &English));
+        Message {
+            id: Some(self),
+            args,
+            text,
+        }
+    }
+}
+
+// Arg is an argument of a Message. An argument that is made of messages is
+// localized along with the message it is an argument of, with one line per
+// message.
+#[derive(Debug, Clone)]
+pub enum Arg {
+    Text(String),
+    Messages(Vec<Message>),
+}
+
+impl Arg {
+    fn localize(&self, locale: &dyn Locale) -> String {
+        match self {
+            Arg::Text(text) => text.clone(),
+            Arg::Messages(messages) => {
+                let lines: Vec<String> = messages.iter().map(|msg| msg.localize(locale)).collect();
+                lines.join("\n")
+            }
+        }
+    }
+
+    fn localize_all(args: &[Arg], locale: &dyn Locale) -> Vec<String> {
+        args.iter().map(|arg| arg.localize(locale)).collect()
+    }
+}
+
+impl From<String> for Arg {
+    fn from(text: String) -> Self {
+        Arg::Text(text)
+    }
+}
+
+impl From<Message> for Arg {
+    fn from(message: Message) -> Self {
+        Arg::Messages(vec![message])
+    }
+}
+
+impl From<Vec<Message>> for Arg {
+    fn from(messages: Vec<Message>) -> Self {
+        Arg::Messages(messages)
+    }
+}
+
+// Message is a user-facing diagnostic: a message of the catalog together
+// with its arguments, or a message that is not in the catalog, such as the
+// error of a native function defined by an embedder, or a value thrown by
+// a program. Messages are equal if they read the same in English.
+#[derive(Debug, Clone)]
+pub struct Message {
+    id: Option<MessageId>,
+    args: Vec<Arg>,
+    // text is the message in English.
+    text: String,
+}
+
+impl Message {
+    // code returns the code of the message, or None if it is not in the
+    // catalog.
+    pub fn code(&self) -> Option<&'static str> {
+        self.id.map(|id| id.code)
+    }
+
+    // args returns the arguments of the message, in English.
+    pub fn args(&self) -> Vec<String> {
+        Arg::localize_all(&self.args, &English)
+    }
+
+    // as_str returns the message in English.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    // localize returns the message in locale, falling back to English if
+    // the locale does not translate it.
+    pub fn localize(&self, locale: &dyn Locale) -> String {
+        match self.id {
+            Some(id) => {
+                let template = locale.template(id.code).unwrap_or(id.template);
+                render(template, &Arg::localize_all(&self.args, locale))
+            }
+            None => self.text.clone(),
+        }
+    }
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl PartialEq<str> for Message {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for Message {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl From<MessageId> for Message {
+    fn from(id: MessageId) -> Self {
+        id.with(&[])
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message {
+            id: None,
+            args: Vec::new(),
+            text,
+        }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::from(text.to_owned())
+    }
+}
+
+// render replaces the placeholders of template with args. Placeholders of
+// missing arguments are left as they are.
+fn render(template: &str, args: &[String]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let arg = rest[1..].find('}').and_then(|end| {
+            let index: usize = rest[1..=end].parse().ok()?;
+            Some((args.get(index)?, end + 2))
+        });
+        match arg {
+            Some((arg, len)) => {
+                rendered.push_str(arg);
+                rest = &rest[len..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+// Locale translates the messages of the catalog. template returns the
+// template of the message with code, or None to use the English template.
+pub trait Locale {
+    fn template(&self, code: &str) -> Option<&str>;
+}
+
+// English is the locale of the built-in templates.
+pub struct English;
+
+impl Locale for English {
+    fn template(&self, _code: &str) -> Option<&str> {
+        None
+    }
+}
+
+// Catalog is a Locale of translated templates, e.g. as loaded from a file
+// by Catalog::parse. Messages the catalog does not translate are in
+// English.
+#[derive(Debug, Default, Clone)]
+pub struct Catalog {
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Catalog::default()
+    }
+
+    // insert translates the message with code to template.
+    pub fn insert(&mut self, code: &str, template: &str) {
+        self.templates.insert(code.to_owned(), template.to_owned());
+    }
+
+    // parse parses a catalog with one "CODE = template" line per message.
+    // Blank lines and lines starting with '#' are ignored. In templates,
+    // "\n" stands for a line break, and "\\" for a backslash.
+    pub fn parse(text: &str) -> Result<Catalog, Message> {
+        let mut catalog = Catalog::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (code, template) = line
+                .split_once('=')
+                .ok_or_else(|| INVALID_CATALOG_LINE.with(&[&(i + 1)]))?;
+            let code = code.trim();
+            if !MESSAGES.iter().any(|id| id.code == code) {
+                return Err(UNKNOWN_MESSAGE_CODE.with(&[&code, &(i + 1)]));
+            }
+            catalog.insert(code, &unescape(template.trim()));
+        }
+        Ok(catalog)
+    }
+
+    // english returns the catalog of all messages in English, in the
+    // format read by parse, as a starting point for translations.
+    pub fn english() -> String {
+        MESSAGES
+            .iter()
+            .map(|id| format!("{} = {}\n", id.code, escape(id.template)))
+            .collect()
+    }
+}
+
+impl Locale for Catalog {
+    fn template(&self, code: &str) -> Option<&str> {
+        self.templates.get(code).map(String::as_str)
+    }
+}
+
+fn escape(template: &str) -> String {
+    template.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(template: &str) -> String {
+    let mut unescaped = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// The messages of the catalog. Codes are prefixed by the stage reporting
+// them: D for the layout of diagnostics, S for the scanner, P for the
+// parser, R for the resolver, W for warnings, E for runtime errors, N for
+// the natives of the standard library, and T for tools.
+
+pub const ERROR: MessageId = MessageId::new("D001", "[line {0}] Error: {1}");
+pub const ERROR_AT: MessageId = MessageId::new("D002", "[line {0}] Error at '{1}': {2}");
+pub const ERROR_AT_END: MessageId = MessageId::new("D003", "[line {0}] Error at end: {1}");
+pub const RUNTIME_ERROR: MessageId = MessageId::new("D004", "{0}\n[line {1}]");
+pub const WARNING: MessageId = MessageId::new("D005", "[line {0}] Warning: {1}");
+
+pub const TOO_MANY_ERRORS: MessageId = MessageId::new("S001", "Too many errors, stopping.");
+pub const UNTERMINATED_BLOCK_COMMENT: MessageId =
+    MessageId::new("S002", "Unterminated block comment.");
+pub const UNTERMINATED_STRING: MessageId = MessageId::new("S003", "Unterminated string.");
+pub const UNTERMINATED_INTERPOLATION: MessageId =
+    MessageId::new("S004", "Unterminated interpolation.");
+pub const INVALID_UNICODE_ESCAPE: MessageId =
+    MessageId::new("S005", "Invalid unicode escape sequence.");
+pub const INVALID_ESCAPE: MessageId = MessageId::new("S006", "Invalid escape sequence '\\{0}'.");
+pub const INVALID_TRAILING_ESCAPE: MessageId =
+    MessageId::new("S007", "Invalid escape sequence '\\'.");
+pub const INVALID_NUMBER: MessageId = MessageId::new("S008", "Invalid number literal '{0}'.");
+pub const UNEXPECTED_CHARACTER: MessageId = MessageId::new("S009", "Unexpected character '{0}'.");
+pub const UNEXPECTED_CHARACTERS: MessageId = MessageId::new("S010", "Unexpected characters '{0}'.");
+pub const INVALID_UTF8: MessageId = MessageId::new("S011", "Invalid UTF-8 sequence.");
+
+pub const EXPECT_EXPRESSION: MessageId = MessageId::new("P001", "Expect expression.");
+pub const EXPECT_END_OF_EXPRESSION: MessageId = MessageId::new("P002", "Expect end of expression.");
+pub const EXPECT_MODULE_PATH: MessageId = MessageId::new("P003", "Expect module path string.");
+pub const EXPECT_MODULE_NAME: MessageId = MessageId::new("P004", "Expect module name after 'as'.");
+pub const EXPECT_AS: MessageId = MessageId::new(
+    "P005",
+    "Expect 'as' and a module name, as the file name is not an identifier.",
+);
+pub const EXPECT_SEMICOLON_AFTER_IMPORT: MessageId =
+    MessageId::new("P006", "Expect ';' after import.");
+pub const EXPECT_CLASS_NAME: MessageId = MessageId::new("P007", "Expect class name.");
+pub const EXPECT_SUPERCLASS_NAME: MessageId = MessageId::new("P008", "Expect superclass name.");
+pub const EXPECT_CLASS_BODY: MessageId = MessageId::new("P009", "Expect '{' before class body.");
+pub const EXPECT_CLASS_BODY_END: MessageId = MessageId::new("P010", "Expect '}' after class body.");
+pub const EXPECT_FUNCTION_NAME: MessageId = MessageId::new("P011", "Expect function name.");
+pub const EXPECT_METHOD_NAME: MessageId = MessageId::new("P012", "Expect method name.");
+pub const EXPECT_FUNCTION_PARAMETERS: MessageId =
+    MessageId::new("P013", "Expect '(' after function name.");
+pub const EXPECT_METHOD_PARAMETERS: MessageId =
+    MessageId::new("P014", "Expect '(' after method name.");
+pub const EXPECT_LAMBDA_PARAMETERS: MessageId = MessageId::new("P015", "Expect '(' after 'fun'.");
+pub const TOO_MANY_PARAMETERS: MessageId =
+    MessageId::new("P016", "Cannot have more than 255 parameters.");
+pub const EXPECT_PARAMETER_NAME: MessageId = MessageId::new("P017", "Expect parameter name.");
+pub const EXPECT_PARAMETERS_END: MessageId = MessageId::new("P018", "Expect ')' after parameters.");
+pub const EXPECT_FUNCTION_BODY: MessageId =
+    MessageId::new("P019", "Expect '{' before function body.");
+pub const EXPECT_METHOD_BODY: MessageId = MessageId::new("P020", "Expect '{' before method body.");
+pub const EXPECT_VARIABLE_NAME: MessageId = MessageId::new("P021", "Expect variable name.");
+pub const EXPECT_SEMICOLON_AFTER_VARIABLE: MessageId =
+    MessageId::new("P022", "Expect ';' after variable declaration.");
+pub const EXPECT_CONSTANT_NAME: MessageId = MessageId::new("P023", "Expect constant name.");
+pub const EXPECT_CONSTANT_VALUE: MessageId =
+    MessageId::new("P024", "Expect '=' after constant name.");
+pub const EXPECT_SEMICOLON_AFTER_CONSTANT: MessageId =
+    MessageId::new("P025", "Expect ';' after constant declaration.");
+pub const EXPECT_SEMICOLON_AFTER_VALUE: MessageId =
+    MessageId::new("P026", "Expect ';' after value.");
+pub const EXPECT_SEMICOLON_AFTER_BREAK: MessageId =
+    MessageId::new("P027", "Expect ';' after 'break'.");
+pub const EXPECT_SEMICOLON_AFTER_CONTINUE: MessageId =
+    MessageId::new("P028", "Expect ';' after 'continue'.");
+pub const EXPECT_SEMICOLON_AFTER_RETURN: MessageId =
+    MessageId::new("P029", "Expect ';' after return value.");
+pub const EXPECT_WHILE_CONDITION: MessageId = MessageId::new("P030", "Expect '(' after 'while'.");
+pub const EXPECT_WHILE_CONDITION_END: MessageId =
+    MessageId::new("P031", "Expect ')' after condition.");
+pub const EXPECT_SEMICOLON_AFTER_EXPRESSION: MessageId =
+    MessageId::new("P032", "Expect ';' after expression.");
+pub const EXPECT_FOR_CLAUSES: MessageId = MessageId::new("P033", "Expect '(' after 'for'.");
+pub const EXPECT_SEMICOLON_AFTER_LOOP_CONDITION: MessageId =
+    MessageId::new("P034", "Expect ';' after loop condition.");
+pub const EXPECT_FOR_CLAUSES_END: MessageId =
+    MessageId::new("P035", "Expect ')' after for clauses.");
+pub const EXPECT_IF_CONDITION: MessageId = MessageId::new("P036", "Expect '(' after 'if'.");
+pub const EXPECT_IF_CONDITION_END: MessageId =
+    MessageId::new("P037", "Expect ')' after if condition.");
+pub const EXPECT_TRY_BLOCK: MessageId = MessageId::new("P038", "Expect '{' after 'try'.");
+pub const EXPECT_CATCH: MessageId = MessageId::new("P039", "Expect 'catch' after try block.");
+pub const EXPECT_CATCH_VARIABLE: MessageId = MessageId::new("P040", "Expect '(' after 'catch'.");
+pub const EXPECT_CATCH_VARIABLE_END: MessageId =
+    MessageId::new("P041", "Expect ')' after variable name.");
+pub const EXPECT_CATCH_BLOCK: MessageId = MessageId::new("P042", "Expect '{' after catch clause.");
+pub const EXPECT_BLOCK_END: MessageId = MessageId::new("P043", "Expect '}' after block.");
+pub const INVALID_COMPOUND_ASSIGNMENT_TARGET: MessageId =
+    MessageId::new("P044", "Invalid compound assignment target.");
+pub const INVALID_ASSIGNMENT_TARGET: MessageId =
+    MessageId::new("P045", "Invalid assignment target.");
+pub const EXPECT_PROPERTY_NAME: MessageId =
+    MessageId::new("P046", "Expect property name after '.'.");
+pub const EXPECT_INDEX_END: MessageId = MessageId::new("P047", "Expect ']' after index.");
+pub const TOO_MANY_ARGUMENTS: MessageId =
+    MessageId::new("P048", "Cannot have more than 255 arguments.");
+pub const EXPECT_ARGUMENTS_END: MessageId = MessageId::new("P049", "Expect ')' after arguments.");
+pub const EXPECT_SUPER_DOT: MessageId = MessageId::new("P050", "Expect '.' after 'super'.");
+pub const EXPECT_SUPERCLASS_METHOD_NAME: MessageId =
+    MessageId::new("P051", "Expect superclass method name.");
+pub const EXPECT_GROUPING_END: MessageId = MessageId::new("P052", "Expect ')' after expression.");
+pub const EXPECT_LIST_END: MessageId = MessageId::new("P053", "Expect ']' after list elements.");
+pub const EXPECT_MAP_COLON: MessageId = MessageId::new("P054", "Expect ':' after map key.");
+pub const EXPECT_MAP_END: MessageId = MessageId::new("P055", "Expect '}' after map entries.");
+pub const EXPECT_INTERPOLATION_END: MessageId =
+    MessageId::new("P056", "Expect '}' after interpolated expression.");
+
+pub const ALREADY_DECLARED: MessageId = MessageId::new(
+    "R001",
+    "Variable with this name already declared in this scope.",
+);
+pub const ASSIGN_TO_CONSTANT: MessageId = MessageId::new("R002", "Cannot assign to a constant.");
+pub const SUPER_OUTSIDE_CLASS: MessageId =
+    MessageId::new("R003", "Cannot use 'super' outside of a class.");
+pub const SUPER_WITHOUT_SUPERCLASS: MessageId =
+    MessageId::new("R004", "Cannot use 'super' in a class with no superclass.");
+pub const THIS_OUTSIDE_CLASS: MessageId =
+    MessageId::new("R005", "Cannot use 'this' outside of a class.");
+pub const READ_IN_OWN_INITIALIZER: MessageId =
+    MessageId::new("R006", "Cannot read local variable in its own initializer.");
+pub const BREAK_OUTSIDE_LOOP: MessageId =
+    MessageId::new("R007", "Cannot use 'break' outside of a loop.");
+pub const FIELD_ALREADY_DECLARED: MessageId = MessageId::new(
+    "R008",
+    "Field with this name already declared in this class.",
+);
+pub const INHERIT_FROM_SELF: MessageId =
+    MessageId::new("R009", "A class cannot inherit from itself.");
+pub const GETTER_INITIALIZER: MessageId =
+    MessageId::new("R010", "An initializer cannot be a getter.");
+pub const CONTINUE_OUTSIDE_LOOP: MessageId =
+    MessageId::new("R011", "Cannot use 'continue' outside of a loop.");
+pub const RETURN_FROM_TOP_LEVEL: MessageId =
+    MessageId::new("R012", "Cannot return from top-level code.");
+pub const RETURN_VALUE_FROM_INITIALIZER: MessageId =
+    MessageId::new("R013", "Cannot return a value from an initializer.");
+
+pub const UNUSED_LOCAL: MessageId = MessageId::new("W001", "Local variable '{0}' is never used.");
+pub const ASSIGNMENT_AS_CONDITION: MessageId = MessageId::new(
+    "W002",
+    "Assignment used as a condition; use '==' to compare.",
+);
+pub const CHAINED_COMPARISON: MessageId = MessageId::new(
+    "W003",
+    "Chained comparison compares a boolean to a value; \
+     combine the comparisons with 'and' instead.",
+);
+
+pub const UNDEFINED_VARIABLE: MessageId = MessageId::new("E001", "Undefined variable '{0}'.");
+pub const ASSIGN_TO_CONSTANT_VARIABLE: MessageId =
+    MessageId::new("E002", "Cannot assign to constant '{0}'.");
+pub const UNDEFINED_PROPERTY: MessageId = MessageId::new("E003", "Undefined property '{0}'.");
+pub const UNDEFINED_MODULE_PROPERTY: MessageId =
+    MessageId::new("E004", "Undefined property '{0}' of module '{1}'.");
+pub const INVALID_MAP_KEY: MessageId = MessageId::new(
+    "E005",
+    "Map keys must be strings, numbers, booleans or nil.",
+);
+pub const CLOCK_NOT_GRANTED: MessageId =
+    MessageId::new("E006", "The clock capability is not granted.");
+pub const CLOCK_BEFORE_EPOCH: MessageId =
+    MessageId::new("E007", "The system clock is before the Unix epoch.");
+pub const NOT_CALLABLE: MessageId = MessageId::new("E008", "Can only call functions and classes.");
+pub const NOT_AN_OBJECT: MessageId = MessageId::new("E009", "Only instances have properties.");
+pub const IMPORTS_NOT_GRANTED: MessageId =
+    MessageId::new("E010", "The imports capability is not granted.");
+pub const CANNOT_IMPORT: MessageId = MessageId::new("E011", "Cannot import \"{0}\": {1}.");
+pub const IMPORT_CYCLE: MessageId = MessageId::new("E012", "Import cycle: {0}.");
+pub const ERROR_IN_MODULE: MessageId = MessageId::new("E013", "Error in module \"{0}\":\n{1}");
+pub const STEP_LIMIT_EXCEEDED: MessageId = MessageId::new("E014", "Step limit exceeded.");
+pub const WRONG_ARITY: MessageId = MessageId::new("E015", "Expected {0} arguments but got {1}.");
+pub const OPERAND_NOT_NUMBER: MessageId = MessageId::new("E016", "Operand must be a number.");
+pub const OPERANDS_NOT_NUMBERS: MessageId = MessageId::new("E017", "Operands must be numbers.");
+pub const UNDEFINED_KEY: MessageId = MessageId::new("E018", "Undefined key {0}.");
+pub const NOT_INDEXABLE: MessageId = MessageId::new("E019", "Only lists and maps can be indexed.");
+pub const LIST_INDEX_NOT_INTEGER: MessageId =
+    MessageId::new("E020", "List index must be an integer.");
+pub const LIST_INDEX_OUT_OF_RANGE: MessageId = MessageId::new("E021", "List index out of range.");
+pub const OPERANDS_NOT_ADDABLE: MessageId =
+    MessageId::new("E022", "Operands must be two numbers or two strings.");
+pub const NOT_AN_INSTANCE: MessageId = MessageId::new("E023", "Only instances have fields.");
+pub const INVALID_INCREMENT_TARGET: MessageId = MessageId::new(
+    "E024",
+    "Operand of '{0}' must be a variable, property or element.",
+);
+pub const SUPER_OUTSIDE_SUBCLASS: MessageId =
+    MessageId::new("E025", "Cannot use 'super' outside of a subclass.");
+pub const SUPERCLASS_NOT_CLASS: MessageId = MessageId::new("E026", "Superclass must be a class.");
+pub const STACK_OVERFLOW: MessageId = MessageId::new("E027", "Stack overflow.");
+pub const WRONG_ARITY_RANGE: MessageId =
+    MessageId::new("E028", "Expected {0} to {1} arguments but got {2}.");
+
+pub const POP_FROM_EMPTY_LIST: MessageId = MessageId::new("N001", "Cannot pop from an empty list.");
+pub const ARGUMENT_NOT_STRING: MessageId = MessageId::new("N002", "Argument must be a string.");
+pub const ARGUMENT_NOT_LIST: MessageId = MessageId::new("N003", "Argument must be a list.");
+pub const ARGUMENT_NOT_MAP: MessageId = MessageId::new("N004", "Argument must be a map.");
+pub const ARGUMENT_NOT_NUMBER: MessageId = MessageId::new("N005", "Argument must be a number.");
+pub const INDEX_NOT_NATURAL: MessageId =
+    MessageId::new("N006", "Index must be a non-negative integer.");
+pub const ARGUMENT_NOT_SIZED: MessageId =
+    MessageId::new("N007", "Argument must be a string, list or map.");
+pub const SUBSTRING_OUT_OF_RANGE: MessageId =
+    MessageId::new("N008", "Substring start out of range.");
+
+pub const RENAME_WITH_ERRORS: MessageId =
+    MessageId::new("T001", "Cannot rename in a program with errors.");
+pub const NOTHING_TO_RENAME: MessageId = MessageId::new("T002", "No variable to rename here.");
+pub const RENAME_IMPORT_NAME: MessageId = MessageId::new(
+    "T003",
+    "Cannot rename '{0}', as it is named by an import path.",
+);
+pub const INVALID_NAME: MessageId = MessageId::new("T004", "'{0}' is not a valid name.");
+pub const RENAME_CONFLICT: MessageId = MessageId::new(
+    "T005",
+    "Renaming '{0}' to '{1}' would change what the program refers to.",
+);
+pub const INVALID_CATALOG_LINE: MessageId =
+    MessageId::new("T006", "Expect 'CODE = template' on line {0}.");
+pub const UNKNOWN_MESSAGE_CODE: MessageId =
+    MessageId::new("T007", "Unknown message code '{0}' on line {1}.");
+
+// MESSAGES is the catalog of all messages.
+pub const MESSAGES: &[MessageId] = &[
+    ERROR,
+    ERROR_AT,
+    ERROR_AT_END,
+    RUNTIME_ERROR,
+    WARNING,
+    TOO_MANY_ERRORS,
+    UNTERMINATED_BLOCK_COMMENT,
+    UNTERMINATED_STRING,
+    UNTERMINATED_INTERPOLATION,
+    INVALID_UNICODE_ESCAPE,
+    INVALID_ESCAPE,
+    INVALID_TRAILING_ESCAPE,
+    INVALID_NUMBER,
+    UNEXPECTED_CHARACTER,
+    UNEXPECTED_CHARACTERS,
+    INVALID_UTF8,
+    EXPECT_EXPRESSION,
+    EXPECT_END_OF_EXPRESSION,
+    EXPECT_MODULE_PATH,
+    EXPECT_MODULE_NAME,
+    EXPECT_AS,
+    EXPECT_SEMICOLON_AFTER_IMPORT,
+    EXPECT_CLASS_NAME,
+    EXPECT_SUPERCLASS_NAME,
+    EXPECT_CLASS_BODY,
+    EXPECT_CLASS_BODY_END,
+    EXPECT_FUNCTION_NAME,
+    EXPECT_METHOD_NAME,
+    EXPECT_FUNCTION_PARAMETERS,
+    EXPECT_METHOD_PARAMETERS,
+    EXPECT_LAMBDA_PARAMETERS,
+    TOO_MANY_PARAMETERS,
+    EXPECT_PARAMETER_NAME,
+    EXPECT_PARAMETERS_END,
+    EXPECT_FUNCTION_BODY,
+    EXPECT_METHOD_BODY,
+    EXPECT_VARIABLE_NAME,
+    EXPECT_SEMICOLON_AFTER_VARIABLE,
+    EXPECT_CONSTANT_NAME,
+    EXPECT_CONSTANT_VALUE,
+    EXPECT_SEMICOLON_AFTER_CONSTANT,
+    EXPECT_SEMICOLON_AFTER_VALUE,
+    EXPECT_SEMICOLON_AFTER_BREAK,
+    EXPECT_SEMICOLON_AFTER_CONTINUE,
+    EXPECT_SEMICOLON_AFTER_RETURN,
+    EXPECT_WHILE_CONDITION,
+    EXPECT_WHILE_CONDITION_END,
+    EXPECT_SEMICOLON_AFTER_EXPRESSION,
+    EXPECT_FOR_CLAUSES,
+    EXPECT_SEMICOLON_AFTER_LOOP_CONDITION,
+    EXPECT_FOR_CLAUSES_END,
+    EXPECT_IF_CONDITION,
+    EXPECT_IF_CONDITION_END,
+    EXPECT_TRY_BLOCK,
+    EXPECT_CATCH,
+    EXPECT_CATCH_VARIABLE,
+    EXPECT_CATCH_VARIABLE_END,
+    EXPECT_CATCH_BLOCK,
+    EXPECT_BLOCK_END,
+    INVALID_COMPOUND_ASSIGNMENT_TARGET,
+    INVALID_ASSIGNMENT_TARGET,
+    EXPECT_PROPERTY_NAME,
+    EXPECT_INDEX_END,
+    TOO_MANY_ARGUMENTS,
+    EXPECT_ARGUMENTS_END,
+    EXPECT_SUPER_DOT,
+    EXPECT_SUPERCLASS_METHOD_NAME,
+    EXPECT_GROUPING_END,
+    EXPECT_LIST_END,
+    EXPECT_MAP_COLON,
+    EXPECT_MAP_END,
+    EXPECT_INTERPOLATION_END,
+    ALREADY_DECLARED,
+    ASSIGN_TO_CONSTANT,
+    SUPER_OUTSIDE_CLASS,
+    SUPER_WITHOUT_SUPERCLASS,
+    THIS_OUTSIDE_CLASS,
+    READ_IN_OWN_INITIALIZER,
+    BREAK_OUTSIDE_LOOP,
+    FIELD_ALREADY_DECLARED,
+    INHERIT_FROM_SELF,
+    GETTER_INITIALIZER,
+    CONTINUE_OUTSIDE_LOOP,
+    RETURN_FROM_TOP_LEVEL,
+    RETURN_VALUE_FROM_INITIALIZER,
+    UNUSED_LOCAL,
+    ASSIGNMENT_AS_CONDITION,
+    CHAINED_COMPARISON,
+    UNDEFINED_VARIABLE,
+    ASSIGN_TO_CONSTANT_VARIABLE,
+    UNDEFINED_PROPERTY,
+    UNDEFINED_MODULE_PROPERTY,
+    INVALID_MAP_KEY,
+    CLOCK_NOT_GRANTED,
+    CLOCK_BEFORE_EPOCH,
+    NOT_CALLABLE,
+    NOT_AN_OBJECT,
+    IMPORTS_NOT_GRANTED,
+    CANNOT_IMPORT,
+    IMPORT_CYCLE,
+    ERROR_IN_MODULE,
+    STEP_LIMIT_EXCEEDED,
+    WRONG_ARITY,
+    OPERAND_NOT_NUMBER,
+    OPERANDS_NOT_NUMBERS,
+    UNDEFINED_KEY,
+    NOT_INDEXABLE,
+    LIST_INDEX_NOT_INTEGER,
+    LIST_INDEX_OUT_OF_RANGE,
+    OPERANDS_NOT_ADDABLE,
+    NOT_AN_INSTANCE,
+    INVALID_INCREMENT_TARGET,
+    SUPER_OUTSIDE_SUBCLASS,
+    SUPERCLASS_NOT_CLASS,
+    STACK_OVERFLOW,
+    WRONG_ARITY_RANGE,
+    POP_FROM_EMPTY_LIST,
+    ARGUMENT_NOT_STRING,
+    ARGUMENT_NOT_LIST,
+    ARGUMENT_NOT_MAP,
+    ARGUMENT_NOT_NUMBER,
+    INDEX_NOT_NATURAL,
+    ARGUMENT_NOT_SIZED,
+    SUBSTRING_OUT_OF_RANGE,
+    RENAME_WITH_ERRORS,
+    NOTHING_TO_RENAME,
+    RENAME_IMPORT_NAME,
+    INVALID_NAME,
+    RENAME_CONFLICT,
+    INVALID_CATALOG_LINE,
+    UNKNOWN_MESSAGE_CODE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_catalog_codes() {
+        let codes: HashSet<&str> = MESSAGES.iter().map(|id| id.code).collect();
+        assert_eq!(codes.len(), MESSAGES.len());
+    }
+
+    #[test]
+    fn test_render() {
+        let message = UNDEFINED_MODULE_PROPERTY.with(&[&"f", &"lib"]);
+        assert_eq!(message, "Undefined property 'f' of module 'lib'.");
+        assert_eq!(message.code(), Some("E004"));
+        assert_eq!(message.args(), ["f", "lib"]);
+        // Braces that are not placeholders are literal.
+        let message = Message::from(EXPECT_CLASS_BODY);
+        assert_eq!(message.as_str(), "Expect '{' before class body.");
+        assert_eq!(render("{1} {x} {0", &[String::from("a")]), "{1} {x} {0");
+        assert_eq!(Message::from("thrown").code(), None);
+    }
+
+    #[test]
+    fn test_localize() {
+        let mut catalog = Catalog::new();
+        catalog.insert("E004", "Le module '{1}' n'a pas de propriété '{0}'.");
+        let message = UNDEFINED_MODULE_PROPERTY.with(&[&"f", &"lib"]);
+        assert_eq!(
+            message.localize(&catalog),
+            "Le module 'lib' n'a pas de propriété 'f'."
+        );
+        assert_eq!(message.localize(&English), message.as_str());
+        // Untranslated messages, and those not in the catalog, are English.
+        let message = UNDEFINED_VARIABLE.with(&[&"a"]);
+        assert_eq!(message.localize(&catalog), "Undefined variable 'a'.");
+        assert_eq!(Message::from("thrown").localize(&catalog), "thrown");
+    }
+
+    #[test]
+    fn test_localize_nested() {
+        let mut catalog = Catalog::new();
+        catalog.insert("E013", "Erreur dans le module \"{0}\" :\n{1}");
+        catalog.insert("E001", "Variable '{0}' non définie.");
+        let errors = vec![
+            UNDEFINED_VARIABLE.with(&[&"a"]),
+            UNDEFINED_VARIABLE.with(&[&"b"]),
+        ];
+        let message = ERROR_IN_MODULE.with_args(vec![Arg::from("lib".to_owned()), errors.into()]);
+        assert_eq!(
+            message,
+            "Error in module \"lib\":\nUndefined variable 'a'.\nUndefined variable 'b'."
+        );
+        assert_eq!(
+            message.localize(&catalog),
+            "Erreur dans le module \"lib\" :\nVariable 'a' non définie.\nVariable 'b' non définie."
+        );
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let text = "# Deutsch\n\nE001 = Undefinierte Variable '{0}'.\nD004 = {0}\\n[Zeile {1}]\n";
+        let catalog = Catalog::parse(text).unwrap();
+        assert_eq!(
+            catalog.template("E001"),
+            Some("Undefinierte Variable '{0}'.")
+        );
+        assert_eq!(catalog.template("D004"), Some("{0}\n[Zeile {1}]"));
+        assert_eq!(catalog.template("E002"), None);
+        assert_eq!(
+            Catalog::parse("E001").unwrap_err(),
+            "Expect 'CODE = template' on line 1."
+        );
+        assert_eq!(
+            Catalog::parse("\nX001 = x").unwrap_err(),
+            "Unknown message code 'X001' on line 2."
+        );
+        // The English catalog reads back as itself.
+        let english = Catalog::parse(&Catalog::english()).unwrap();
+        for id in MESSAGES {
+            assert_eq!(english.template(id.code), Some(id.template));
+        }
+    }
+}
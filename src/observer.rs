@@ -0,0 +1,22 @@
+use super::expr::Expr;
+use super::messages::Message;
+use super::span::Span;
+use super::token::Token;
+
+// FrontendObserver receives events from the frontend stages (scanner and
+// parser) as they happen, allowing external tools such as editors and
+// analyzers to build indexes or live views without re-walking the output.
+// All methods default to doing nothing, so an observer only implements the
+// events it is interested in.
+pub trait FrontendObserver {
+    // on_token is called for each token produced by the scanner.
+    fn on_token(&self, _token: &Token) {}
+
+    // on_node_parsed is called for each expression node produced by the
+    // parser, children before their parents.
+    fn on_node_parsed(&self, _expr: &Expr) {}
+
+    // on_diagnostic is called for each error reported while scanning or
+    // parsing, in addition to the errors returned by the stage.
+    fn on_diagnostic(&self, _span: Span, _msg: &Message) {}
+}
@@ -0,0 +1,2273 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::rc::Rc;
+
+use super::error::{FixIt, LoxError};
+use super::expr::Expr;
+use super::messages::{self, Message, MessageId};
+use super::observer::FrontendObserver;
+use super::scanner::Scanner;
+use super::span::{Position, Span};
+use super::stmt::{FunctionStmt, Stmt, VarStmt};
+use super::token::{Lexeme, Literal, Token, TokenType};
+
+// WarningCallback is called with the span, message and optional FixIt of
+// each warning reported while parsing or resolving.
+pub type WarningCallback = dyn Fn(Span, &Message, Option<&FixIt>);
+
+// TokenExpander is called with each token before it is parsed, other than
+// the final Eof, and pushes the tokens to parse in its place, e.g. the token
+// itself, or the Lox tokens a domain-specific shorthand stands for. Tokens
+// made with Token::expanded keep the span of the token they replace, so
+// that diagnostics point into the original source.
+pub type TokenExpander = dyn Fn(Token, &mut Vec<Token>);
+
+// Dialect selects the variant of Lox accepted by the parser, easing the
+// migration of code from other Lox implementations.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Dialect {
+    // Standard is Lox as in Crafting Interpreters, where print is a
+    // statement.
+    Standard,
+    // PrintFunctions has no print statement. Instead print is a native
+    // function, print(x), along with println(x), which the Interpreter
+    // defines when running in this dialect.
+    PrintFunctions,
+}
+
+// A Parser turns a sequence of Tokens into an abstract syntax tree, using
+// recursive descent over the grammar of RULES, with the binary operators
+// parsed by precedence climbing over BINARY_LEVELS. Parser::grammar renders
+// the grammar as EBNF, as printed by "loxrust grammar".
+pub struct Parser<'a> {
+    // warning_cb is an optional WarningCallback that will be notified for
+    // each (if any) suspicious but valid construct encountered while parsing.
+    warning_cb: Option<&'a WarningCallback>,
+
+    // observer is an optional FrontendObserver that will be notified of each
+    // node parsed and each error while parsing.
+    observer: Option<&'a dyn FrontendObserver>,
+
+    // dialect is the variant of Lox to parse.
+    dialect: Dialect,
+
+    // expander is an optional TokenExpander applied to the tokens before
+    // they are parsed.
+    expander: Option<&'a TokenExpander>,
+
+    // unicode_identifiers is true if identifiers may contain non-ASCII
+    // characters, see with_unicode_identifiers.
+    unicode_identifiers: bool,
+}
+
+#[allow(clippy::new_without_default)]
+impl<'a> Parser<'a> {
+    pub fn new() -> Self {
+        Parser {
+            warning_cb: None,
+            observer: None,
+            dialect: Dialect::Standard,
+            expander: None,
+            unicode_identifiers: false,
+        }
+    }
+
+    // with_dialect makes the parser parse dialect instead of standard Lox.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // with_unicode_identifiers makes the parser accept the identifiers of a
+    // Scanner with unicode identifiers enabled, such as the module names
+    // taken from the file names of imports. It should match the scanner of
+    // the tokens parsed.
+    pub fn with_unicode_identifiers(mut self, enabled: bool) -> Self {
+        self.unicode_identifiers = enabled;
+        self
+    }
+
+    // with_warning_cb installs warning_cb on the parser, to be called for
+    // each warning while parsing. Warnings do not make the parse fail.
+    pub fn with_warning_cb(mut self, warning_cb: &'a WarningCallback) -> Self {
+        self.warning_cb = Some(warning_cb);
+        self
+    }
+
+    // with_observer installs observer on the parser, to be notified of each
+    // node parsed and each error while parsing.
+    pub fn with_observer(mut self, observer: &'a dyn FrontendObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    // with_token_expander installs expander on the parser, to transform the
+    // tokens before they are parsed.
+    pub fn with_token_expander(mut self, expander: &'a TokenExpander) -> Self {
+        self.expander = Some(expander);
+        self
+    }
+
+    // parse parses tokens, as produced by a Scanner, into a program (a list
+    // of statements). parse returns the errors encountered if tokens could
+    // not be parsed. Tokens are pulled from tokens as they are parsed, so
+    // e.g. the tokens of Scanner::tokens are scanned on demand.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "parse", skip_all)
+    )]
+    pub fn parse<I: IntoIterator<Item = Token>>(
+        &self,
+        tokens: I,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        ParserContext::new(tokens.into_iter(), self).parse()
+    }
+
+    // parse_expression parses tokens into a single expression, which must
+    // make up all of tokens. Errors are returned as for parse.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "parse", skip_all)
+    )]
+    pub fn parse_expression<I: IntoIterator<Item = Token>>(
+        &self,
+        tokens: I,
+    ) -> Result<Expr, Vec<LoxError>> {
+        ParserContext::new(tokens.into_iter(), self).parse_expression()
+    }
+
+    // parse_repl parses a line of REPL input into a program. Unlike parse, a
+    // line that is a single expression without a trailing semicolon is
+    // accepted, and parsed as a print statement of the expression so that
+    // its value is shown. If the line is neither, the errors of parsing it
+    // as a program are returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "parse", skip_all)
+    )]
+    pub fn parse_repl<I: IntoIterator<Item = Token>>(
+        &self,
+        tokens: I,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let tokens: Vec<Token> = tokens.into_iter().collect();
+        let errors = match ParserContext::new(tokens.clone().into_iter(), self).parse() {
+            Ok(program) => return Ok(program),
+            Err(errors) => errors,
+        };
+        // The fallback is parsed quietly, as anything worth reporting has
+        // already been reported by the attempt to parse a program.
+        let mut ctx = ParserContext::new(tokens.into_iter(), self);
+        ctx.warning_cb = None;
+        ctx.observer = None;
+        match ctx.parse_expression() {
+            Ok(expr) => Ok(vec![Stmt::make_print(expr)]),
+            Err(_) => Err(errors),
+        }
+    }
+
+    // grammar returns the grammar accepted by the parser as EBNF, one rule
+    // per line.
+    pub fn grammar(&self) -> String {
+        let mut rules: Vec<(&str, String)> = Vec::new();
+        for rule in RULES {
+            if rule.dialect.is_some_and(|dialect| dialect != self.dialect) {
+                continue;
+            }
+            if rule.name.is_empty() {
+                for (i, level) in BINARY_LEVELS.iter().enumerate() {
+                    let operand = BINARY_LEVELS.get(i + 1).map_or("unary", |next| next.name);
+                    rules.push((level.name, level.production(operand)));
+                }
+            } else {
+                rules.push((rule.name, rule.production.to_string()));
+            }
+        }
+        let width = rules.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        rules
+            .iter()
+            .map(|(name, production)| {
+                format!("{:width$} → {} ;\n", name, production, width = width)
+            })
+            .collect()
+    }
+
+    // is_incomplete returns true if tokens are the start of a valid program
+    // that ends early, inside an unclosed bracket of any kind. The REPL uses
+    // this to read more lines of input, rather than reporting syntax errors
+    // for e.g. the first line of a function declaration.
+    pub fn is_incomplete(&self, tokens: &[Token]) -> bool {
+        let depth = tokens
+            .iter()
+            .fold(0i64, |depth, token| match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => depth + 1,
+                TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => {
+                    depth - 1
+                }
+                _ => depth,
+            });
+        if depth <= 0 {
+            return false;
+        }
+        // The input is incomplete, rather than invalid, if the first error is
+        // at the end of the input (the only token with an empty lexeme), as
+        // more input could then fix it.
+        let mut quiet = Parser::new().with_dialect(self.dialect);
+        quiet.expander = self.expander;
+        quiet.unicode_identifiers = self.unicode_identifiers;
+        match ParserContext::new(tokens.iter().cloned(), &quiet).parse() {
+            Ok(_) => false,
+            Err(errors) => errors[0].lexeme().is_empty(),
+        }
+    }
+}
+
+// MAX_ARGUMENTS is the maximum number of parameters of a function, and of
+// arguments to a call.
+const MAX_ARGUMENTS: usize = 255;
+
+// Rule is a rule of the grammar accepted by the parser, as rendered by
+// Parser::grammar. Each rule is parsed by the ParserContext method of the
+// same name.
+struct Rule {
+    name: &'static str,
+    production: &'static str,
+    // dialect is the only dialect the rule is part of, or None if the rule
+    // is part of all dialects.
+    dialect: Option<Dialect>,
+}
+
+impl Rule {
+    const fn new(name: &'static str, production: &'static str) -> Self {
+        Rule {
+            name,
+            production,
+            dialect: None,
+        }
+    }
+
+    const fn only(dialect: Dialect, name: &'static str, production: &'static str) -> Self {
+        Rule {
+            name,
+            production,
+            dialect: Some(dialect),
+        }
+    }
+}
+
+// RULES are the rules of the grammar, except for those of the binary
+// operators, which are generated from BINARY_LEVELS and take the place of
+// BINARY_LEVELS_MARKER.
+const RULES: &[Rule] = &[
+    Rule::new("program", "declaration* EOF"),
+    Rule::new(
+        "declaration",
+        "classDecl | funDecl | varDecl | constDecl | importDecl | statement",
+    ),
+    Rule::new(
+        "classDecl",
+        "\"class\" IDENTIFIER ( \"<\" IDENTIFIER )? \"{\" ( varDecl | \"class\"? method )* \"}\"",
+    ),
+    Rule::new("method", "IDENTIFIER ( functionBody | block )"),
+    Rule::new("funDecl", "\"fun\" function"),
+    Rule::new("function", "IDENTIFIER functionBody"),
+    Rule::new("functionBody", "\"(\" parameters? \")\" block"),
+    Rule::new("parameters", "IDENTIFIER ( \",\" IDENTIFIER )*"),
+    Rule::new("varDecl", "\"var\" IDENTIFIER ( \"=\" expression )? \";\""),
+    Rule::new("constDecl", "\"const\" IDENTIFIER \"=\" expression \";\""),
+    Rule::new(
+        "importDecl",
+        "\"import\" STRING ( \"as\" IDENTIFIER )? \";\"",
+    ),
+    Rule::only(
+        Dialect::Standard,
+        "statement",
+        "exprStmt | forStmt | ifStmt | printStmt | returnStmt | whileStmt | breakStmt \
+         | continueStmt | tryStmt | block",
+    ),
+    Rule::only(
+        Dialect::PrintFunctions,
+        "statement",
+        "exprStmt | forStmt | ifStmt | returnStmt | whileStmt | breakStmt | continueStmt \
+         | tryStmt | block",
+    ),
+    Rule::new("exprStmt", "expression \";\""),
+    Rule::new(
+        "forStmt",
+        "\"for\" \"(\" ( varDecl | exprStmt | \";\" ) expression? \";\" expression? \")\" statement",
+    ),
+    Rule::new(
+        "ifStmt",
+        "\"if\" \"(\" expression \")\" statement ( \"else\" statement )?",
+    ),
+    Rule::only(Dialect::Standard, "printStmt", "\"print\" expression \";\""),
+    Rule::new("returnStmt", "\"return\" expression? \";\""),
+    Rule::new(
+        "whileStmt",
+        "\"while\" \"(\" expression \")\" statement",
+    ),
+    Rule::new("breakStmt", "\"break\" \";\""),
+    Rule::new("continueStmt", "\"continue\" \";\""),
+    Rule::new(
+        "tryStmt",
+        "\"try\" block \"catch\" \"(\" IDENTIFIER \")\" block",
+    ),
+    Rule::new("block", "\"{\" declaration* \"}\""),
+    Rule::new("expression", "comma"),
+    Rule::new("comma", "assignment ( \",\" assignment )*"),
+    Rule::new(
+        "assignment",
+        "( ( call \".\" )? IDENTIFIER | call \"[\" expression \"]\" ) \
+         ( \"=\" | \"+=\" | \"-=\" | \"*=\" | \"/=\" ) assignment | \"throw\" assignment \
+         | logic_or",
+    ),
+    BINARY_LEVELS_MARKER,
+    Rule::new(
+        "unary",
+        "( \"!\" | \"-\" | \"++\" | \"--\" ) unary | exponent",
+    ),
+    Rule::new("exponent", "postfix ( \"**\" unary )?"),
+    Rule::new("postfix", "call ( \"++\" | \"--\" )?"),
+    Rule::new(
+        "call",
+        "primary ( \"(\" arguments? \")\" | \".\" IDENTIFIER | \"[\" expression \"]\" )*",
+    ),
+    Rule::new("arguments", "assignment ( \",\" assignment )*"),
+    Rule::only(
+        Dialect::Standard,
+        "primary",
+        "NUMBER | STRING | interpolation | \"false\" | \"true\" | \"nil\" | \"this\" \
+         | IDENTIFIER | \"(\" expression \")\" | \"super\" \".\" IDENTIFIER | lambda | list \
+         | map",
+    ),
+    // Without a print statement, print is the name of a function.
+    Rule::only(
+        Dialect::PrintFunctions,
+        "primary",
+        "NUMBER | STRING | interpolation | \"false\" | \"true\" | \"nil\" | \"this\" \
+         | IDENTIFIER | \"print\" | \"(\" expression \")\" | \"super\" \".\" IDENTIFIER \
+         | lambda | list | map",
+    ),
+    Rule::new("interpolation", "( INTERPOLATION expression )+ STRING"),
+    Rule::new("lambda", "\"fun\" functionBody"),
+    Rule::new("list", "\"[\" arguments? \"]\""),
+    Rule::new(
+        "map",
+        "\"{\" ( assignment \":\" assignment ( \",\" assignment \":\" assignment )* )? \"}\"",
+    ),
+];
+
+// BINARY_LEVELS_MARKER marks the place of the binary operator rules in
+// RULES.
+const BINARY_LEVELS_MARKER: Rule = Rule::new("", "");
+
+// BinaryLevel is a precedence level of binary operators, which are all left
+// associative. The operands of a level are parsed at the next level, with
+// the last level taking unary operands.
+struct BinaryLevel {
+    name: &'static str,
+    // operators are the operators of the level, along with how they are
+    // written in the grammar.
+    operators: &'static [(TokenType, &'static str)],
+    // is_logical is true if the operators short-circuit, and so are parsed
+    // into LogicalExprs rather than BinaryExprs.
+    is_logical: bool,
+    // warn_chained is true if chaining operators of the level, as in
+    // "a < b < c", is warned about.
+    warn_chained: bool,
+}
+
+// BINARY_LEVELS are the precedence levels of binary operators, from lowest
+// to highest precedence.
+const BINARY_LEVELS: &[BinaryLevel] = &[
+    BinaryLevel {
+        name: "logic_or",
+        operators: &[(TokenType::Or, "or")],
+        is_logical: true,
+        warn_chained: false,
+    },
+    BinaryLevel {
+        name: "logic_and",
+        operators: &[(TokenType::And, "and")],
+        is_logical: true,
+        warn_chained: false,
+    },
+    BinaryLevel {
+        name: "equality",
+        operators: &[(TokenType::BangEqual, "!="), (TokenType::EqualEqual, "==")],
+        is_logical: false,
+        warn_chained: false,
+    },
+    BinaryLevel {
+        name: "comparison",
+        operators: &[
+            (TokenType::Greater, ">"),
+            (TokenType::GreaterEqual, ">="),
+            (TokenType::Less, "<"),
+            (TokenType::LessEqual, "<="),
+        ],
+        is_logical: false,
+        warn_chained: true,
+    },
+    BinaryLevel {
+        name: "addition",
+        operators: &[(TokenType::Minus, "-"), (TokenType::Plus, "+")],
+        is_logical: false,
+        warn_chained: false,
+    },
+    BinaryLevel {
+        name: "multiplication",
+        operators: &[
+            (TokenType::Slash, "/"),
+            (TokenType::Star, "*"),
+            (TokenType::Percent, "%"),
+        ],
+        is_logical: false,
+        warn_chained: false,
+    },
+];
+
+impl BinaryLevel {
+    // production renders the rule of the level, whose operands are parsed
+    // by the rule named operand.
+    fn production(&self, operand: &str) -> String {
+        let operators: Vec<String> = self
+            .operators
+            .iter()
+            .map(|(_, text)| format!("\"{}\"", text))
+            .collect();
+        let operator = match operators.len() {
+            1 => operators[0].clone(),
+            _ => format!("( {} )", operators.join(" | ")),
+        };
+        format!("{} ( {} {} )*", operand, operator, operand)
+    }
+}
+
+// ParseError is returned when a syntax error is encountered. The error has
+// already been recorded when a ParseError is returned.
+struct ParseError;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+// FunctionKind is what a function being parsed is declared as.
+#[derive(Copy, Clone)]
+enum FunctionKind {
+    Function,
+    Method,
+}
+
+// ParserContext encapsulates the state of a single parse of some tokens.
+struct ParserContext<'a, I: Iterator<Item = Token>> {
+    // tokens is the tokens not yet pulled for the parse.
+    tokens: I,
+
+    // next is the next token to be consumed, and previous the most recently
+    // consumed token, if any.
+    next: Token,
+    previous: Option<Token>,
+
+    // after_next is the token after next, if it has been pulled by
+    // peek_next.
+    after_next: Option<Token>,
+
+    // expander is an optional TokenExpander applied to tokens as they are
+    // pulled, and expanded the tokens it produced that are not yet pulled.
+    expander: Option<&'a TokenExpander>,
+    expanded: VecDeque<Token>,
+
+    // errors is the errors reported so far.
+    errors: RefCell<Vec<LoxError>>,
+
+    // warning_cb is an optional WarningCallback that will be notified for
+    // each (if any) warnings encountered while parsing.
+    warning_cb: Option<&'a WarningCallback>,
+
+    // observer is an optional FrontendObserver that will be notified of each
+    // node parsed and each error while parsing.
+    observer: Option<&'a dyn FrontendObserver>,
+
+    dialect: Dialect,
+    unicode_identifiers: bool,
+
+    // last_equals is the "=" of the last plain assignment parsed, used to
+    // warn about an assignment used as a condition.
+    last_equals: Option<Token>,
+}
+
+impl<'a, I: Iterator<Item = Token>> ParserContext<'a, I> {
+    fn new(tokens: I, parser: &Parser<'a>) -> Self {
+        let start = Position::new(1, 1, 0);
+        let mut ctx = ParserContext {
+            tokens,
+            next: Self::eof_token(start),
+            previous: None,
+            after_next: None,
+            expander: parser.expander,
+            expanded: VecDeque::new(),
+            errors: RefCell::new(Vec::new()),
+            warning_cb: parser.warning_cb,
+            observer: parser.observer,
+            dialect: parser.dialect,
+            unicode_identifiers: parser.unicode_identifiers,
+            last_equals: None,
+        };
+        ctx.next = ctx.pull();
+        ctx
+    }
+
+    // pull pulls the token following next from tokens. Tokens without an Eof
+    // token are terminated by one at the end of the last token, so the
+    // parser never has to consider running out of tokens. With an expander,
+    // tokens are expanded as they are pulled, skipping those expanding to
+    // nothing.
+    fn pull(&mut self) -> Token {
+        match self.after_next.take() {
+            Some(token) => token,
+            None => self.pull_expanded(),
+        }
+    }
+
+    // pull_expanded pulls the next token from tokens, expanding it with the
+    // expander, if any.
+    fn pull_expanded(&mut self) -> Token {
+        loop {
+            if let Some(token) = self.expanded.pop_front() {
+                return token;
+            }
+            let end = self.next.span.end;
+            let token = self.tokens.next().unwrap_or_else(|| Self::eof_token(end));
+            match self.expander {
+                Some(expander) if token.token_type != TokenType::Eof => {
+                    let mut expansion = Vec::new();
+                    expander(token, &mut expansion);
+                    self.expanded.extend(expansion);
+                }
+                _ => return token,
+            }
+        }
+    }
+
+    // eof_token returns an Eof token at position.
+    fn eof_token(position: Position) -> Token {
+        Token {
+            token_type: TokenType::Eof,
+            lexeme: Lexeme::from(""),
+            symbol: None,
+            span: Span::new(position, position),
+            literal: None,
+            error: false,
+        }
+    }
+
+    // parse parses a program → declaration* EOF ;
+    //
+    // A syntax error does not stop the parse. Instead the parser recovers at
+    // the start of the next statement, so that all errors are reported.
+    fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(ParseError) => self.synchronize(),
+            }
+        }
+        self.finish(statements)
+    }
+
+    fn parse_expression(&mut self) -> Result<Expr, Vec<LoxError>> {
+        match self.expression() {
+            Ok(expr) => {
+                if !self.is_at_end() {
+                    self.error(self.peek(), messages::EXPECT_END_OF_EXPRESSION);
+                }
+                self.finish(expr)
+            }
+            Err(ParseError) => Err(self.errors.take()),
+        }
+    }
+
+    // finish returns result, or the errors reported while parsing if any.
+    // Some errors, such as an invalid assignment target, are reported
+    // without stopping the parse, so a parse can fail despite producing a
+    // result.
+    fn finish<T>(&self, result: T) -> Result<T, Vec<LoxError>> {
+        let errors = self.errors.take();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(errors = errors.len(), "parsed");
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // declaration → classDecl | funDecl | varDecl | constDecl | importDecl
+    //             | statement ;
+    //
+    // A "fun" followed by "(" starts a lambda rather than a function
+    // declaration, so the statement is an expression statement.
+    fn declaration(&mut self) -> ParseResult<Stmt> {
+        if self.advance_if_any(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+        if self.check(TokenType::Fun) && self.peek_next().token_type != TokenType::LeftParen {
+            self.advance();
+            return Ok(Stmt::Function(self.function(FunctionKind::Function)?));
+        }
+        if self.advance_if_any(&[TokenType::Var]) {
+            return Ok(Stmt::Var(self.var_declaration()?));
+        }
+        if self.advance_if_any(&[TokenType::Const]) {
+            return self.const_declaration();
+        }
+        if self.advance_if_any(&[TokenType::Import]) {
+            return self.import_declaration();
+        }
+        self.statement()
+    }
+
+    // importDecl → "import" STRING ( "as" IDENTIFIER )? ";" ;
+    //
+    // "as" is only a keyword here. Without it, the module is named by the
+    // file name of the path, which must then be an identifier.
+    fn import_declaration(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous().clone();
+        let path = self
+            .consume(TokenType::String, messages::EXPECT_MODULE_PATH)?
+            .clone();
+        let name = if self.check(TokenType::Identifier) && self.peek().lexeme.as_str() == "as" {
+            self.advance();
+            self.consume(TokenType::Identifier, messages::EXPECT_MODULE_NAME)?
+                .clone()
+        } else {
+            let stem = match path.literal {
+                Some(Literal::String(ref path)) => Path::new(&**path)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("")
+                    .to_owned(),
+                _ => String::new(),
+            };
+            let scanner = Scanner::new().with_unicode_identifiers(self.unicode_identifiers);
+            if !scanner.is_identifier(&stem) {
+                let msg = messages::EXPECT_AS;
+                self.error(self.peek(), msg);
+                return Err(ParseError);
+            }
+            Token {
+                token_type: TokenType::Identifier,
+                lexeme: Lexeme::from(stem.as_str()),
+                symbol: None,
+                literal: None,
+                ..path.clone()
+            }
+        };
+        self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_IMPORT)?;
+        Ok(Stmt::make_import(keyword, path, name))
+    }
+
+    // classDecl → "class" IDENTIFIER ( "<" IDENTIFIER )?
+    //             "{" ( varDecl | "class"? method )* "}" ;
+    fn class_declaration(&mut self) -> ParseResult<Stmt> {
+        let name = self
+            .consume(TokenType::Identifier, messages::EXPECT_CLASS_NAME)?
+            .clone();
+        let superclass = if self.advance_if_any(&[TokenType::Less]) {
+            let name = self.consume(TokenType::Identifier, messages::EXPECT_SUPERCLASS_NAME)?;
+            Some(Expr::make_variable(name.clone()))
+        } else {
+            None
+        };
+        self.consume(TokenType::LeftBrace, messages::EXPECT_CLASS_BODY)?;
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        let mut class_methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if self.advance_if_any(&[TokenType::Var]) {
+                fields.push(Rc::new(self.var_declaration()?));
+            } else if self.advance_if_any(&[TokenType::Class]) {
+                class_methods.push(self.method()?);
+            } else {
+                methods.push(self.method()?);
+            }
+        }
+        self.consume(TokenType::RightBrace, messages::EXPECT_CLASS_BODY_END)?;
+        Ok(Stmt::make_class(
+            name,
+            superclass,
+            fields,
+            methods,
+            class_methods,
+        ))
+    }
+
+    // method → IDENTIFIER ( functionBody | block ) ;
+    //
+    // A method without a parameter list is a getter.
+    fn method(&mut self) -> ParseResult<Rc<FunctionStmt>> {
+        let is_getter = self.check(TokenType::Identifier)
+            && self.peek_next().token_type == TokenType::LeftBrace;
+        if !is_getter {
+            return self.function(FunctionKind::Method);
+        }
+        let name = self.advance().clone();
+        self.advance();
+        let body = self.block()?;
+        Ok(Rc::new(FunctionStmt {
+            name,
+            params: Vec::new(),
+            body,
+            getter: true,
+            constants: RefCell::default(),
+        }))
+    }
+
+    // function → IDENTIFIER functionBody ;
+    //
+    // kind is what is being declared, and selects the error messages.
+    fn function(&mut self, kind: FunctionKind) -> ParseResult<Rc<FunctionStmt>> {
+        let (name_msg, paren_msg) = match kind {
+            FunctionKind::Function => (
+                messages::EXPECT_FUNCTION_NAME,
+                messages::EXPECT_FUNCTION_PARAMETERS,
+            ),
+            FunctionKind::Method => (
+                messages::EXPECT_METHOD_NAME,
+                messages::EXPECT_METHOD_PARAMETERS,
+            ),
+        };
+        let name = self.consume(TokenType::Identifier, name_msg)?.clone();
+        self.function_body(name, kind, paren_msg)
+    }
+
+    // functionBody → "(" parameters? ")" block ;
+    //
+    // msg is the error reported if the opening parenthesis is missing.
+    fn function_body(
+        &mut self,
+        name: Token,
+        kind: FunctionKind,
+        msg: MessageId,
+    ) -> ParseResult<Rc<FunctionStmt>> {
+        self.consume(TokenType::LeftParen, msg)?;
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek(), messages::TOO_MANY_PARAMETERS);
+                }
+                let param = self.consume(TokenType::Identifier, messages::EXPECT_PARAMETER_NAME)?;
+                params.push(param.clone());
+                if !self.advance_if_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, messages::EXPECT_PARAMETERS_END)?;
+        let msg = match kind {
+            FunctionKind::Function => messages::EXPECT_FUNCTION_BODY,
+            FunctionKind::Method => messages::EXPECT_METHOD_BODY,
+        };
+        self.consume(TokenType::LeftBrace, msg)?;
+        let body = self.block()?;
+        Ok(Rc::new(FunctionStmt {
+            name,
+            params,
+            body,
+            getter: false,
+            constants: RefCell::default(),
+        }))
+    }
+
+    // varDecl → "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self) -> ParseResult<VarStmt> {
+        let start = self.previous().span.start;
+        let name = self
+            .consume(TokenType::Identifier, messages::EXPECT_VARIABLE_NAME)?
+            .clone();
+        let initializer = if self.advance_if_any(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_VARIABLE)?;
+        let span = Span::new(start, self.previous().span.end);
+        Ok(VarStmt {
+            name,
+            initializer,
+            span,
+            constant: false,
+        })
+    }
+
+    // constDecl → "const" IDENTIFIER "=" expression ";" ;
+    fn const_declaration(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span.start;
+        let name = self
+            .consume(TokenType::Identifier, messages::EXPECT_CONSTANT_NAME)?
+            .clone();
+        self.consume(TokenType::Equal, messages::EXPECT_CONSTANT_VALUE)?;
+        let initializer = self.expression()?;
+        self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_CONSTANT)?;
+        let span = Span::new(start, self.previous().span.end);
+        Ok(Stmt::make_const(name, initializer, span))
+    }
+
+    // statement → exprStmt | forStmt | ifStmt | printStmt | returnStmt
+    //           | whileStmt | breakStmt | continueStmt | tryStmt | block ;
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        use TokenType::*;
+        match self.peek().token_type {
+            For => {
+                self.advance();
+                self.for_statement()
+            }
+            If => {
+                self.advance();
+                self.if_statement()
+            }
+            Print if self.dialect == Dialect::Standard => {
+                self.advance();
+                let value = self.expression()?;
+                self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_VALUE)?;
+                Ok(Stmt::make_print(value))
+            }
+            Break => {
+                let keyword = self.advance().clone();
+                self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_BREAK)?;
+                Ok(Stmt::make_break(keyword))
+            }
+            Continue => {
+                let keyword = self.advance().clone();
+                self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_CONTINUE)?;
+                Ok(Stmt::make_continue(keyword))
+            }
+            Return => {
+                let keyword = self.advance().clone();
+                let value = if self.check(Semicolon) {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+                self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_RETURN)?;
+                Ok(Stmt::make_return(keyword, value))
+            }
+            While => {
+                let keyword = self.advance().clone();
+                self.consume(LeftParen, messages::EXPECT_WHILE_CONDITION)?;
+                let condition = self.condition()?;
+                self.consume(RightParen, messages::EXPECT_WHILE_CONDITION_END)?;
+                let body = self.statement()?;
+                Ok(Stmt::make_while(keyword, condition, body))
+            }
+            Try => {
+                self.advance();
+                self.try_statement()
+            }
+            LeftBrace => {
+                self.advance();
+                Ok(Stmt::make_block(self.block()?))
+            }
+            _ => {
+                let expr = self.expression()?;
+                self.consume_semicolon(messages::EXPECT_SEMICOLON_AFTER_EXPRESSION)?;
+                Ok(Stmt::make_expression(expr))
+            }
+        }
+    }
+
+    // forStmt → "for" "(" ( varDecl | exprStmt | ";" )
+    //           expression? ";" expression? ")" statement ;
+    //
+    // The for loop is desugared into an equivalent while loop.
+    fn for_statement(&mut self) -> ParseResult<Stmt> {
+        use TokenType::*;
+        let keyword = self.previous().clone();
+        self.consume(LeftParen, messages::EXPECT_FOR_CLAUSES)?;
+        let initializer = if self.advance_if_any(&[Semicolon]) {
+            None
+        } else if self.advance_if_any(&[Var]) {
+            Some(Stmt::Var(self.var_declaration()?))
+        } else {
+            let expr = self.expression()?;
+            self.consume(Semicolon, messages::EXPECT_SEMICOLON_AFTER_EXPRESSION)?;
+            Some(Stmt::make_expression(expr))
+        };
+        let condition = if self.check(Semicolon) {
+            Expr::make_literal(Literal::Bool(true))
+        } else {
+            self.condition()?
+        };
+        self.consume(Semicolon, messages::EXPECT_SEMICOLON_AFTER_LOOP_CONDITION)?;
+        let increment = if self.check(RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(RightParen, messages::EXPECT_FOR_CLAUSES_END)?;
+
+        let body = self.statement()?;
+        let mut body = Stmt::make_for(keyword, condition, increment, body);
+        if let Some(initializer) = initializer {
+            body = Stmt::make_block(vec![initializer, body]);
+        }
+        Ok(body)
+    }
+
+    // ifStmt → "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> ParseResult<Stmt> {
+        self.consume(TokenType::LeftParen, messages::EXPECT_IF_CONDITION)?;
+        let condition = self.condition()?;
+        self.consume(TokenType::RightParen, messages::EXPECT_IF_CONDITION_END)?;
+        let then_branch = self.statement()?;
+        let else_branch = if self.advance_if_any(&[TokenType::Else]) {
+            Some(self.statement()?)
+        } else {
+            None
+        };
+        Ok(Stmt::make_if(condition, then_branch, else_branch))
+    }
+
+    // tryStmt → "try" block "catch" "(" IDENTIFIER ")" block ;
+    fn try_statement(&mut self) -> ParseResult<Stmt> {
+        use TokenType::*;
+        let keyword = self.previous().clone();
+        self.consume(LeftBrace, messages::EXPECT_TRY_BLOCK)?;
+        let body = self.block()?;
+        self.consume(Catch, messages::EXPECT_CATCH)?;
+        self.consume(LeftParen, messages::EXPECT_CATCH_VARIABLE)?;
+        let name = self
+            .consume(Identifier, messages::EXPECT_VARIABLE_NAME)?
+            .clone();
+        self.consume(RightParen, messages::EXPECT_CATCH_VARIABLE_END)?;
+        self.consume(LeftBrace, messages::EXPECT_CATCH_BLOCK)?;
+        let handler = self.block()?;
+        Ok(Stmt::make_try(keyword, body, name, handler))
+    }
+
+    // block → "{" declaration* "}" ;
+    //
+    // The opening brace is expected to already have been consumed.
+    fn block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let mut statements = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(ParseError) => self.synchronize(),
+            }
+        }
+        self.consume(TokenType::RightBrace, messages::EXPECT_BLOCK_END)?;
+        Ok(statements)
+    }
+
+    // condition parses the condition of an if statement or loop. An
+    // assignment as the condition, as in "if (a = b)", is valid but is
+    // usually a mistyped comparison, so it is warned about with a FixIt
+    // making it one. A grouped assignment, "if ((a = b))", is not warned
+    // about.
+    fn condition(&mut self) -> ParseResult<Expr> {
+        self.last_equals = None;
+        let condition = self.expression()?;
+        let is_assignment = matches!(
+            condition,
+            Expr::Assign(_) | Expr::Set(_) | Expr::SetIndex(_)
+        );
+        if let (true, Some(equals)) = (is_assignment, self.last_equals.take()) {
+            let fix = FixIt {
+                span: equals.span,
+                replacement: String::from("=="),
+            };
+            self.warning(&equals, messages::ASSIGNMENT_AS_CONDITION, Some(&fix));
+        }
+        Ok(condition)
+    }
+
+    // expression → comma ;
+    fn expression(&mut self) -> ParseResult<Expr> {
+        self.comma()
+    }
+
+    // comma → assignment ( "," assignment )* ;
+    //
+    // The comma operator evaluates its operands in order, yielding the value
+    // of the last one. Where commas separate items, such as the arguments of
+    // a call, the items are parsed with assignment instead.
+    fn comma(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.assignment()?;
+        while self.advance_if_any(&[TokenType::Comma]) {
+            let operator = self.previous().clone();
+            let right = self.assignment()?;
+            expr = self.node(Expr::make_binary(expr, operator, right));
+        }
+        Ok(expr)
+    }
+
+    // assignment → ( ( call "." )? IDENTIFIER | call "[" expression "]" )
+    //              ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+    //            | "throw" assignment
+    //            | logic_or ;
+    //
+    // A compound assignment such as "a += b" is desugared into "a = a + b".
+    fn assignment(&mut self) -> ParseResult<Expr> {
+        use TokenType::*;
+        if self.advance_if_any(&[Throw]) {
+            let keyword = self.previous().clone();
+            let value = self.assignment()?;
+            return Ok(self.node(Expr::make_throw(keyword, value)));
+        }
+        let mut expr = self.binary(0)?;
+        if self.advance_if_any(&[Equal, PlusEqual, MinusEqual, StarEqual, SlashEqual]) {
+            let equals = self.previous().clone();
+            let mut value = self.assignment()?;
+            let operator = Self::compound_operator(&equals);
+            // The outermost assignment of a chain is parsed last, so it is
+            // the one recorded.
+            self.last_equals = match operator {
+                Some(_) => None,
+                None => Some(equals.clone()),
+            };
+            match expr {
+                Expr::Variable(ref target) => {
+                    if let Some(operator) = operator {
+                        let current = self.node(Expr::make_variable(target.name.clone()));
+                        value = self.node(Expr::make_binary(current, operator, value));
+                    }
+                    let assign = Expr::make_assign(target.name.clone(), value);
+                    return Ok(self.node(assign));
+                }
+                Expr::Get(ref mut target) => {
+                    if let Some(operator) = operator {
+                        // Reading the current value evaluates the object a
+                        // second time, so it must not have side effects.
+                        let object = match self.duplicate(&target.object) {
+                            Some(object) => object,
+                            None => {
+                                self.error(&equals, messages::INVALID_COMPOUND_ASSIGNMENT_TARGET);
+                                return Ok(expr);
+                            }
+                        };
+                        let current = self.node(Expr::make_get(object, target.name.clone()));
+                        value = self.node(Expr::make_binary(current, operator, value));
+                    }
+                    // Expr can not be moved out of, as it implements Drop.
+                    let leaf = Expr::make_literal(Literal::Nil);
+                    let object = std::mem::replace(&mut *target.object, leaf);
+                    let set = Expr::make_set(object, target.name.clone(), value);
+                    return Ok(self.node(set));
+                }
+                Expr::Index(ref mut target) => {
+                    if let Some(operator) = operator {
+                        // As for properties, the object and index are
+                        // evaluated a second time.
+                        let (object, index) = match (
+                            self.duplicate(&target.object),
+                            self.duplicate(&target.index),
+                        ) {
+                            (Some(object), Some(index)) => (object, index),
+                            _ => {
+                                self.error(&equals, messages::INVALID_COMPOUND_ASSIGNMENT_TARGET);
+                                return Ok(expr);
+                            }
+                        };
+                        let current = Expr::make_index(object, target.bracket.clone(), index);
+                        let current = self.node(current);
+                        value = self.node(Expr::make_binary(current, operator, value));
+                    }
+                    let leaf = || Expr::make_literal(Literal::Nil);
+                    let object = std::mem::replace(&mut *target.object, leaf());
+                    let index = std::mem::replace(&mut *target.index, leaf());
+                    let set = Expr::make_set_index(object, target.bracket.clone(), index, value);
+                    return Ok(self.node(set));
+                }
+                _ => {}
+            }
+            // The parser is not in a confused state, so report the error
+            // without unwinding.
+            self.error(&equals, messages::INVALID_ASSIGNMENT_TARGET);
+        }
+        Ok(expr)
+    }
+
+    // duplicate returns a copy of expr, if it is an expression without side
+    // effects that can be copied: a variable, "this" or a literal.
+    fn duplicate(&self, expr: &Expr) -> Option<Expr> {
+        let copy = match expr {
+            Expr::Variable(variable) => Expr::make_variable(variable.name.clone()),
+            Expr::This(this) => Expr::make_this(this.keyword.clone()),
+            Expr::Literal(literal) => Expr::make_literal(literal.value.clone()),
+            _ => return None,
+        };
+        Some(self.node(copy))
+    }
+
+    // compound_operator returns the binary operator applied by the
+    // compound assignment operator equals, e.g. "+" for "+=", or None if
+    // equals is a plain "=".
+    fn compound_operator(equals: &Token) -> Option<Token> {
+        use TokenType::*;
+        let (token_type, lexeme) = match equals.token_type {
+            PlusEqual => (Plus, "+"),
+            MinusEqual => (Minus, "-"),
+            StarEqual => (Star, "*"),
+            SlashEqual => (Slash, "/"),
+            _ => return None,
+        };
+        Some(Token {
+            token_type,
+            lexeme: Lexeme::from(lexeme),
+            ..equals.clone()
+        })
+    }
+
+    // binary parses the binary operators of BINARY_LEVELS[level] and the
+    // levels of higher precedence, falling through to unary past the last
+    // level.
+    fn binary(&mut self, level: usize) -> ParseResult<Expr> {
+        let current = match BINARY_LEVELS.get(level) {
+            Some(current) => current,
+            None => return self.unary(),
+        };
+        let mut expr = self.binary(level + 1)?;
+        let mut is_chained = false;
+        while current.operators.iter().any(|&(op, _)| self.check(op)) {
+            let operator = self.advance().clone();
+            // A chain such as "a < b < c" is valid, but compares the boolean
+            // result of "a < b" to c, which is rarely what was intended.
+            if current.warn_chained && is_chained {
+                self.warning(&operator, messages::CHAINED_COMPARISON, None);
+            }
+            is_chained = true;
+            let right = self.binary(level + 1)?;
+            expr = if current.is_logical {
+                self.node(Expr::make_logical(expr, operator, right))
+            } else {
+                self.node(Expr::make_binary(expr, operator, right))
+            };
+        }
+        Ok(expr)
+    }
+
+    // unary → ( "!" | "-" ) unary | exponent ;
+    fn unary(&mut self) -> ParseResult<Expr> {
+        if self.advance_if_any(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(self.node(Expr::make_unary(operator, right)));
+        }
+        if self.advance_if_any(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous().clone();
+            let target = self.unary()?;
+            return Ok(self.node(Expr::make_increment(operator, target, true)));
+        }
+        self.exponent()
+    }
+
+    // exponent → call ( "**" unary )? ;
+    //
+    // Exponentiation is right associative, and binds tighter than a unary
+    // operator to its left, so that -2 ** 2 is -(2 ** 2).
+    fn exponent(&mut self) -> ParseResult<Expr> {
+        let expr = self.postfix()?;
+        if self.advance_if_any(&[TokenType::StarStar]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(self.node(Expr::make_binary(expr, operator, right)));
+        }
+        Ok(expr)
+    }
+
+    // postfix → call ( "++" | "--" )? ;
+    //
+    // A postfix operator must be on the line of its operand, so that a "++"
+    // or "--" starting a line after a missing semicolon is a prefix operator
+    // of the next statement.
+    fn postfix(&mut self) -> ParseResult<Expr> {
+        let expr = self.call()?;
+        let same_line = self.peek().span.start.line == self.previous().span.end.line;
+        if same_line && self.advance_if_any(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous().clone();
+            return Ok(self.node(Expr::make_increment(operator, expr, false)));
+        }
+        Ok(expr)
+    }
+
+    // call → primary ( "(" arguments? ")" | "." IDENTIFIER
+    //                | "[" expression "]" )* ;
+    fn call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.advance_if_any(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.advance_if_any(&[TokenType::Dot]) {
+                let name = self
+                    .consume(TokenType::Identifier, messages::EXPECT_PROPERTY_NAME)?
+                    .clone();
+                expr = self.node(Expr::make_get(expr, name));
+            } else if self.advance_if_any(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(TokenType::RightBracket, messages::EXPECT_INDEX_END)?
+                    .clone();
+                expr = self.node(Expr::make_index(expr, bracket, index));
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    // finish_call parses the arguments of a call to callee, following the
+    // opening parenthesis.
+    //
+    // arguments → assignment ( "," assignment )* ;
+    fn finish_call(&mut self, callee: Expr) -> ParseResult<Expr> {
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek(), messages::TOO_MANY_ARGUMENTS);
+                }
+                arguments.push(self.assignment()?);
+                if !self.advance_if_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self
+            .consume(TokenType::RightParen, messages::EXPECT_ARGUMENTS_END)?
+            .clone();
+        Ok(self.node(Expr::make_call(callee, paren, arguments)))
+    }
+
+    // primary → NUMBER | STRING | "false" | "true" | "nil"
+    //         | "this" | IDENTIFIER | "(" expression ")"
+    //         | "super" "." IDENTIFIER | interpolation | lambda | list
+    //         | map ;
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let token_type = self.peek().token_type;
+        let expr = match token_type {
+            TokenType::False => Expr::make_literal(Literal::Bool(false)),
+            TokenType::True => Expr::make_literal(Literal::Bool(true)),
+            TokenType::Nil => Expr::make_literal(Literal::Nil),
+            TokenType::This => Expr::make_this(self.peek().clone()),
+            TokenType::Identifier => Expr::make_variable(self.peek().clone()),
+            // Without a print statement, print is the name of a function.
+            TokenType::Print if self.dialect == Dialect::PrintFunctions => {
+                Expr::make_variable(self.peek().clone())
+            }
+            TokenType::Number | TokenType::String => {
+                let literal = self.peek().literal.clone();
+                Expr::make_literal(literal.expect("literal token without a value"))
+            }
+            TokenType::Super => {
+                let keyword = self.advance().clone();
+                self.consume(TokenType::Dot, messages::EXPECT_SUPER_DOT)?;
+                let method = self
+                    .consume(
+                        TokenType::Identifier,
+                        messages::EXPECT_SUPERCLASS_METHOD_NAME,
+                    )?
+                    .clone();
+                return Ok(self.node(Expr::make_super(keyword, method)));
+            }
+            TokenType::LeftParen => {
+                self.advance();
+                let expr = self.expression()?;
+                self.consume(TokenType::RightParen, messages::EXPECT_GROUPING_END)?;
+                return Ok(self.node(Expr::make_grouping(expr)));
+            }
+            TokenType::Interpolation => return self.interpolation(),
+            TokenType::Fun => return self.lambda(),
+            TokenType::LeftBracket => return self.list(),
+            TokenType::LeftBrace => return self.map(),
+            _ => {
+                self.error(self.peek(), messages::EXPECT_EXPRESSION);
+                return Err(ParseError);
+            }
+        };
+        self.advance();
+        Ok(self.node(expr))
+    }
+
+    // list → "[" arguments? "]" ;
+    fn list(&mut self) -> ParseResult<Expr> {
+        let bracket = self.advance().clone();
+        let mut elements = Vec::new();
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                elements.push(self.assignment()?);
+                if !self.advance_if_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBracket, messages::EXPECT_LIST_END)?;
+        Ok(self.node(Expr::make_list(bracket, elements)))
+    }
+
+    // map → "{" ( assignment ":" assignment
+    //         ( "," assignment ":" assignment )* )? "}" ;
+    fn map(&mut self) -> ParseResult<Expr> {
+        let brace = self.advance().clone();
+        let mut entries = Vec::new();
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                let key = self.assignment()?;
+                self.consume(TokenType::Colon, messages::EXPECT_MAP_COLON)?;
+                entries.push((key, self.assignment()?));
+                if !self.advance_if_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBrace, messages::EXPECT_MAP_END)?;
+        Ok(self.node(Expr::make_map(brace, entries)))
+    }
+
+    // lambda → "fun" functionBody ;
+    fn lambda(&mut self) -> ParseResult<Expr> {
+        let keyword = self.advance();
+        let name = Token {
+            lexeme: Lexeme::from("lambda"),
+            ..keyword.clone()
+        };
+        let function = self.function_body(
+            name,
+            FunctionKind::Function,
+            messages::EXPECT_LAMBDA_PARAMETERS,
+        )?;
+        Ok(self.node(Expr::make_function(function)))
+    }
+
+    // interpolation → ( INTERPOLATION expression )+ STRING ;
+    //
+    // An interpolated string is parsed as the concatenation of its parts,
+    // with the value of each interpolated expression converted to a string
+    // by a unary "${" operator, so "a ${b} c" is ("a " + ${b) + " c".
+    fn interpolation(&mut self) -> ParseResult<Expr> {
+        let mut expr: Option<Expr> = None;
+        loop {
+            let part = self.advance().clone();
+            expr = self.concat(expr, &part);
+            if part.token_type == TokenType::String {
+                break;
+            }
+            let value = self.expression()?;
+            let operator = Token {
+                token_type: TokenType::Interpolation,
+                lexeme: Lexeme::from("${"),
+                symbol: None,
+                span: part.span,
+                literal: None,
+                error: false,
+            };
+            let value = self.node(Expr::make_unary(operator, value));
+            expr = Some(match expr {
+                Some(left) => self.node(Expr::make_binary(left, Self::plus(&part), value)),
+                None => value,
+            });
+            if !self.check(TokenType::Interpolation) && !self.check(TokenType::String) {
+                self.error(self.peek(), messages::EXPECT_INTERPOLATION_END);
+                return Err(ParseError);
+            }
+        }
+        Ok(expr.expect("interpolation without parts"))
+    }
+
+    // concat returns expr concatenated with the string literal of part, the
+    // part of an interpolated string. Empty parts are left out, so "${a}" is
+    // only the converted a.
+    fn concat(&self, expr: Option<Expr>, part: &Token) -> Option<Expr> {
+        if matches!(part.literal, Some(Literal::String(ref s)) if s.is_empty()) {
+            return expr;
+        }
+        let literal = part.literal.clone().expect("literal token without a value");
+        let literal = self.node(Expr::make_literal(literal));
+        Some(match expr {
+            Some(left) => self.node(Expr::make_binary(left, Self::plus(part), literal)),
+            None => literal,
+        })
+    }
+
+    // plus returns a "+" token at part, for concatenating the parts of an
+    // interpolated string.
+    fn plus(part: &Token) -> Token {
+        Token {
+            token_type: TokenType::Plus,
+            lexeme: Lexeme::from("+"),
+            symbol: None,
+            span: part.span,
+            literal: None,
+            error: false,
+        }
+    }
+
+    // node notifies the observer (if any) that expr has been parsed, and
+    // returns it.
+    fn node(&self, expr: Expr) -> Expr {
+        if let Some(observer) = self.observer {
+            observer.on_node_parsed(&expr);
+        }
+        expr
+    }
+
+    // advance_if_any consumes the next token if its type is any of
+    // token_types, returning true if a token was consumed.
+    // Note this method is called "match" in lox/Parser.java, but match is
+    // reserved in rust.
+    fn advance_if_any(&mut self, token_types: &[TokenType]) -> bool {
+        if token_types.iter().any(|&t| self.check(t)) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    // consume consumes the next token if it is of token_type, or reports an
+    // error with msg otherwise.
+    fn consume(&mut self, token_type: TokenType, msg: MessageId) -> ParseResult<&Token> {
+        if self.check(token_type) {
+            return Ok(self.advance());
+        }
+        self.error(self.peek(), msg);
+        Err(ParseError)
+    }
+
+    // consume_semicolon consumes the semicolon ending a statement, or
+    // reports an error with msg otherwise. A semicolon missing at the end of
+    // a line, with the next statement on a following line, is reported at
+    // the end of the line with a FixIt inserting it. Parsing then goes on as
+    // if the semicolon was there, so that the next statement is not lost to
+    // error recovery.
+    fn consume_semicolon(&mut self, msg: MessageId) -> ParseResult<()> {
+        if self.advance_if_any(&[TokenType::Semicolon]) {
+            return Ok(());
+        }
+        let previous = self.previous().clone();
+        if self.is_at_end() || self.peek().span.start.line <= previous.span.end.line {
+            self.error(self.peek(), msg);
+            return Err(ParseError);
+        }
+        let fix = FixIt {
+            span: Span::new(previous.span.end, previous.span.end),
+            replacement: String::from(";"),
+        };
+        self.error_with_fix(&previous, msg, Some(fix));
+        Ok(())
+    }
+
+    // peek_next returns the token after the next one without consuming
+    // either.
+    fn peek_next(&mut self) -> &Token {
+        if self.after_next.is_none() {
+            let token = self.pull_expanded();
+            self.after_next = Some(token);
+        }
+        self.after_next.as_ref().expect("no token after next")
+    }
+
+    // check returns true if the next token is of token_type.
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    // synchronize discards tokens until the likely start of the next
+    // statement, i.e. just past a semicolon or at a keyword starting a
+    // statement, to recover after a syntax error.
+    fn synchronize(&mut self) {
+        self.advance();
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::Const
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Try
+                | TokenType::Import => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    // advance consumes the next token and returns it.
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            let next = self.pull();
+            self.previous = Some(std::mem::replace(&mut self.next, next));
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    // peek returns the next token without consuming it.
+    fn peek(&self) -> &Token {
+        &self.next
+    }
+
+    // previous returns the most recently consumed token.
+    fn previous(&self) -> &Token {
+        self.previous.as_ref().expect("no token consumed")
+    }
+
+    // warning reports a warning with msg and the optional fix at token to
+    // the registered warning_cb.
+    fn warning(&self, token: &Token, msg: MessageId, fix: Option<&FixIt>) {
+        if let Some(f) = self.warning_cb {
+            f(token.span, &msg.into(), fix)
+        }
+    }
+
+    // error records an error with msg at token, and reports it to the
+    // observer.
+    fn error(&self, token: &Token, msg: MessageId) {
+        self.error_with_fix(token, msg, None);
+    }
+
+    // error_with_fix is as error, with fix as the suggested fix.
+    fn error_with_fix(&self, token: &Token, msg: MessageId, fix: Option<FixIt>) {
+        let msg = Message::from(msg);
+        if let Some(observer) = self.observer {
+            observer.on_diagnostic(token.span, &msg);
+        }
+        self.errors.borrow_mut().push(LoxError::Parse {
+            span: token.span,
+            lexeme: token.lexeme.to_string(),
+            message: msg,
+            fix,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::AcceptsVisitor;
+    use crate::print::AstPrinter;
+    use crate::scanner::Scanner;
+
+    // expect_ok returns the value of result, panicking on the first error of
+    // result, if any.
+    fn expect_ok<T>(result: Result<T, Vec<LoxError>>) -> T {
+        match result {
+            Ok(value) => value,
+            Err(errors) => {
+                let err = &errors[0];
+                panic!(
+                    "error: '{line}:{msg}'",
+                    line = err.line(),
+                    msg = err.message()
+                );
+            }
+        }
+    }
+
+    // scan scans source, panicking on any error.
+    fn scan(source: &str) -> Vec<Token> {
+        let (tokens, errors) = Scanner::new().scan_tokens(source);
+        expect_ok(if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        })
+    }
+
+    // parse_to_string scans and parses source, returning the parsed
+    // expression as printed by the AstPrinter.
+    fn parse_to_string(source: &str) -> String {
+        let tokens = scan(source);
+        let expr = expect_ok(Parser::new().parse_expression(tokens));
+        AstPrinter::new().print(expr)
+    }
+
+    // parse_program_to_string scans and parses the program source, returning
+    // the parsed statements as printed by the AstPrinter.
+    fn parse_program_to_string(source: &str) -> String {
+        let tokens = scan(source);
+        let program = expect_ok(Parser::new().parse(tokens));
+        AstPrinter::new().print_program(&program)
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        assert_eq!(parse_to_string("123"), "123");
+        assert_eq!(parse_to_string("\"abc\""), "\"abc\"");
+        assert_eq!(parse_to_string("true"), "true");
+        assert_eq!(parse_to_string("false"), "false");
+        assert_eq!(parse_to_string("nil"), "nil");
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(parse_to_string("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(parse_to_string("1 * 2 + 3"), "(+ (* 1 2) 3)");
+        assert_eq!(parse_to_string("1 < 2 == 3 >= 4"), "(== (< 1 2) (>= 3 4))");
+        assert_eq!(parse_to_string("-1 - -2"), "(- (- 1) (- 2))");
+        assert_eq!(parse_to_string("!!true"), "(! (! true))");
+        assert_eq!(parse_to_string("1 + 2 % 3"), "(+ 1 (% 2 3))");
+        assert_eq!(parse_to_string("-2 ** 2"), "(- (** 2 2))");
+        assert_eq!(parse_to_string("2 * 3 ** 2"), "(* 2 (** 3 2))");
+        assert_eq!(parse_to_string("2 ** -1"), "(** 2 (- 1))");
+        assert_eq!(parse_to_string("f() ** a.b"), "(** (call f) (. a b))");
+    }
+
+    #[test]
+    fn test_parse_left_associative() {
+        assert_eq!(parse_to_string("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(parse_to_string("1 / 2 / 3"), "(/ (/ 1 2) 3)");
+        assert_eq!(parse_to_string("1 == 2 != 3"), "(!= (== 1 2) 3)");
+        assert_eq!(parse_to_string("1 % 2 * 3"), "(* (% 1 2) 3)");
+    }
+
+    #[test]
+    fn test_parse_right_associative() {
+        assert_eq!(parse_to_string("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+    }
+
+    #[test]
+    fn test_parse_increment() {
+        assert_eq!(parse_to_string("++a"), "(++ a)");
+        assert_eq!(parse_to_string("a.b--"), "((. a b) --)");
+        assert_eq!(parse_to_string("-a++ ** 2"), "(- (** (a ++) 2))");
+        assert_eq!(parse_to_string("--f().a"), "(-- (. (call f) a))");
+
+        // A "++" on the next line is not a postfix operator.
+        let errors = Parser::new().parse(scan("a = 1\n++b;")).err().unwrap();
+        let messages: Vec<(u64, &str)> = errors.iter().map(|e| (e.line(), e.message())).collect();
+        assert_eq!(messages, vec![(1, "Expect ';' after expression.")]);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse_to_string("[]"), "(list)");
+        assert_eq!(parse_to_string("[1, [a]]"), "(list 1 (list a))");
+        assert_eq!(parse_to_string("a[1][b]"), "([] ([] a 1) b)");
+        assert_eq!(parse_to_string("f()[0] = 1"), "(= ([] (call f) 0) 1)");
+        assert_eq!(parse_to_string("a[i] += 1"), "(= ([] a i) (+ ([] a i) 1))");
+        let errors = Parser::new().parse(scan("f()[0] += 1;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Invalid compound assignment target.");
+        let errors = Parser::new().parse(scan("[1, 2;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect ']' after list elements.");
+        let errors = Parser::new().parse(scan("a[1;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect ']' after index.");
+    }
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(parse_to_string("{}"), "(map)");
+        assert_eq!(
+            parse_to_string("{\"a\": 1, b: {}}[\"a\"]"),
+            "([] (map \"a\" 1 b (map)) \"a\")"
+        );
+        // A brace starting a statement starts a block.
+        assert_eq!(parse_program_to_string("{}"), "(block)");
+        let errors = Parser::new().parse(scan("print {1 2};")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect ':' after map key.");
+        let errors = Parser::new().parse(scan("print {1: 2;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect '}' after map entries.");
+    }
+
+    #[test]
+    fn test_parse_lambda() {
+        assert_eq!(
+            parse_to_string("f(fun (a, b) { return a; })"),
+            "(call f (fun (a b) (return a)))"
+        );
+        // A statement starting with a lambda is an expression statement.
+        assert_eq!(
+            parse_program_to_string("fun () {}(); fun g() {}"),
+            "(; (call (fun ())))\n(fun g ())"
+        );
+        let errors = Parser::new()
+            .parse(scan("var f = fun a() {};"))
+            .err()
+            .unwrap();
+        assert_eq!(errors[0].message(), "Expect '(' after 'fun'.");
+    }
+
+    #[test]
+    fn test_parse_try() {
+        assert_eq!(
+            parse_program_to_string("try { f(); } catch (e) { print e; }"),
+            "(try (block (; (call f))) (catch e (print e)))"
+        );
+        assert_eq!(parse_to_string("a = throw b = c"), "(= a (throw (= b c)))");
+        let errors = Parser::new().parse(scan("try {} catch {}")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect '(' after 'catch'.");
+    }
+
+    #[test]
+    fn test_parse_const() {
+        assert_eq!(
+            parse_program_to_string("const a = 1 + 2; { const b = a; }"),
+            "(const a = (+ 1 2))\n(block (const b = a))"
+        );
+        let errors = Parser::new().parse(scan("const a;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect '=' after constant name.");
+    }
+
+    #[test]
+    fn test_parse_import() {
+        assert_eq!(
+            parse_program_to_string("import \"lib/util.lox\"; import \"a-b.lox\" as ab;"),
+            "(import \"lib/util.lox\" as util)\n(import \"a-b.lox\" as ab)"
+        );
+        let errors = Parser::new()
+            .parse(scan("import \"a-b.lox\";"))
+            .err()
+            .unwrap();
+        let msg = "Expect 'as' and a module name, as the file name is not an identifier.";
+        assert_eq!(errors[0].message(), msg);
+        // The file name is an identifier if the parser accepts unicode
+        // identifiers.
+        let errors = Parser::new()
+            .parse(scan("import \"café.lox\";"))
+            .err()
+            .unwrap();
+        assert_eq!(errors[0].message(), msg);
+        let program = Parser::new()
+            .with_unicode_identifiers(true)
+            .parse(scan("import \"café.lox\";"));
+        assert_eq!(
+            AstPrinter::new().print_program(&expect_ok(program)),
+            "(import \"café.lox\" as café)"
+        );
+    }
+
+    #[test]
+    fn test_parse_token_expander() {
+        // "inc" expands to "+= 1", and "_" to nothing.
+        let expander = |token: Token, tokens: &mut Vec<Token>| match token.lexeme.as_str() {
+            "inc" => {
+                tokens.push(Token::expanded(TokenType::PlusEqual, "+=", None, &token));
+                let one = Some(Literal::Number(1.0));
+                tokens.push(Token::expanded(TokenType::Number, "1", one, &token));
+            }
+            "_" => {}
+            _ => tokens.push(token),
+        };
+        let parser = Parser::new().with_token_expander(&expander);
+        let program = parser.parse(scan("a inc _; _")).unwrap();
+        assert_eq!(
+            AstPrinter::new().print_program(&program),
+            "(; (= a (+ a 1)))"
+        );
+        // Errors in the expansion point at the shorthand.
+        let source = "a inc inc;";
+        let errors = parser.parse(scan(source)).err().unwrap();
+        assert_eq!(&source[errors[0].span().bytes()], "inc");
+        assert_eq!(errors[0].span().start.column, 7);
+        // Tokens looked ahead at are expanded too.
+        let expander = |token: Token, tokens: &mut Vec<Token>| match token.lexeme.as_str() {
+            "function" => tokens.push(Token::expanded(TokenType::Fun, "fun", None, &token)),
+            _ => tokens.push(token),
+        };
+        let parser = Parser::new().with_token_expander(&expander);
+        let program = parser.parse(scan("function f() {}")).unwrap();
+        assert_eq!(AstPrinter::new().print_program(&program), "(fun f ())");
+    }
+
+    #[test]
+    fn test_parse_interpolation() {
+        assert_eq!(
+            parse_to_string("\"a ${b} c\""),
+            "(+ (+ \"a \" (${ b)) \" c\")"
+        );
+        assert_eq!(parse_to_string("\"${b}\""), "(${ b)");
+        assert_eq!(
+            parse_to_string("\"${a}${b + \"${c}\"}\""),
+            "(+ (${ a) (${ (+ b (${ c))))"
+        );
+        let errors = Parser::new()
+            .parse(scan("print \"${a b}\";"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].message(),
+            "Expect '}' after interpolated expression."
+        );
+    }
+
+    #[test]
+    fn test_parse_grouping() {
+        assert_eq!(parse_to_string("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+        assert_eq!(parse_to_string("((1))"), "(group (group 1))");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect expression.")]
+    fn test_parse_expect_expression() {
+        parse_to_string("1 + ");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect ')' after expression.")]
+    fn test_parse_unclosed_grouping() {
+        parse_to_string("(1 + 2");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect end of expression.")]
+    fn test_parse_trailing_tokens() {
+        parse_to_string("1 2");
+    }
+
+    #[test]
+    fn test_parse_pulls_tokens_lazily() {
+        let pulled = std::cell::Cell::new(0);
+        let tokens = Scanner::new()
+            .tokens("1 2 3")
+            .map(Result::unwrap)
+            .inspect(|_| pulled.set(pulled.get() + 1));
+        let errors = Parser::new().parse_expression(tokens).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect end of expression.");
+        // The parse stopped at "2", so "3" and the Eof token were never
+        // scanned.
+        assert_eq!(pulled.get(), 2);
+    }
+
+    #[test]
+    fn test_parse_chained_comparison_warning() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&warnings);
+        let record_warning =
+            move |span: Span, _: &Message, _: Option<&FixIt>| recorded.borrow_mut().push(span);
+
+        let tokens = scan("1 < 2 <= 3 == (4 > 5)");
+        let parser = Parser::new().with_warning_cb(&record_warning);
+        let expr = expect_ok(parser.parse_expression(tokens));
+
+        // The chain still parses as a valid (left associative) expression.
+        let printed = AstPrinter::new().print(expr);
+        assert_eq!(printed, "(== (<= (< 1 2) 3) (group (> 4 5)))");
+        let operator_span = Span::new(Position::new(1, 7, 6), Position::new(1, 9, 8));
+        assert_eq!(*warnings.borrow(), vec![operator_span]);
+    }
+
+    #[test]
+    fn test_parse_assignment_condition_warning() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&warnings);
+        let record_warning = move |_: Span, msg: &Message, fix: Option<&FixIt>| {
+            recorded.borrow_mut().push((msg.to_string(), fix.cloned()))
+        };
+        let parser = Parser::new().with_warning_cb(&record_warning);
+        let source = "if (a = b) {} while ((a = b)) {} for (; a == b;) {} if (a = b = c) {}";
+        expect_ok(parser.parse(scan(source)));
+
+        let msg = "Assignment used as a condition; use '==' to compare.";
+        let fix = |column: u64| FixIt {
+            span: Span::new(
+                Position::new(1, column, column as usize - 1),
+                Position::new(1, column + 1, column as usize),
+            ),
+            replacement: String::from("=="),
+        };
+        // Only the outermost assignment of a chain is warned about.
+        assert_eq!(
+            *warnings.borrow(),
+            vec![
+                (msg.to_owned(), Some(fix(7))),
+                (msg.to_owned(), Some(fix(59))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error_returns_errors() {
+        let tokens = scan("(1");
+        let errors = Parser::new().parse_expression(tokens).err().unwrap();
+        assert_eq!(
+            errors,
+            vec![LoxError::Parse {
+                span: Span::new(Position::new(1, 3, 2), Position::new(1, 3, 2)),
+                lexeme: String::new(),
+                message: "Expect ')' after expression.".into(),
+                fix: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_recovers_from_errors() {
+        let tokens = scan("var = 1; print 2;\n{ print (; var b = 3; }\nfun f( { } class C {}");
+        let errors = Parser::new().parse(tokens).err().unwrap();
+        let errors: Vec<(u64, &str)> = errors.iter().map(|e| (e.line(), e.message())).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, "Expect variable name."),
+                (2, "Expect expression."),
+                (3, "Expect parameter name."),
+            ]
+        );
+        // Parsing resumes at break and continue.
+        let tokens = scan("var 1 break x;\nvar 2 continue y;");
+        let errors = Parser::new().parse(tokens).err().unwrap();
+        let errors: Vec<(u64, &str)> = errors.iter().map(|e| (e.line(), e.message())).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, "Expect variable name."),
+                (1, "Expect ';' after 'break'."),
+                (2, "Expect variable name."),
+                (2, "Expect ';' after 'continue'."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_semicolon_at_end_of_line() {
+        let tokens = scan("var a = 1\nprint a\nprint (;");
+        let errors = Parser::new().parse(tokens).err().unwrap();
+        let messages: Vec<(u64, &str)> = errors.iter().map(|e| (e.line(), e.message())).collect();
+        // The statements following the missing semicolons are still parsed.
+        assert_eq!(
+            messages,
+            vec![
+                (1, "Expect ';' after variable declaration."),
+                (2, "Expect ';' after value."),
+                (3, "Expect expression."),
+            ]
+        );
+        assert_eq!(errors[0].lexeme(), "1");
+        let fix = FixIt {
+            span: Span::new(Position::new(1, 10, 9), Position::new(1, 10, 9)),
+            replacement: String::from(";"),
+        };
+        assert_eq!(errors[0].fix(), Some(&fix));
+        assert_eq!(errors[2].fix(), None);
+
+        // Without a line break, the semicolon is reported missing at the
+        // next token as usual.
+        let errors = Parser::new().parse(scan("print 1 print 2;")).err().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].lexeme(), "print");
+        assert_eq!(errors[0].fix(), None);
+    }
+
+    #[test]
+    fn test_parse_reports_errors_that_do_not_stop_the_parse() {
+        let tokens = scan("a + b = c; var d = 1;");
+        let errors = Parser::new().parse(tokens).err().unwrap();
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+        assert_eq!(messages, vec!["Invalid assignment target."]);
+        assert_eq!(errors[0].lexeme(), "=");
+    }
+
+    #[test]
+    fn test_parse_print_functions_dialect() {
+        let parser = Parser::new().with_dialect(Dialect::PrintFunctions);
+        let program = expect_ok(parser.parse(scan("print(1); println(print);")));
+        assert_eq!(
+            AstPrinter::new().print_program(&program),
+            "(; (call print 1))\n(; (call println print))"
+        );
+        let errors = parser.parse(scan("print 1;")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect ';' after expression.");
+    }
+
+    #[test]
+    fn test_parse_repl() {
+        let parse_repl = |source| {
+            let program = expect_ok(Parser::new().parse_repl(scan(source)));
+            AstPrinter::new().print_program(&program)
+        };
+        assert_eq!(parse_repl("1 + 2"), parse_program_to_string("print 1 + 2;"));
+        assert_eq!(parse_repl("a = 1"), parse_program_to_string("print a = 1;"));
+        assert_eq!(parse_repl("1 + 2;"), parse_program_to_string("1 + 2;"));
+        assert_eq!(
+            parse_repl("var a = 1;"),
+            parse_program_to_string("var a = 1;")
+        );
+
+        // Errors are those of parsing a program.
+        let errors = Parser::new().parse_repl(scan("1 +")).err().unwrap();
+        assert_eq!(errors[0].message(), "Expect expression.");
+        let errors = Parser::new().parse_repl(scan("var a = 1")).err().unwrap();
+        assert_eq!(
+            errors[0].message(),
+            "Expect ';' after variable declaration."
+        );
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        let is_incomplete = |source| Parser::new().is_incomplete(&scan(source));
+        assert!(is_incomplete("fun foo() {"));
+        assert!(is_incomplete("fun foo() {\n  print 1;"));
+        assert!(is_incomplete("print (1 +"));
+        assert!(is_incomplete("class A { f() { if (true) {}"));
+        assert!(is_incomplete("if (true) {} else {"));
+        assert!(is_incomplete("var a = [1,"));
+
+        // Complete or invalid input, or input missing something other than
+        // a closing parenthesis or brace.
+        assert!(!is_incomplete("fun foo() {}"));
+        assert!(!is_incomplete("1 + 2"));
+        assert!(!is_incomplete("var a = 1"));
+        assert!(!is_incomplete("fun foo() { 1 +; "));
+        assert!(!is_incomplete("{ ) ("));
+        assert!(!is_incomplete("}"));
+    }
+
+    #[test]
+    fn test_parse_without_eof() {
+        let parser = Parser::new();
+        assert!(parser.parse_expression(Vec::new()).is_err());
+        assert_eq!(parser.parse(Vec::new()).map(|p| p.len()), Ok(0));
+    }
+
+    #[test]
+    fn test_parse_notifies_observer() {
+        use std::cell::RefCell;
+
+        #[derive(Default)]
+        struct Recorder {
+            nodes: RefCell<Vec<String>>,
+        }
+
+        impl FrontendObserver for Recorder {
+            fn on_node_parsed(&self, expr: &Expr) {
+                let node = expr.accept(&mut AstPrinter::new());
+                self.nodes.borrow_mut().push(node);
+            }
+        }
+
+        let tokens = scan("-1 + 2");
+        let recorder = Recorder::default();
+        let parser = Parser::new().with_observer(&recorder);
+        expect_ok(parser.parse_expression(tokens));
+        assert_eq!(
+            recorder.nodes.into_inner(),
+            vec!["1", "(- 1)", "2", "(+ (- 1) 2)"]
+        );
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        assert_eq!(parse_to_string("a = b = c"), "(= a (= b c))");
+        assert_eq!(parse_to_string("a or b and c"), "(or a (and b c))");
+        assert_eq!(parse_to_string("a == b or c"), "(or (== a b) c)");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Invalid assignment target.")]
+    fn test_parse_invalid_assignment_target() {
+        parse_to_string("a + b = c");
+    }
+
+    #[test]
+    fn test_parse_compound_assignment() {
+        assert_eq!(parse_to_string("a += 1"), "(= a (+ a 1))");
+        assert_eq!(parse_to_string("a -= b *= 2"), "(= a (- a (= b (* b 2))))");
+        assert_eq!(parse_to_string("a.b /= 2"), "(= (. a b) (/ (. a b) 2))");
+        assert_eq!(
+            parse_to_string("this.b -= 1"),
+            "(= (. this b) (- (. this b) 1))"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Invalid compound assignment target.")]
+    fn test_parse_invalid_compound_assignment_target() {
+        // Desugaring would call f twice.
+        parse_to_string("f().a += 1");
+    }
+
+    #[test]
+    fn test_parse_calls() {
+        assert_eq!(parse_to_string("f()"), "(call f)");
+        assert_eq!(parse_to_string("f(1, a)(2)"), "(call (call f 1 a) 2)");
+        assert_eq!(parse_to_string("-f(1)"), "(- (call f 1))");
+    }
+
+    #[test]
+    fn test_parse_comma() {
+        assert_eq!(parse_to_string("a, b, c"), "(, (, a b) c)");
+        assert_eq!(parse_to_string("a = 1, b = 2"), "(, (= a 1) (= b 2))");
+        assert_eq!(parse_to_string("a or b, c"), "(, (or a b) c)");
+        // Commas separate the arguments of a call, unless grouped.
+        assert_eq!(
+            parse_to_string("f(a, (b, c))"),
+            "(call f a (group (, b c)))"
+        );
+    }
+
+    #[test]
+    fn test_grammar() {
+        let grammar = Parser::new().grammar();
+        let lines: Vec<&str> = grammar.lines().collect();
+        assert_eq!(lines[0], "program        → declaration* EOF ;");
+        assert!(lines.contains(&"logic_or       → logic_and ( \"or\" logic_and )* ;"));
+        assert!(lines.contains(
+            &"comparison     → addition ( ( \">\" | \">=\" | \"<\" | \"<=\" ) addition )* ;"
+        ));
+        assert!(lines.contains(&"multiplication → unary ( ( \"/\" | \"*\" | \"%\" ) unary )* ;"));
+        assert!(lines.iter().any(|line| line.starts_with("printStmt ")));
+
+        // Without a print statement, print is parsed as a primary instead.
+        let grammar = Parser::new()
+            .with_dialect(Dialect::PrintFunctions)
+            .grammar();
+        assert!(!grammar.contains("printStmt"));
+        assert!(grammar.contains("IDENTIFIER | \"print\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect ')' after arguments.")]
+    fn test_parse_unclosed_call() {
+        parse_to_string("f(1");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Cannot have more than 255 arguments.")]
+    fn test_parse_too_many_arguments() {
+        let arguments = vec!["1"; 256].join(",");
+        parse_to_string(&format!("f({})", arguments));
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        assert_eq!(parse_program_to_string("print 1;"), "(print 1)");
+        assert_eq!(parse_program_to_string("var a;"), "(var a)");
+        assert_eq!(
+            parse_program_to_string("var a = 1; a;"),
+            "(var a = 1)\n(; a)"
+        );
+        assert_eq!(
+            parse_program_to_string("{ var a; { print a; } }"),
+            "(block (var a) (block (print a)))"
+        );
+        assert_eq!(
+            parse_program_to_string("if (a) print 1; else print 2;"),
+            "(if a (print 1) (print 2))"
+        );
+        assert_eq!(
+            parse_program_to_string("while (a) a = false;"),
+            "(while a (; (= a false)))"
+        );
+    }
+
+    #[test]
+    fn test_parse_for_desugars_to_while() {
+        assert_eq!(
+            parse_program_to_string("for (var i = 0; i < 2; i = i + 1) print i;"),
+            "(block (var i = 0) (while (< i 2) (print i) (= i (+ i 1))))"
+        );
+        assert_eq!(
+            parse_program_to_string("for (;;) print 1;"),
+            "(while true (print 1))"
+        );
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        assert_eq!(
+            parse_program_to_string("fun f(a, b) { return a + b; }"),
+            "(fun f (a b) (return (+ a b)))"
+        );
+        assert_eq!(
+            parse_program_to_string("fun f() { return; }"),
+            "(fun f () (return))"
+        );
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        assert_eq!(parse_to_string("a.b.c"), "(. (. a b) c)");
+        assert_eq!(
+            parse_to_string("a.b(1).c = 2"),
+            "(= (. (call (. a b) 1) c) 2)"
+        );
+        assert_eq!(parse_to_string("this.x"), "(. this x)");
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        assert_eq!(
+            parse_program_to_string("class A { init(x) { this.x = x; } get() { return 1; } }"),
+            "(class A (fun init (x) (; (= (. this x) x))) (fun get () (return 1)))"
+        );
+        assert_eq!(parse_program_to_string("class A {}"), "(class A)");
+    }
+
+    #[test]
+    fn test_parse_class_methods_and_getters() {
+        assert_eq!(
+            parse_program_to_string("class A { class make() { return A(); } size { return 1; } }"),
+            "(class A (getter size (return 1)) (static (fun make () (return (call A)))))"
+        );
+        assert_eq!(
+            parse_program_to_string("class A { class zero { return 0; } }"),
+            "(class A (static (getter zero (return 0))))"
+        );
+    }
+
+    #[test]
+    fn test_parse_class_fields() {
+        assert_eq!(
+            parse_program_to_string("class P { var x = 0; f() {} var y; }"),
+            "(class P (var x = 0) (var y) (fun f ()))"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect ';' after variable declaration.")]
+    fn test_parse_class_field_missing_semicolon() {
+        parse_program_to_string("class P { var x = 0 }");
+    }
+
+    #[test]
+    fn test_parse_inheritance() {
+        assert_eq!(
+            parse_program_to_string("class B < A { f() { return super.f(); } }"),
+            "(class B < A (fun f () (return (call (. super f)))))"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect '.' after 'super'.")]
+    fn test_parse_bare_super() {
+        parse_to_string("super");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect property name after '.'.")]
+    fn test_parse_missing_property_name() {
+        parse_to_string("a.1");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect ';' after expression.")]
+    fn test_parse_missing_semicolon() {
+        parse_program_to_string("1 + 2");
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Expect '}' after block.")]
+    fn test_parse_unclosed_block() {
+        parse_program_to_string("{ print 1;");
+    }
+}
@@ -0,0 +1,57 @@
+use super::error::LoxError;
+use super::parser::Parser;
+use super::resolver::Resolver;
+use super::scanner::Scanner;
+use super::stmt::Stmt;
+
+// Prelude is a Lox program, such as a library of functions and classes,
+// compiled once so that it can be loaded into any number of Interpreters
+// without being scanned, parsed and resolved again for each of them.
+//
+// A Prelude is never modified after it is compiled, so a single Prelude can
+// be shared (e.g. behind an Rc) by all interpreters of a thread. Loading it
+// with Interpreter::load_prelude still runs its statements, so each
+// interpreter gets its own copy of the prelude's global variables.
+pub struct Prelude {
+    program: Vec<Stmt>,
+}
+
+impl Prelude {
+    // compile scans, parses and resolves source into a Prelude, returning
+    // the errors if source is not a valid program.
+    pub fn compile(source: &str) -> Result<Self, Vec<LoxError>> {
+        let (tokens, errors) = Scanner::new().scan_tokens(source);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let program = Parser::new().parse(tokens)?;
+        Resolver::new().resolve(&program)?;
+        Ok(Prelude { program })
+    }
+
+    // program returns the compiled statements of the prelude.
+    pub fn program(&self) -> &[Stmt] {
+        &self.program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let prelude = Prelude::compile("fun f() {} var a = 1;").unwrap();
+        assert_eq!(prelude.program().len(), 2);
+
+        let errors = Prelude::compile("var a = ;").err().unwrap();
+        assert_eq!(errors[0].message(), "Expect expression.");
+        let errors = Prelude::compile("{").err().unwrap();
+        assert_eq!(errors[0].message(), "Expect '}' after block.");
+        let errors = Prelude::compile("{ var a = a; }").err().unwrap();
+        assert_eq!(
+            errors[0].message(),
+            "Cannot read local variable in its own initializer."
+        );
+    }
+}
@@ -1,4 +1,7 @@
+use std::rc::Rc;
+
 use super::expr::*;
+use super::stmt::*;
 use super::token;
 
 pub struct AstPrinter {}
@@ -12,11 +15,33 @@ impl AstPrinter {
     pub fn print(&mut self, expr: Expr) -> String {
         expr.accept(self)
     }
+
+    // print_program prints each statement in program on a line of its own.
+    pub fn print_program(&mut self, program: &[Stmt]) -> String {
+        let lines: Vec<String> = program.iter().map(|stmt| stmt.accept(self)).collect();
+        lines.join("\n")
+    }
+
+    // parenthesize prints name followed by each of parts, space separated
+    // and wrapped in parentheses.
+    fn parenthesize(name: &str, parts: &[String]) -> String {
+        let mut out = format!("({}", name);
+        for part in parts {
+            out.push(' ');
+            out.push_str(part);
+        }
+        out.push(')');
+        out
+    }
 }
 
 impl Visitor for AstPrinter {
     type Result = String;
 
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> String {
+        format!("(= {} {})", expr.name.lexeme, expr.value.accept(self))
+    }
+
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
         format!(
             "({op} {left} {right})",
@@ -26,18 +51,108 @@ impl Visitor for AstPrinter {
         )
     }
 
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> String {
+        let mut parts = vec![expr.callee.accept(self)];
+        parts.extend(expr.arguments.iter().map(|a| a.accept(self)));
+        Self::parenthesize("call", &parts)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> String {
+        format!(
+            "([] {} {})",
+            expr.object.accept(self),
+            expr.index.accept(self)
+        )
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> String {
+        let elements: Vec<String> = expr.elements.iter().map(|e| e.accept(self)).collect();
+        Self::parenthesize("list", &elements)
+    }
+
+    fn visit_map_expr(&mut self, expr: &MapExpr) -> String {
+        let mut parts = Vec::new();
+        for (key, value) in &expr.entries {
+            parts.push(key.accept(self));
+            parts.push(value.accept(self));
+        }
+        Self::parenthesize("map", &parts)
+    }
+
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) -> String {
+        let function = &expr.function;
+        let params: Vec<&str> = function.params.iter().map(|p| p.lexeme.as_str()).collect();
+        let mut parts = vec![format!("({})", params.join(" "))];
+        parts.extend(function.body.iter().map(|s| s.accept(self)));
+        Self::parenthesize("fun", &parts)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> String {
+        format!("(. {} {})", expr.object.accept(self), expr.name.lexeme)
+    }
+
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
         format!("(group {})", expr.expression.accept(self))
     }
 
+    fn visit_increment_expr(&mut self, expr: &IncrementExpr) -> String {
+        let target = expr.target.accept(self);
+        if expr.prefix {
+            format!("({} {})", expr.operator.lexeme, target)
+        } else {
+            format!("({} {})", target, expr.operator.lexeme)
+        }
+    }
+
     fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
         use token::Literal;
         match expr.value {
             Literal::Number(n) => format!("{}", n),
             Literal::String(ref s) => format!("\"{}\"", s),
+            Literal::Bool(b) => format!("{}", b),
+            Literal::Nil => String::from("nil"),
         }
     }
 
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        format!(
+            "({op} {left} {right})",
+            op = expr.operator.lexeme,
+            left = expr.left.accept(self),
+            right = expr.right.accept(self)
+        )
+    }
+
+    fn visit_set_index_expr(&mut self, expr: &SetIndexExpr) -> String {
+        format!(
+            "(= ([] {} {}) {})",
+            expr.object.accept(self),
+            expr.index.accept(self),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> String {
+        format!(
+            "(= (. {} {}) {})",
+            expr.object.accept(self),
+            expr.name.lexeme,
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> String {
+        format!("(. super {})", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) -> String {
+        String::from("this")
+    }
+
+    fn visit_throw_expr(&mut self, expr: &ThrowExpr) -> String {
+        format!("(throw {})", expr.value.accept(self))
+    }
+
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
         format!(
             "({op} {expr})",
@@ -45,19 +160,133 @@ impl Visitor for AstPrinter {
             expr = expr.expression.accept(self)
         )
     }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        expr.name.lexeme.to_string()
+    }
+}
+
+impl StmtVisitor for AstPrinter {
+    type Result = String;
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> String {
+        let statements: Vec<String> = stmt.statements.iter().map(|s| s.accept(self)).collect();
+        Self::parenthesize("block", &statements)
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) -> String {
+        Self::parenthesize("break", &[])
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> String {
+        let mut parts = vec![stmt.name.lexeme.to_string()];
+        if let Some(ref superclass) = stmt.superclass {
+            parts.push(String::from("<"));
+            parts.push(superclass.accept(self));
+        }
+        parts.extend(stmt.fields.iter().map(|f| self.visit_var_stmt(f)));
+        parts.extend(stmt.methods.iter().map(|m| m.accept(self)));
+        for method in &stmt.class_methods {
+            let method = method.accept(self);
+            parts.push(Self::parenthesize("static", &[method]));
+        }
+        Self::parenthesize("class", &parts)
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &ContinueStmt) -> String {
+        Self::parenthesize("continue", &[])
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> String {
+        Self::parenthesize(";", &[stmt.expression.accept(self)])
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> String {
+        let mut parts = vec![stmt.name.lexeme.to_string()];
+        if !stmt.getter {
+            let params: Vec<&str> = stmt.params.iter().map(|p| p.lexeme.as_str()).collect();
+            parts.push(format!("({})", params.join(" ")));
+        }
+        parts.extend(stmt.body.iter().map(|s| s.accept(self)));
+        let name = if stmt.getter { "getter" } else { "fun" };
+        Self::parenthesize(name, &parts)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> String {
+        let mut parts = vec![stmt.condition.accept(self), stmt.then_branch.accept(self)];
+        if let Some(ref else_branch) = stmt.else_branch {
+            parts.push(else_branch.accept(self));
+        }
+        Self::parenthesize("if", &parts)
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> String {
+        let parts = [
+            stmt.path.lexeme.to_string(),
+            String::from("as"),
+            stmt.name.lexeme.to_string(),
+        ];
+        Self::parenthesize("import", &parts)
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> String {
+        Self::parenthesize("print", &[stmt.expression.accept(self)])
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> String {
+        let parts: Vec<String> = stmt.value.iter().map(|v| v.accept(self)).collect();
+        Self::parenthesize("return", &parts)
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> String {
+        let body: Vec<String> = stmt.body.iter().map(|s| s.accept(self)).collect();
+        let mut handler = vec![stmt.name.lexeme.to_string()];
+        handler.extend(stmt.handler.iter().map(|s| s.accept(self)));
+        let parts = [
+            Self::parenthesize("block", &body),
+            Self::parenthesize("catch", &handler),
+        ];
+        Self::parenthesize("try", &parts)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> String {
+        let mut parts = vec![stmt.name.lexeme.to_string()];
+        if let Some(ref initializer) = stmt.initializer {
+            parts.push(String::from("="));
+            parts.push(initializer.accept(self));
+        }
+        let name = if stmt.constant { "const" } else { "var" };
+        Self::parenthesize(name, &parts)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> String {
+        let mut parts = vec![stmt.condition.accept(self), stmt.body.accept(self)];
+        if let Some(ref increment) = stmt.increment {
+            parts.push(increment.accept(self));
+        }
+        Self::parenthesize("while", &parts)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use token::{Literal, Token, TokenType};
+    use crate::span::{Position, Span};
+    use token::{Lexeme, Literal, Token, TokenType};
+
+    fn span() -> Span {
+        Span::new(Position::new(1, 1, 0), Position::new(1, 1, 0))
+    }
 
     fn make_token(token_type: TokenType, lexeme: &str) -> Token {
+        let end = Position::new(1, 1 + lexeme.len() as u64, lexeme.len());
         Token {
             token_type,
-            lexeme: lexeme.to_owned(),
-            line: 1,
+            lexeme: Lexeme::from(lexeme),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), end),
             literal: None,
+            error: false,
         }
     }
 
@@ -84,7 +313,7 @@ mod tests {
         let mut printer = AstPrinter::new();
         let ex = Expr::make_literal(Literal::Number(2.0));
         assert_eq!(ex.accept(&mut printer), "2");
-        let ex = Expr::make_literal(Literal::String(String::from("2.0")));
+        let ex = Expr::make_literal(Literal::String("2.0".into()));
         assert_eq!(ex.accept(&mut printer), "\"2.0\"");
     }
 
@@ -98,6 +327,138 @@ mod tests {
         assert_eq!(ex.accept(&mut printer), "(- 2)");
     }
 
+    #[test]
+    fn test_visit_variable_exprs() {
+        let mut printer = AstPrinter::new();
+        let ex = Expr::make_assign(
+            make_token(TokenType::Identifier, "a"),
+            Expr::make_logical(
+                Expr::make_variable(make_token(TokenType::Identifier, "b")),
+                make_token(TokenType::Or, "or"),
+                Expr::make_call(
+                    Expr::make_variable(make_token(TokenType::Identifier, "f")),
+                    make_token(TokenType::RightParen, ")"),
+                    vec![Expr::make_literal(Literal::Nil)],
+                ),
+            ),
+        );
+        assert_eq!(ex.accept(&mut printer), "(= a (or b (call f nil)))");
+    }
+
+    fn number(n: f64) -> Expr {
+        Expr::make_literal(Literal::Number(n))
+    }
+
+    #[test]
+    fn test_visit_simple_stmts() {
+        let mut printer = AstPrinter::new();
+        let stmt = Stmt::make_expression(number(1.0));
+        assert_eq!(stmt.accept(&mut printer), "(; 1)");
+        let stmt = Stmt::make_print(number(1.0));
+        assert_eq!(stmt.accept(&mut printer), "(print 1)");
+        let stmt = Stmt::make_var(make_token(TokenType::Identifier, "a"), None, span());
+        assert_eq!(stmt.accept(&mut printer), "(var a)");
+        let stmt = Stmt::make_var(
+            make_token(TokenType::Identifier, "a"),
+            Some(number(1.0)),
+            span(),
+        );
+        assert_eq!(stmt.accept(&mut printer), "(var a = 1)");
+        let stmt = Stmt::make_return(make_token(TokenType::Return, "return"), None);
+        assert_eq!(stmt.accept(&mut printer), "(return)");
+    }
+
+    #[test]
+    fn test_visit_control_flow_stmts() {
+        let mut printer = AstPrinter::new();
+        let stmt = Stmt::make_if(
+            Expr::make_literal(Literal::Bool(true)),
+            Stmt::make_print(number(1.0)),
+            Some(Stmt::make_print(number(2.0))),
+        );
+        assert_eq!(stmt.accept(&mut printer), "(if true (print 1) (print 2))");
+        let stmt = Stmt::make_while(
+            make_token(TokenType::While, "while"),
+            Expr::make_literal(Literal::Bool(false)),
+            Stmt::make_block(vec![
+                Stmt::make_print(number(1.0)),
+                Stmt::make_block(vec![]),
+            ]),
+        );
+        assert_eq!(
+            stmt.accept(&mut printer),
+            "(while false (block (print 1) (block)))"
+        );
+        let stmt = Stmt::make_for(
+            make_token(TokenType::For, "for"),
+            Expr::make_literal(Literal::Bool(true)),
+            Some(number(1.0)),
+            Stmt::make_block(vec![
+                Stmt::make_break(make_token(TokenType::Break, "break")),
+                Stmt::make_continue(make_token(TokenType::Continue, "continue")),
+            ]),
+        );
+        assert_eq!(
+            stmt.accept(&mut printer),
+            "(while true (block (break) (continue)) 1)"
+        );
+    }
+
+    #[test]
+    fn test_visit_class_stmt() {
+        let mut printer = AstPrinter::new();
+        let method = Rc::new(FunctionStmt {
+            name: make_token(TokenType::Identifier, "f"),
+            params: vec![
+                make_token(TokenType::Identifier, "a"),
+                make_token(TokenType::Identifier, "b"),
+            ],
+            body: vec![Stmt::make_return(
+                make_token(TokenType::Return, "return"),
+                Some(number(1.0)),
+            )],
+            getter: false,
+            constants: Default::default(),
+        });
+        let getter = Rc::new(FunctionStmt {
+            name: make_token(TokenType::Identifier, "g"),
+            params: Vec::new(),
+            body: Vec::new(),
+            getter: true,
+            constants: Default::default(),
+        });
+        let stmt = Stmt::make_class(
+            make_token(TokenType::Identifier, "C"),
+            Some(Expr::make_variable(make_token(TokenType::Identifier, "B"))),
+            vec![Rc::new(VarStmt {
+                name: make_token(TokenType::Identifier, "x"),
+                initializer: Some(number(0.0)),
+                span: span(),
+                constant: false,
+            })],
+            vec![method],
+            vec![getter],
+        );
+        assert_eq!(
+            stmt.accept(&mut printer),
+            "(class C < B (var x = 0) (fun f (a b) (return 1)) (static (getter g)))"
+        );
+    }
+
+    #[test]
+    fn test_print_program() {
+        let mut printer = AstPrinter::new();
+        let program = vec![
+            Stmt::make_var(
+                make_token(TokenType::Identifier, "a"),
+                Some(number(1.0)),
+                span(),
+            ),
+            Stmt::make_print(number(2.0)),
+        ];
+        assert_eq!(printer.print_program(&program), "(var a = 1)\n(print 2)");
+    }
+
     #[test]
     fn test_print() {
         let mut printer = AstPrinter::new();
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::error::LoxError;
+use super::parser::{Dialect, Parser};
+use super::resolver::Resolver;
+use super::scanner::Scanner;
+use super::span::Span;
+
+// ReferenceKind is how a Reference refers to its variable.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ReferenceKind {
+    // Declaration is the name of a var, function or class declaration, or
+    // of a parameter.
+    Declaration,
+    Read,
+    // Write is the target of an assignment or an increment. Compound
+    // assignments and increments also read their target, so the name is
+    // referred to twice.
+    Write,
+}
+
+impl fmt::Display for ReferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            ReferenceKind::Declaration => "declaration",
+            ReferenceKind::Read => "read",
+            ReferenceKind::Write => "write",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+// Reference is an occurrence of the name of a variable in the source.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Reference {
+    pub span: Span,
+    pub kind: ReferenceKind,
+}
+
+// Variable is a variable of a program, together with all the references to
+// it, in source order. Each local declaration is a variable of its own,
+// while globals are identified by their name alone, so a global that is
+// never declared, e.g. a native function, is still a variable.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub global: bool,
+    pub references: Vec<Reference>,
+}
+
+impl Variable {
+    // refers_to returns whether one of the references of the variable
+    // covers offset.
+    pub fn refers_to(&self, offset: usize) -> bool {
+        self.references
+            .iter()
+            .any(|reference| reference.span.bytes().contains(&offset))
+    }
+}
+
+// ReferenceIndex is the index of the variables of a program, built by a
+// Resolver, and so agreeing with the scoping rules used to run it. The
+// implicit variables this and super are not indexed.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ReferenceIndex {
+    variables: Vec<Variable>,
+    // globals maps the names of the globals to their index in variables.
+    globals: HashMap<String, usize>,
+}
+
+impl ReferenceIndex {
+    pub fn new() -> Self {
+        ReferenceIndex::default()
+    }
+
+    // variables returns the variables of the program, in the order they
+    // were first referred to.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    // variable_at returns the variable referred to at offset in the source,
+    // if any.
+    pub fn variable_at(&self, offset: usize) -> Option<&Variable> {
+        self.variables
+            .iter()
+            .find(|variable| variable.refers_to(offset))
+    }
+
+    // local adds a new local variable named name, returning its id.
+    pub(crate) fn local(&mut self, name: &str) -> usize {
+        self.add(name, false)
+    }
+
+    // global returns the id of the global variable named name, adding it
+    // if it is not yet indexed.
+    pub(crate) fn global(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.globals.get(name) {
+            return id;
+        }
+        let id = self.add(name, true);
+        self.globals.insert(name.to_owned(), id);
+        id
+    }
+
+    fn add(&mut self, name: &str, global: bool) -> usize {
+        self.variables.push(Variable {
+            name: name.to_owned(),
+            global,
+            references: Vec::new(),
+        });
+        self.variables.len() - 1
+    }
+
+    pub(crate) fn add_reference(&mut self, variable: usize, span: Span, kind: ReferenceKind) {
+        self.variables[variable]
+            .references
+            .push(Reference { span, kind });
+    }
+
+    // finish orders the references of each variable by their position,
+    // since the resolver does not visit the program in source order, e.g.
+    // the value of an assignment is resolved before its target.
+    pub(crate) fn finish(&mut self) {
+        for variable in &mut self.variables {
+            variable
+                .references
+                .sort_by_key(|reference| reference.span.start.offset);
+        }
+    }
+}
+
+// index scans, parses and resolves source, returning the ReferenceIndex of
+// its variables, or the errors preventing it from being built.
+pub fn index(
+    source: &str,
+    scanner: &Scanner,
+    dialect: Dialect,
+) -> Result<ReferenceIndex, Vec<LoxError>> {
+    let (tokens, errors) = scanner.scan_tokens(source);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let program = Parser::new()
+        .with_dialect(dialect)
+        .with_unicode_identifiers(scanner.unicode_identifiers())
+        .parse(tokens)?;
+    Resolver::new().resolve_with_index(&program)
+}
+
+// find_references returns all references to the variable at offset in
+// source, in source order, or no references if there is no variable at
+// offset. Errors are returned as for index.
+pub fn find_references(
+    source: &str,
+    offset: usize,
+    scanner: &Scanner,
+    dialect: Dialect,
+) -> Result<Vec<Reference>, Vec<LoxError>> {
+    let index = index(source, scanner, dialect)?;
+    Ok(index
+        .variable_at(offset)
+        .map(|variable| variable.references.clone())
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_of(source: &str) -> ReferenceIndex {
+        index(source, &Scanner::new(), Dialect::Standard).unwrap()
+    }
+
+    // references returns the kind and source text of each reference of the
+    // variable at offset.
+    fn references(source: &str, offset: usize) -> Vec<(ReferenceKind, &str)> {
+        let index = index_of(source);
+        let variable = index.variable_at(offset).unwrap();
+        variable
+            .references
+            .iter()
+            .map(|reference| (reference.kind, &source[reference.span.bytes()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_index_kinds() {
+        use ReferenceKind::*;
+        let source = "var a = 1; a = a + 1; a += 2; a++; print a;";
+        let kinds: Vec<_> = references(source, 4).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            kinds,
+            vec![Declaration, Write, Read, Read, Write, Read, Write, Read]
+        );
+    }
+
+    #[test]
+    fn test_index_scopes() {
+        use ReferenceKind::*;
+        let source = "var a = 1;\n\
+                      fun f(a) { { var b = a; } return a; }\n\
+                      print a + clock();";
+        let index = index_of(source);
+        let names: Vec<_> = index
+            .variables()
+            .iter()
+            .map(|variable| (variable.name.as_str(), variable.global))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a", true),
+                ("f", true),
+                ("a", false),
+                ("b", false),
+                ("clock", true)
+            ]
+        );
+        // The parameter is read by the inner initializer and the return.
+        let parameter = source.find("(a)").unwrap() + 1;
+        assert_eq!(
+            references(source, parameter),
+            vec![(Declaration, "a"), (Read, "a"), (Read, "a")]
+        );
+        let global = source.rfind("a +").unwrap();
+        assert_eq!(
+            references(source, global),
+            vec![(Declaration, "a"), (Read, "a")]
+        );
+        assert!(index.variable_at(source.find("fun").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_find_references() {
+        let source = "var a = 1;\nprint a;\na = 2;";
+        let find = |offset| find_references(source, offset, &Scanner::new(), Dialect::Standard);
+        let references = find(source.find("a;").unwrap()).unwrap();
+        let described: Vec<String> = references
+            .iter()
+            .map(|reference| format!("{} {}", reference.span, reference.kind))
+            .collect();
+        assert_eq!(
+            described,
+            vec!["1:5-1:6 declaration", "2:7-2:8 read", "3:1-3:2 write"]
+        );
+        assert_eq!(find(0), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_index_errors() {
+        let result = index("{ var a = a; }", &Scanner::new(), Dialect::Standard);
+        assert_eq!(
+            result.unwrap_err()[0].message(),
+            "Cannot read local variable in its own initializer."
+        );
+    }
+}
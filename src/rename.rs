@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use super::error::FixIt;
+use super::messages::{self, Message};
+use super::parser::Dialect;
+use super::references::{self, ReferenceIndex};
+use super::scanner::Scanner;
+
+// rename returns the edits renaming the variable at offset in source to
+// new_name, one for each reference to it, ordered by position. Renaming is
+// refused if source has errors, if new_name is not an identifier, or if the
+// renamed program would resolve differently, i.e. if new_name would shadow,
+// or be shadowed by, another variable, or collide with one declared in the
+// same scope.
+pub fn rename(
+    source: &str,
+    offset: usize,
+    new_name: &str,
+    scanner: &Scanner,
+    dialect: Dialect,
+) -> Result<Vec<FixIt>, Message> {
+    let index = references::index(source, scanner, dialect)
+        .map_err(|_| Message::from(messages::RENAME_WITH_ERRORS))?;
+    let variable = index
+        .variable_at(offset)
+        .ok_or_else(|| Message::from(messages::NOTHING_TO_RENAME))?;
+    // A module imported without "as" is named by the file name of its path,
+    // which is not renamed.
+    let named_by_path = variable
+        .references
+        .iter()
+        .any(|reference| source[reference.span.bytes()] != variable.name);
+    if named_by_path {
+        return Err(messages::RENAME_IMPORT_NAME.with(&[&variable.name]));
+    }
+    if !scanner.is_identifier(new_name) {
+        return Err(messages::INVALID_NAME.with(&[&new_name]));
+    }
+    let mut edits: Vec<FixIt> = Vec::new();
+    for reference in &variable.references {
+        // Compound assignments refer to their target twice, at the same
+        // span.
+        if edits.last().map(|edit| edit.span) == Some(reference.span) {
+            continue;
+        }
+        edits.push(FixIt {
+            span: reference.span,
+            replacement: new_name.to_owned(),
+        });
+    }
+
+    let conflict = || messages::RENAME_CONFLICT.with(&[&variable.name, &new_name]);
+    let renamed = match references::index(&apply(source, &edits), scanner, dialect) {
+        Ok(renamed) => renamed,
+        Err(_) => return Err(conflict()),
+    };
+    if bindings(&index) != bindings(&renamed) {
+        return Err(conflict());
+    }
+    Ok(edits)
+}
+
+// apply applies edits, which must be ordered by position and not overlap,
+// to source.
+fn apply(source: &str, edits: &[FixIt]) -> String {
+    let mut applied = String::with_capacity(source.len());
+    let mut end = 0;
+    for edit in edits {
+        let range = edit.span.bytes();
+        applied.push_str(&source[end..range.start]);
+        applied.push_str(&edit.replacement);
+        end = range.end;
+    }
+    applied.push_str(&source[end..]);
+    applied
+}
+
+// bindings returns, for each reference of the program in source order, the
+// variable it refers to, numbered in the order the variables are first
+// referred to. Renaming preserves the order and number of references, so
+// two programs have the same bindings exactly when every name refers to
+// the same variable in both.
+fn bindings(index: &ReferenceIndex) -> Vec<usize> {
+    let mut references: Vec<(usize, usize)> = Vec::new();
+    for (id, variable) in index.variables().iter().enumerate() {
+        references.extend(
+            variable
+                .references
+                .iter()
+                .map(|reference| (reference.span.start.offset, id)),
+        );
+    }
+    // The sort is stable, keeping the two references of a compound
+    // assignment together.
+    references.sort_by_key(|&(offset, _)| offset);
+    let mut numbers = HashMap::new();
+    references
+        .into_iter()
+        .map(|(_, id)| {
+            let next = numbers.len();
+            *numbers.entry(id).or_insert(next)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rename_at renames the variable at the first occurrence of at in
+    // source, returning the renamed source.
+    fn rename_at(source: &str, at: &str, new_name: &str) -> Result<String, String> {
+        let offset = source.find(at).unwrap();
+        let edits = rename(source, offset, new_name, &Scanner::new(), Dialect::Standard)
+            .map_err(|err| err.to_string())?;
+        Ok(apply(source, &edits))
+    }
+
+    #[test]
+    fn test_rename() {
+        let source = "var a = 1;\n\
+                      fun f(a) { a += 1; a++; return a; }\n\
+                      print a + f(a);";
+        assert_eq!(
+            rename_at(source, "a)", "n"),
+            Ok(String::from(
+                "var a = 1;\n\
+                 fun f(n) { n += 1; n++; return n; }\n\
+                 print a + f(a);"
+            ))
+        );
+        assert_eq!(
+            rename_at(source, "a =", "total"),
+            Ok(String::from(
+                "var total = 1;\n\
+                 fun f(a) { a += 1; a++; return a; }\n\
+                 print total + f(total);"
+            ))
+        );
+        // Functions are variables too, and so are undeclared globals.
+        assert_eq!(
+            rename_at("fun f() {} f(); print clock();", "clock", "now"),
+            Ok(String::from("fun f() {} f(); print now();"))
+        );
+    }
+
+    #[test]
+    fn test_rename_conflicts() {
+        let conflict = |old: &str, new: &str| {
+            Err(format!(
+                "Renaming '{}' to '{}' would change what the program refers to.",
+                old, new
+            ))
+        };
+        // Colliding with a variable of the same scope.
+        let source = "{ var a = 1; var b = 2; print a + b; }";
+        assert_eq!(rename_at(source, "a =", "b"), conflict("a", "b"));
+        // Shadowing a variable read in the scope.
+        let source = "var g = 1; fun f(x) { return x + g; }";
+        assert_eq!(rename_at(source, "x)", "g"), conflict("x", "g"));
+        // Being shadowed where the variable is read.
+        let source = "var g = 1; fun f(x) { return g; }";
+        assert_eq!(rename_at(source, "g =", "x"), conflict("g", "x"));
+        // Merging two globals.
+        assert_eq!(
+            rename_at("var a = clock();", "a =", "clock"),
+            conflict("a", "clock")
+        );
+        // Shadowing is fine where the shadowed variable is not used.
+        let source = "var g = 1; fun f(x) { return x; }";
+        assert_eq!(
+            rename_at(source, "x)", "g"),
+            Ok(String::from("var g = 1; fun f(g) { return g; }"))
+        );
+    }
+
+    #[test]
+    fn test_rename_invalid() {
+        let source = "var a = 1; print a;";
+        assert_eq!(
+            rename_at(source, "a;", "class"),
+            Err(String::from("'class' is not a valid name."))
+        );
+        assert_eq!(
+            rename_at(source, "a;", "b c"),
+            Err(String::from("'b c' is not a valid name."))
+        );
+        assert_eq!(
+            rename_at(source, "print", "b"),
+            Err(String::from("No variable to rename here."))
+        );
+        assert_eq!(
+            rename_at("import \"lib.lox\"; print lib;", "lib;", "b"),
+            Err(String::from(
+                "Cannot rename 'lib', as it is named by an import path."
+            ))
+        );
+        assert_eq!(
+            rename_at("print a", "a", "b"),
+            Err(String::from("Cannot rename in a program with errors."))
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::{self, BufRead, Write};
+
+use loxrust::trace::Trace;
+
+const HELP: &str = "Commands: next (n, or an empty line), back (b), goto <step> (g), \
+                    first, last, help (h), quit (q).";
+
+// run runs the "replay" viewer, stepping through trace as directed by the
+// commands read from input, one per line, and showing the state of the
+// program at each step on output. Unlike the program itself, the viewer can
+// step backwards, since any state can be reconstructed from the trace.
+pub fn run<R: BufRead, W: Write>(trace: &Trace, input: R, output: &mut W) -> io::Result<()> {
+    let last = trace.events().len();
+    let mut step = 0;
+    writeln!(output, "{}", HELP)?;
+    show(trace, step, output)?;
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let next = match words.next() {
+            None | Some("n") | Some("next") => step + 1,
+            Some("b") | Some("back") => step.saturating_sub(1),
+            Some("first") => 0,
+            Some("last") => last,
+            Some("g") | Some("goto") => match words.next().map(str::parse) {
+                Some(Ok(target)) => target,
+                _ => {
+                    writeln!(output, "Expect a step number.")?;
+                    continue;
+                }
+            },
+            Some("q") | Some("quit") => break,
+            Some("h") | Some("help") => {
+                writeln!(output, "{}", HELP)?;
+                continue;
+            }
+            Some(command) => {
+                writeln!(output, "Unknown command '{}'. {}", command, HELP)?;
+                continue;
+            }
+        };
+        if next > last {
+            writeln!(output, "The trace ends at step {}.", last)?;
+            continue;
+        }
+        step = next;
+        show(trace, step, output)?;
+    }
+    Ok(())
+}
+
+// show shows the event leading to step, and the state of the program after
+// it.
+fn show<W: Write>(trace: &Trace, step: usize, output: &mut W) -> io::Result<()> {
+    let last = trace.events().len();
+    match step.checked_sub(1).map(|i| &trace.events()[i]) {
+        Some(event) => writeln!(
+            output,
+            "[step {}/{}] line {}: {}",
+            step,
+            last,
+            event.line(),
+            event
+        )?,
+        None => writeln!(output, "[step 0/{}] start", last)?,
+    }
+    let state = trace.state(step);
+    show_variables(output, "globals", &state.globals)?;
+    for (depth, frame) in state.frames.iter().enumerate() {
+        let label = format!("#{} {}", depth, frame.function);
+        show_variables(output, &label, &frame.variables)?;
+    }
+    Ok(())
+}
+
+// show_variables shows label followed by variables, as a comma separated
+// list of "name = value".
+fn show_variables<W: Write>(
+    output: &mut W,
+    label: &str,
+    variables: &[(String, String)],
+) -> io::Result<()> {
+    if variables.is_empty() {
+        return writeln!(output, "  {}", label);
+    }
+    let variables: Vec<String> = variables
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect();
+    writeln!(output, "  {}: {}", label, variables.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let trace = Trace::parse(
+            "define\t1\tglobal\ta\t1\n\
+             call\t2\tf\t2\n\
+             define\t1\tlocal\tn\t2\n\
+             assign\t3\tglobal\ta\t2\n\
+             return\t2\tf\tnil\n",
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        run(
+            &trace,
+            "n\nn\n\nback\ng 9\nx\nlast\nq\nn\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let expected = format!(
+            "{help}\n\
+             [step 0/5] start\n  globals\n  #0 script\n\
+             [step 1/5] line 1: define a = 1\n  globals: a = 1\n  #0 script\n\
+             [step 2/5] line 2: call f(2)\n  globals: a = 1\n  #0 script\n  #1 f\n\
+             [step 3/5] line 1: define n = 2\n  globals: a = 1\n  #0 script\n  #1 f: n = 2\n\
+             [step 2/5] line 2: call f(2)\n  globals: a = 1\n  #0 script\n  #1 f\n\
+             The trace ends at step 5.\n\
+             Unknown command 'x'. {help}\n\
+             [step 5/5] line 2: f returned nil\n  globals: a = 2\n  #0 script\n",
+            help = HELP
+        );
+        assert_eq!(output, expected);
+    }
+}
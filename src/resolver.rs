@@ -0,0 +1,860 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::const_eval::eval_const;
+use super::constants::Constants;
+use super::error::{FixIt, LoxError};
+use super::expr::*;
+use super::messages::{self, Message, MessageId};
+use super::parser::WarningCallback;
+use super::references::{ReferenceIndex, ReferenceKind};
+use super::stmt::*;
+use super::token::Token;
+
+// A Resolver performs a static pass over a parsed program before it is
+// interpreted. It resolves each variable reference to the number of scopes
+// between the reference and the declaration of the variable, recording the
+// depth in the AST, and reports the errors that can be detected without
+// running the program. It also canonicalizes the literals of each function
+// into the function's constant table.
+pub struct Resolver<'a> {
+    // warning_cb is an optional WarningCallback that will be notified for
+    // each local variable that is declared but never used.
+    warning_cb: Option<&'a WarningCallback>,
+}
+
+#[allow(clippy::new_without_default)]
+impl<'a> Resolver<'a> {
+    pub fn new() -> Self {
+        Resolver { warning_cb: None }
+    }
+
+    // with_warning_cb installs warning_cb on the resolver, to be called for
+    // each warning found while resolving.
+    pub fn with_warning_cb(mut self, warning_cb: &'a WarningCallback) -> Self {
+        self.warning_cb = Some(warning_cb);
+        self
+    }
+
+    // resolve resolves the variables of program, as produced by a Parser.
+    // resolve returns the errors encountered if any, in which case program
+    // must not be run.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "resolve", skip_all)
+    )]
+    pub fn resolve(&self, program: &[Stmt]) -> Result<(), Vec<LoxError>> {
+        self.resolve_with_constants(program).map(|_| ())
+    }
+
+    // resolve_with_constants is as resolve, but returns the constant table
+    // of the top level of program. The tables of functions are recorded in
+    // their FunctionStmt.
+    pub fn resolve_with_constants(&self, program: &[Stmt]) -> Result<Constants, Vec<LoxError>> {
+        let mut ctx = self.context(None);
+        ctx.resolve_statements(program);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(errors = ctx.errors.len(), "resolved");
+        if ctx.errors.is_empty() {
+            Ok(ctx.constants.pop().expect("no top-level constants"))
+        } else {
+            Err(ctx.errors)
+        }
+    }
+
+    // resolve_with_index is as resolve, but returns the ReferenceIndex of
+    // the variables of program.
+    pub fn resolve_with_index(&self, program: &[Stmt]) -> Result<ReferenceIndex, Vec<LoxError>> {
+        let mut ctx = self.context(Some(ReferenceIndex::new()));
+        ctx.resolve_statements(program);
+        if ctx.errors.is_empty() {
+            let mut index = ctx.index.take().expect("no reference index");
+            index.finish();
+            Ok(index)
+        } else {
+            Err(ctx.errors)
+        }
+    }
+
+    fn context(&self, index: Option<ReferenceIndex>) -> ResolverContext<'a> {
+        ResolverContext {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            constants: vec![Constants::new()],
+            errors: Vec::new(),
+            index,
+            global_constants: HashSet::new(),
+            warning_cb: self.warning_cb,
+        }
+    }
+}
+
+// FunctionType is the kind of function the resolver is currently in.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+// ClassType is the kind of class the resolver is currently in.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// Local is a variable declared in a local scope.
+struct Local {
+    // defined is whether the declaration has been fully defined, i.e. its
+    // initializer has been resolved.
+    defined: bool,
+
+    // used is whether the variable has been referred to.
+    used: bool,
+
+    // var is the name and the FixIt removing the declaration, if any, of a
+    // local declared by a var statement. Only such locals are warned about
+    // when never used.
+    var: Option<(Token, Option<FixIt>)>,
+
+    // variable is the id of the local in the ReferenceIndex being built,
+    // if any. Implicit locals are not indexed.
+    variable: Option<usize>,
+
+    // constant is whether the local is declared by a const statement.
+    constant: bool,
+}
+
+impl Local {
+    fn new(defined: bool) -> Self {
+        Local {
+            defined,
+            used: false,
+            var: None,
+            variable: None,
+            constant: false,
+        }
+    }
+}
+
+// ResolverContext encapsulates the state of a single resolve of a program.
+struct ResolverContext<'a> {
+    // scopes is the stack of local scopes currently in scope, innermost
+    // last. The global scope is not tracked. Each scope maps the names
+    // declared in it to their Local.
+    scopes: Vec<HashMap<String, Local>>,
+
+    current_function: FunctionType,
+    current_class: ClassType,
+
+    // loop_depth is the number of loops enclosing the current statement,
+    // within the current function.
+    loop_depth: usize,
+
+    // constants is the stack of constant tables of the functions being
+    // resolved, innermost last. The first table is that of the top level.
+    constants: Vec<Constants>,
+
+    // errors is the errors reported so far.
+    errors: Vec<LoxError>,
+
+    // index is the ReferenceIndex being built, if one was asked for.
+    index: Option<ReferenceIndex>,
+
+    // global_constants is the names of the globals currently declared as
+    // constants. Only assignments after the declaration are detected, e.g.
+    // not those in a function declared before it, which the interpreter
+    // reports when run.
+    global_constants: HashSet<String>,
+
+    warning_cb: Option<&'a WarningCallback>,
+}
+
+impl<'a> ResolverContext<'a> {
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            stmt.accept(self);
+        }
+    }
+
+    fn resolve_function(&mut self, function: &FunctionStmt, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        // A loop does not extend into the functions declared in it.
+        let enclosing_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.begin_scope();
+        self.constants.push(Constants::new());
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        *function.constants.borrow_mut() = self.constants.pop().expect("no function constants");
+        self.end_scope();
+        self.loop_depth = enclosing_loop_depth;
+        self.current_function = enclosing_function;
+    }
+
+    // resolve_local records in depth the number of scopes between the
+    // innermost scope and the scope declaring name, leaving depth as None
+    // if name is not declared in any local scope, i.e. is a global. The
+    // local is marked as used. resolve_local returns the indexed variable
+    // of the local, if any.
+    fn resolve_local(&mut self, name: &str, depth: &Cell<Option<usize>>) -> Option<usize> {
+        let mut found = None;
+        let mut variable = None;
+        for (distance, scope) in self.scopes.iter_mut().rev().enumerate() {
+            if let Some(local) = scope.get_mut(name) {
+                local.used = true;
+                found = Some(distance);
+                variable = local.variable;
+                break;
+            }
+        }
+        depth.set(found);
+        variable
+    }
+
+    // reference records a reference of kind to name in the index, if one
+    // is being built. A reference with no local variable is to a global.
+    fn reference(&mut self, name: &Token, variable: Option<usize>, kind: ReferenceKind) {
+        if let Some(ref mut index) = self.index {
+            let variable = variable.unwrap_or_else(|| index.global(&name.lexeme));
+            index.add_reference(variable, name.span, kind);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    // end_scope pops the innermost scope, warning about the variables
+    // declared in it that were never used, in source order. Variables
+    // named with a leading underscore are not warned about.
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("no scope to end");
+        let mut unused: Vec<_> = scope
+            .into_iter()
+            .filter(|(name, local)| !local.used && !name.starts_with('_'))
+            .filter_map(|(_, local)| local.var)
+            .collect();
+        unused.sort_by_key(|(name, _)| name.span.start.offset);
+        for (name, fix) in unused {
+            let msg = messages::UNUSED_LOCAL.with(&[&name.lexeme]);
+            self.warning(&name, &msg, fix.as_ref());
+        }
+    }
+
+    // declare adds name to the innermost scope, marked as not yet ready
+    // for use.
+    fn declare(&mut self, name: &Token) {
+        let variable = match (&mut self.index, self.scopes.is_empty()) {
+            (Some(index), false) => Some(index.local(&name.lexeme)),
+            _ => None,
+        };
+        self.reference(name, variable, ReferenceKind::Declaration);
+        let mut local = Local::new(false);
+        local.variable = variable;
+        let already_declared = match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.lexeme.to_string(), local).is_some(),
+            None => {
+                // Redeclaring a global replaces it, also if it was a
+                // constant.
+                self.global_constants.remove(name.lexeme.as_str());
+                return;
+            }
+        };
+        if already_declared {
+            self.error(name, messages::ALREADY_DECLARED);
+        }
+    }
+
+    // define marks name in the innermost scope as ready for use.
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self.innermost_local(name) {
+            local.defined = true;
+        }
+    }
+
+    // check_assignable reports an error if name, resolved to depth, is a
+    // constant.
+    fn check_assignable(&mut self, name: &Token, depth: Option<usize>) {
+        let constant = match depth {
+            Some(depth) => {
+                let scope = &self.scopes[self.scopes.len() - 1 - depth];
+                scope
+                    .get(name.lexeme.as_str())
+                    .is_some_and(|local| local.constant)
+            }
+            None => self.global_constants.contains(name.lexeme.as_str()),
+        };
+        if constant {
+            self.error(name, messages::ASSIGN_TO_CONSTANT);
+        }
+    }
+
+    // innermost_local returns the Local of name in the innermost scope, if
+    // any.
+    fn innermost_local(&mut self, name: &Token) -> Option<&mut Local> {
+        self.scopes.last_mut()?.get_mut(name.lexeme.as_str())
+    }
+
+    // define_implicit defines the implicit variable name (e.g. "this") in
+    // the innermost scope.
+    fn define_implicit(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), Local::new(true));
+        }
+    }
+
+    // error records an error with msg at token.
+    fn error(&mut self, token: &Token, msg: MessageId) {
+        self.errors.push(LoxError::Resolve {
+            span: token.span,
+            lexeme: token.lexeme.to_string(),
+            message: msg.into(),
+        });
+    }
+
+    // warning reports a warning with msg and the optional fix at token to
+    // the registered warning_cb.
+    fn warning(&self, token: &Token, msg: &Message, fix: Option<&FixIt>) {
+        if let Some(f) = self.warning_cb {
+            f(token.span, msg, fix)
+        }
+    }
+}
+
+impl<'a> Visitor for ResolverContext<'a> {
+    type Result = ();
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        expr.value.accept(self);
+        let variable = self.resolve_local(&expr.name.lexeme, &expr.depth);
+        self.check_assignable(&expr.name, expr.depth.get());
+        self.reference(&expr.name, variable, ReferenceKind::Write);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) {
+        expr.callee.accept(self);
+        for argument in &expr.arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) {
+        self.resolve_function(&expr.function, FunctionType::Function);
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) {
+        expr.object.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_increment_expr(&mut self, expr: &IncrementExpr) {
+        expr.target.accept(self);
+        // Incrementing a variable writes it as well as reading it.
+        if let Expr::Variable(ref target) = *expr.target {
+            let variable = self.resolve_local(&target.name.lexeme, &target.depth);
+            self.check_assignable(&target.name, target.depth.get());
+            self.reference(&target.name, variable, ReferenceKind::Write);
+        }
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) {
+        for element in &expr.elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_map_expr(&mut self, expr: &MapExpr) {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) {
+        let constants = self.constants.last_mut().expect("no constants");
+        expr.constant.set(Some(constants.add(&expr.value)));
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+    }
+
+    fn visit_set_index_expr(&mut self, expr: &SetIndexExpr) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        expr.value.accept(self);
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) {
+        match self.current_class {
+            ClassType::None => self.error(&expr.keyword, messages::SUPER_OUTSIDE_CLASS),
+            ClassType::Class => self.error(&expr.keyword, messages::SUPER_WITHOUT_SUPERCLASS),
+            ClassType::Subclass => {
+                self.resolve_local("super", &expr.depth);
+            }
+        }
+    }
+
+    fn visit_this_expr(&mut self, expr: &ThisExpr) {
+        if self.current_class == ClassType::None {
+            self.error(&expr.keyword, messages::THIS_OUTSIDE_CLASS);
+            return;
+        }
+        self.resolve_local("this", &expr.depth);
+    }
+
+    fn visit_throw_expr(&mut self, expr: &ThrowExpr) {
+        expr.value.accept(self);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) {
+        let name = &expr.name;
+        let in_initializer = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(name.lexeme.as_str()))
+            .is_some_and(|local| !local.defined);
+        if in_initializer {
+            self.error(name, messages::READ_IN_OWN_INITIALIZER);
+        }
+        let variable = self.resolve_local(&name.lexeme, &expr.depth);
+        self.reference(name, variable, ReferenceKind::Read);
+    }
+}
+
+impl<'a> StmtVisitor for ResolverContext<'a> {
+    type Result = ();
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.begin_scope();
+        self.resolve_statements(&stmt.statements);
+        self.end_scope();
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        if self.loop_depth == 0 {
+            self.error(&stmt.keyword, messages::BREAK_OUTSIDE_LOOP);
+        }
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        // Field initializers are evaluated in the scope enclosing the class,
+        // so they are resolved before entering the class.
+        let mut field_names = HashSet::new();
+        for field in &stmt.fields {
+            if !field_names.insert(field.name.lexeme.as_str()) {
+                let msg = messages::FIELD_ALREADY_DECLARED;
+                self.error(&field.name, msg);
+            }
+            if let Some(ref initializer) = field.initializer {
+                initializer.accept(self);
+            }
+        }
+
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        if let Some(ref superclass) = stmt.superclass {
+            if let Expr::Variable(ref variable) = *superclass {
+                if variable.name.lexeme == stmt.name.lexeme {
+                    self.error(&variable.name, messages::INHERIT_FROM_SELF);
+                }
+            }
+            self.current_class = ClassType::Subclass;
+            superclass.accept(self);
+            self.begin_scope();
+            self.define_implicit("super");
+        }
+
+        self.begin_scope();
+        self.define_implicit("this");
+        for method in &stmt.methods {
+            let function_type = if method.name.lexeme == "init" {
+                if method.getter {
+                    self.error(&method.name, messages::GETTER_INITIALIZER);
+                }
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+        // Class methods are resolved as methods, with "this" bound to the
+        // class rather than to an instance. A class method named init is
+        // not an initializer.
+        for method in &stmt.class_methods {
+            self.resolve_function(method, FunctionType::Method);
+        }
+        self.end_scope();
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        if self.loop_depth == 0 {
+            self.error(&stmt.keyword, messages::CONTINUE_OUTSIDE_LOOP);
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) {
+        // The function is defined before its body is resolved, so that it
+        // can refer to itself recursively.
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_function(stmt, FunctionType::Function);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(ref else_branch) = stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        if self.current_function == FunctionType::None {
+            self.error(&stmt.keyword, messages::RETURN_FROM_TOP_LEVEL);
+        }
+        if let Some(ref value) = stmt.value {
+            if self.current_function == FunctionType::Initializer {
+                self.error(&stmt.keyword, messages::RETURN_VALUE_FROM_INITIALIZER);
+            }
+            value.accept(self);
+        }
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) {
+        self.begin_scope();
+        self.resolve_statements(&stmt.body);
+        self.end_scope();
+        // As for the parameters of a function, the caught value is bound in
+        // the scope of the handler.
+        self.begin_scope();
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_statements(&stmt.handler);
+        self.end_scope();
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        self.declare(&stmt.name);
+        if let Some(ref initializer) = stmt.initializer {
+            initializer.accept(self);
+        }
+        self.define(&stmt.name);
+
+        // Removing the declaration is only offered when it has no effect
+        // other than declaring the variable.
+        let removable = match stmt.initializer {
+            None => true,
+            Some(ref initializer) => eval_const(initializer).is_some(),
+        };
+        let fix = if removable {
+            Some(FixIt {
+                span: stmt.span,
+                replacement: String::new(),
+            })
+        } else {
+            None
+        };
+        if self.scopes.is_empty() && stmt.constant {
+            self.global_constants.insert(stmt.name.lexeme.to_string());
+        }
+        if let Some(local) = self.innermost_local(&stmt.name) {
+            local.var = Some((stmt.name.clone(), fix));
+            local.constant = stmt.constant;
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        stmt.condition.accept(self);
+        self.loop_depth += 1;
+        stmt.body.accept(self);
+        self.loop_depth -= 1;
+        if let Some(ref increment) = stmt.increment {
+            increment.accept(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::token::Literal;
+
+    // panic_on_errors panics with the first of errors, if any.
+    fn panic_on_errors(errors: &[LoxError]) {
+        if let Some(err) = errors.first() {
+            panic!(
+                "error: '{line}:{msg}'",
+                line = err.line(),
+                msg = err.message()
+            );
+        }
+    }
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let (tokens, errors) = Scanner::new().scan_tokens(source);
+        panic_on_errors(&errors);
+        Parser::new().parse(tokens).unwrap()
+    }
+
+    // resolve parses and resolves source, panicking on any error.
+    fn resolve(source: &str) -> Vec<Stmt> {
+        let program = parse(source);
+        if let Err(errors) = Resolver::new().resolve(&program) {
+            panic_on_errors(&errors);
+        }
+        program
+    }
+
+    // resolve_errors parses and resolves source, returning the error messages.
+    fn resolve_errors(source: &str) -> Vec<String> {
+        let program = parse(source);
+        match Resolver::new().resolve(&program) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.message().to_owned()).collect(),
+        }
+    }
+
+    // print_depth returns the resolved depth of the expression of the
+    // print statement stmt, which must be a variable.
+    fn print_depth(stmt: &Stmt) -> Option<usize> {
+        match *stmt {
+            Stmt::Print(PrintStmt {
+                expression: Expr::Variable(ref variable),
+            }) => variable.depth.get(),
+            _ => panic!("expected print of a variable"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_depths() {
+        let program = resolve("var a; print a; { var b; { print b; print a; } }");
+        assert_eq!(print_depth(&program[1]), None);
+        let outer = match program[2] {
+            Stmt::Block(ref block) => block,
+            _ => panic!("expected block"),
+        };
+        let inner = match outer.statements[1] {
+            Stmt::Block(ref block) => block,
+            _ => panic!("expected block"),
+        };
+        assert_eq!(print_depth(&inner.statements[0]), Some(1));
+        assert_eq!(print_depth(&inner.statements[1]), None);
+    }
+
+    #[test]
+    fn test_resolve_function_params() {
+        let program = resolve("fun f(a) { print a; }");
+        let function = match program[0] {
+            Stmt::Function(ref function) => Rc::clone(function),
+            _ => panic!("expected function"),
+        };
+        assert_eq!(print_depth(&function.body[0]), Some(0));
+    }
+
+    // print_constant returns the constant index of the expression of the
+    // print statement stmt, which must be a literal.
+    fn print_constant(stmt: &Stmt) -> Option<usize> {
+        match *stmt {
+            Stmt::Print(PrintStmt {
+                expression: Expr::Literal(ref literal),
+            }) => literal.constant.get(),
+            _ => panic!("expected print of a literal"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_constants() {
+        let program = parse("print 1; print \"a\"; print 1; fun f() { print \"a\"; print 1; }");
+        let constants = Resolver::new().resolve_with_constants(&program).unwrap();
+        assert_eq!(
+            constants.values(),
+            &[Literal::Number(1.0), Literal::String("a".into())]
+        );
+        assert_eq!(print_constant(&program[0]), Some(0));
+        assert_eq!(print_constant(&program[1]), Some(1));
+        assert_eq!(print_constant(&program[2]), Some(0));
+
+        // Each function has a table of its own.
+        let function = match program[3] {
+            Stmt::Function(ref function) => Rc::clone(function),
+            _ => panic!("expected function"),
+        };
+        assert_eq!(
+            function.constants.borrow().values(),
+            &[Literal::String("a".into()), Literal::Number(1.0)]
+        );
+        assert_eq!(print_constant(&function.body[0]), Some(0));
+        assert_eq!(print_constant(&function.body[1]), Some(1));
+    }
+
+    #[test]
+    fn test_resolve_valid_programs() {
+        resolve("var a = 1; var a = a;");
+        resolve("fun f() { return f(); }");
+        resolve("class A { init() { return; } f() { return this; } }");
+        resolve("class A {} class B < A { f() { return super.f; } }");
+        resolve("class A { class init() { return 1; } n { return this; } }");
+        resolve("const a = 1; var a = 2; a = 3;");
+        resolve("const a = 1; { var a = 2; a = 3; }");
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let cases = vec![
+            (
+                "{ var a = a; }",
+                "Cannot read local variable in its own initializer.",
+            ),
+            (
+                "{ var a; var a; }",
+                "Variable with this name already declared in this scope.",
+            ),
+            (
+                "fun f(a, a) {}",
+                "Variable with this name already declared in this scope.",
+            ),
+            ("return;", "Cannot return from top-level code."),
+            (
+                "class A { init() { return 1; } }",
+                "Cannot return a value from an initializer.",
+            ),
+            ("class A { init { } }", "An initializer cannot be a getter."),
+            ("const a = 1; a = 2;", "Cannot assign to a constant."),
+            ("{ const a = 1; a++; }", "Cannot assign to a constant."),
+            (
+                "const a = 1; fun f() { a += 1; }",
+                "Cannot assign to a constant.",
+            ),
+            ("print this;", "Cannot use 'this' outside of a class."),
+            ("fun f() { this; }", "Cannot use 'this' outside of a class."),
+            ("super.f();", "Cannot use 'super' outside of a class."),
+            (
+                "class A { f() { super.f(); } }",
+                "Cannot use 'super' in a class with no superclass.",
+            ),
+            ("class A < A {}", "A class cannot inherit from itself."),
+            (
+                "class A { var a; var a = 1; }",
+                "Field with this name already declared in this class.",
+            ),
+            (
+                "class A { var a = this; }",
+                "Cannot use 'this' outside of a class.",
+            ),
+            ("break;", "Cannot use 'break' outside of a loop."),
+            (
+                "if (true) continue;",
+                "Cannot use 'continue' outside of a loop.",
+            ),
+            (
+                "while (true) { fun f() { break; } }",
+                "Cannot use 'break' outside of a loop.",
+            ),
+        ];
+        for (source, msg) in cases {
+            assert_eq!(resolve_errors(source), vec![msg.to_owned()], "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_resolve_unused_local_warnings() {
+        use std::cell::RefCell;
+
+        use crate::span::Span;
+
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&warnings);
+        let record_warning = move |span: Span, msg: &Message, fix: Option<&FixIt>| {
+            let fix = fix.map(|fix| (fix.span.start.offset, fix.span.end.offset));
+            recorded
+                .borrow_mut()
+                .push((span.start.offset, msg.to_string(), fix))
+        };
+        let source = "var g; { var b = 1 + 2; var a; var c = f(); var _d; var e; e = 1; }";
+        let program = parse(source);
+        Resolver::new()
+            .with_warning_cb(&record_warning)
+            .resolve(&program)
+            .unwrap();
+        assert_eq!(
+            *warnings.borrow(),
+            vec![
+                (
+                    13,
+                    String::from("Local variable 'b' is never used."),
+                    Some((9, 23))
+                ),
+                (
+                    28,
+                    String::from("Local variable 'a' is never used."),
+                    Some((24, 30))
+                ),
+                (35, String::from("Local variable 'c' is never used."), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_reports_all_errors() {
+        let errors = resolve_errors("return; print this;");
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,55 @@
+use std::io;
+use std::panic;
+use std::thread;
+
+// run_with_stack_size runs f on a new worker thread with a stack of
+// stack_size bytes, blocking until it completes and returning its result.
+// This allows running legitimately deep programs that would overflow the
+// default stack of the calling thread. A panic in f is propagated to the
+// caller.
+pub fn run_with_stack_size<F, T>(stack_size: usize, f: F) -> io::Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let handle = thread::Builder::new()
+        .name(String::from("loxrust-worker"))
+        .stack_size(stack_size)
+        .spawn(f)?;
+    match handle.join() {
+        Ok(result) => Ok(result),
+        Err(err) => panic::resume_unwind(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_stack_size_returns_result() {
+        let result = run_with_stack_size(64 * 1024, || 1 + 2).unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_run_with_stack_size_deep_recursion() {
+        fn depth(n: u64) -> u64 {
+            // Keep a sizeable frame so the recursion needs a large stack.
+            let buf = [n; 64];
+            if n == 0 {
+                0
+            } else {
+                1 + depth(buf[63] - 1)
+            }
+        }
+        let result = run_with_stack_size(256 * 1024 * 1024, || depth(100_000)).unwrap();
+        assert_eq!(result, 100_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "worker failed")]
+    fn test_run_with_stack_size_propagates_panic() {
+        let _ = run_with_stack_size(64 * 1024, || panic!("worker failed"));
+    }
+}
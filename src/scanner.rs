@@ -1,42 +1,330 @@
-use std::str;
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
 
-use super::token::{Literal, Token, TokenType};
-use super::ErrorCallback;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::error::LoxError;
+use super::messages::{self, Message};
+use super::observer::FrontendObserver;
+use super::span::{Position, Span};
+use super::symbol::Interner;
+use super::token::{Lexeme, Literal, Token, TokenType};
 
 // A Scanner turns a string of characters into Tokens.
 pub struct Scanner<'a> {
-    // error_cb is an optional ErrorCallback that will be notified for each
-    // (if any) errors encountered while scanning.
-    error_cb: Option<&'a ErrorCallback>,
+    // observer is an optional FrontendObserver that will be notified of each
+    // token and error while scanning.
+    observer: Option<&'a dyn FrontendObserver>,
+
+    // error_budget is the optional maximum number of errors to report
+    // before giving up on the rest of the source.
+    error_budget: Option<usize>,
+
+    // unicode_identifiers is true if identifiers may contain non-ASCII
+    // characters.
+    unicode_identifiers: bool,
 }
 
+#[allow(clippy::new_without_default)]
 impl<'a> Scanner<'a> {
-    // new creates a new scanner, with the optional error_cb. error_cb is called
-    // for each error encountered while scanning.
-    pub fn new(error_cb: Option<&'a ErrorCallback>) -> Self {
-        Scanner { error_cb }
+    pub fn new() -> Self {
+        Scanner {
+            observer: None,
+            error_budget: None,
+            unicode_identifiers: false,
+        }
+    }
+
+    // with_unicode_identifiers controls whether identifiers may contain
+    // non-ASCII characters. When enabled, identifiers are any XID_Start
+    // character (or '_') followed by XID_Continue characters, and are
+    // normalized to NFC so that visually identical identifiers are equal.
+    // Keywords are still ASCII only.
+    pub fn with_unicode_identifiers(mut self, enabled: bool) -> Self {
+        self.unicode_identifiers = enabled;
+        self
+    }
+
+    // unicode_identifiers returns whether identifiers may contain non-ASCII
+    // characters, see with_unicode_identifiers.
+    pub fn unicode_identifiers(&self) -> bool {
+        self.unicode_identifiers
+    }
+
+    // is_identifier returns whether name scans as a single identifier.
+    pub fn is_identifier(&self, name: &str) -> bool {
+        let (tokens, errors) = self.scan_tokens(name);
+        match tokens.as_slice() {
+            [token, eof] => {
+                errors.is_empty()
+                    && token.token_type == TokenType::Identifier
+                    && token.lexeme.as_str() == name
+                    && eof.token_type == TokenType::Eof
+            }
+            _ => false,
+        }
+    }
+
+    // with_error_budget limits the number of errors reported for a scan to
+    // max_errors. Once the budget is spent, a final "Too many errors" error
+    // is reported and the rest of the source is not scanned. This avoids
+    // flooding the user with errors when scanning e.g. binary input.
+    pub fn with_error_budget(mut self, max_errors: usize) -> Self {
+        self.error_budget = Some(max_errors);
+        self
+    }
+
+    // with_observer installs observer on the scanner, to be notified of each
+    // token and error while scanning.
+    pub fn with_observer(mut self, observer: &'a dyn FrontendObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    // scan_tokens scans the source for tokens returning a tuple (tokens, errors)
+    // where tokens is the successfully scanned tokens, and errors is the
+    // errors encountered while scanning. errors is empty only if all
+    // characters in source were successfully consumed.
+    // Identifiers and string literals are interned into a new interner for
+    // each scan.
+    pub fn scan_tokens(&self, source: &str) -> (Vec<Token>, Vec<LoxError>) {
+        self.scan_tokens_with_interner(source, &mut Interner::new())
+    }
+
+    // scan_tokens_with_interner is like scan_tokens, but interns identifiers
+    // and string literals into the interner. This allows resolving the
+    // symbols of the scanned tokens, and sharing symbols between scans.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "scan", skip_all, fields(bytes = source.len()))
+    )]
+    pub fn scan_tokens_with_interner(
+        &self,
+        source: &str,
+        interner: &mut Interner,
+    ) -> (Vec<Token>, Vec<LoxError>) {
+        let mut ctx = ScannerContext::new(source, std::mem::take(interner), self);
+        let scanned = ctx.scan_tokens();
+        *interner = ctx.interner;
+        scanned
+    }
+
+    // tokens returns an iterator over the tokens of source, scanning them as
+    // they are requested. Each error is yielded as an Err before the token
+    // (if any) recovered from it, and the last item is the Eof token. Unlike
+    // scan_tokens, this does not keep all the tokens of source in memory at
+    // once.
+    pub fn tokens<'s>(&self, source: &'s str) -> Tokens<'s>
+    where
+        'a: 's,
+    {
+        Tokens(ScannerContext::new(source, Interner::new(), self))
+    }
+
+    // scan_bytes is like scan_tokens, but for source that may not be valid
+    // UTF-8, such as the contents of a file. Each invalid sequence of bytes
+    // is reported as an error, and is otherwise scanned as one space per
+    // byte, so that the spans of the tokens still match source.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "scan", skip_all, fields(bytes = source.len()))
+    )]
+    pub fn scan_bytes(&self, source: &[u8]) -> (Vec<Token>, Vec<LoxError>) {
+        let mut text = String::with_capacity(source.len());
+        let mut invalid_utf8 = Vec::new();
+        for chunk in source.utf8_chunks() {
+            text.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                invalid_utf8.push((text.len(), chunk.invalid().len()));
+                text.extend(chunk.invalid().iter().map(|_| ' '));
+            }
+        }
+        let mut ctx = ScannerContext::new(&text, Interner::new(), self);
+        invalid_utf8.reverse();
+        ctx.invalid_utf8 = invalid_utf8;
+        ctx.scan_tokens()
     }
+}
+
+// Tokens is an iterator over the tokens of a source, as returned by
+// Scanner::tokens.
+pub struct Tokens<'a>(ScannerContext<'a>);
+
+impl Tokens<'_> {
+    // interner returns the Interner the identifiers and string literals
+    // scanned so far are interned into, to resolve the symbols of the
+    // tokens.
+    pub fn interner(&self) -> &Interner {
+        &self.0.interner
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, LoxError>;
 
-    // scan_tokens scans the source for tokens returning a tuple (had_error, tokens)
-    // where had_error is false only if all characters in source were successfully
-    // consumed, and tokens is the successfully scanned tokens.
-    pub fn scan_tokens<'s>(&self, source: &'s str) -> (bool, impl IntoIterator<Item = Token>) {
-        ScannerContext::new(source, self.error_cb).scan_tokens()
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
     }
 }
 
+// IncrementalScanner scans source fed to it piece by piece, such as the
+// lines of a statement entered in the REPL, without scanning the source fed
+// earlier again. Lines and positions continue from one piece to the next.
+//
+// A lexeme that reaches the end of the source fed so far could still be
+// continued by the next piece, e.g. a string or block comment spanning
+// lines, so it is held back and scanned once more source is fed, or at
+// finish.
+pub struct IncrementalScanner<'a> {
+    scanner: Scanner<'a>,
+
+    // interner is the Interner identifiers are interned into.
+    interner: Interner,
+
+    // source is the source fed since the last finish.
+    source: String,
+
+    // offset is the offset in source of the first lexeme held back, and
+    // position its source position.
+    offset: usize,
+    position: Position,
+
+    // tokens and errors are those of the lexemes scanned so far.
+    tokens: Vec<Token>,
+    errors: Vec<LoxError>,
+
+    // interpolations is the interpolations open at offset.
+    interpolations: Vec<Interpolation>,
+}
+
+impl<'a> IncrementalScanner<'a> {
+    // new creates an IncrementalScanner scanning as configured by scanner.
+    pub fn new(scanner: Scanner<'a>) -> Self {
+        IncrementalScanner {
+            scanner,
+            interner: Interner::new(),
+            source: String::new(),
+            offset: 0,
+            position: Position::new(1, 1, 0),
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            interpolations: Vec::new(),
+        }
+    }
+
+    // feed appends source to the source being scanned, and scans it up to
+    // the lexeme reaching its end.
+    pub fn feed(&mut self, source: &str) {
+        self.source.push_str(source);
+        let interner = std::mem::take(&mut self.interner);
+        let mut ctx = ScannerContext::new(&self.source, interner, &self.scanner);
+        ctx.resume(self.offset, self.position, self.errors.len());
+        ctx.interpolations = std::mem::take(&mut self.interpolations);
+        ctx.scan_partial();
+        self.offset = ctx.start;
+        self.position = ctx.start_position;
+        self.interpolations = std::mem::take(&mut ctx.interpolations);
+        self.tokens.extend(ctx.tokens.drain(..));
+        self.errors.extend(ctx.errors.drain(..));
+        self.interner = ctx.interner;
+    }
+
+    // is_empty returns true if no source has been fed since the last
+    // finish.
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    // is_pending returns true if the source fed so far ends inside a lexeme
+    // that is held back, other than whitespace, such as a string continuing
+    // on the next line, or inside an interpolation.
+    pub fn is_pending(&self) -> bool {
+        !self.source[self.offset..].trim_start().is_empty() || !self.interpolations.is_empty()
+    }
+
+    // tokens returns the tokens scanned so far, followed by an Eof token at
+    // the start of the lexeme held back, as if no more source was fed.
+    pub fn tokens(&self) -> Vec<Token> {
+        let mut tokens = self.tokens.clone();
+        tokens.push(ScannerContext::eof_token(self.position));
+        tokens
+    }
+
+    // errors returns the errors of the lexemes scanned so far.
+    pub fn errors(&self) -> &[LoxError] {
+        &self.errors
+    }
+
+    // interner returns the Interner identifiers and string literals are
+    // interned into. It is kept across finish, so symbols are comparable
+    // between the sources scanned.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    // finish scans the rest of the source fed, returning a tuple
+    // (tokens, errors) for all of it as Scanner::scan_tokens would. The
+    // scanner is then ready to be fed a new source, starting at line 1.
+    pub fn finish(&mut self) -> (Vec<Token>, Vec<LoxError>) {
+        let interner = std::mem::take(&mut self.interner);
+        let mut ctx = ScannerContext::new(&self.source, interner, &self.scanner);
+        ctx.resume(self.offset, self.position, self.errors.len());
+        ctx.interpolations = std::mem::take(&mut self.interpolations);
+        let (rest, errors) = ctx.scan_tokens();
+        self.interner = ctx.interner;
+        let mut tokens = std::mem::take(&mut self.tokens);
+        tokens.extend(rest);
+        let errors = std::mem::take(&mut self.errors)
+            .into_iter()
+            .chain(errors)
+            .collect();
+        self.source.clear();
+        self.offset = 0;
+        self.position = Position::new(1, 1, 0);
+        (tokens, errors)
+    }
+}
+
+// Interpolation is an interpolation of a string being scanned, i.e. an
+// expression between "${" and "}".
+#[derive(Debug, Clone)]
+struct Interpolation {
+    // braces is the number of braces opened and not yet closed inside the
+    // interpolation, so that the "}" closing the interpolation can be told
+    // apart from the "}" closing e.g. a block.
+    braces: usize,
+    // span is the span of the "${" opening the interpolation.
+    span: Span,
+}
+
 // ScannerContext encapsulates the state of a single scan for some source.
 struct ScannerContext<'a> {
-    source: &'a [u8],
+    source: &'a str,
 
-    tokens: Vec<Token>,
+    // shared is source, shared by the lexemes of the tokens scanned.
+    shared: Rc<str>,
+
+    // tokens and errors are those scanned but not yet yielded.
+    tokens: VecDeque<Token>,
+    errors: VecDeque<LoxError>,
 
-    // had_error is set to true if any error is encountered while scanning.
-    had_error: bool,
+    // interner is the Interner identifiers are interned into.
+    interner: Interner,
 
-    // error_cb is an optional ErrorCallback that will be notified for each
-    // (if any) errors encountered while scanning.
-    error_cb: Option<&'a ErrorCallback>,
+    // error_count is the number of errors reported so far.
+    error_count: usize,
+
+    // error_budget is the optional maximum number of errors to report.
+    error_budget: Option<usize>,
+
+    // observer is an optional FrontendObserver that will be notified of each
+    // token and error while scanning.
+    observer: Option<&'a dyn FrontendObserver>,
+
+    // unicode_identifiers is true if identifiers may contain non-ASCII
+    // characters.
+    unicode_identifiers: bool,
 
     // start is the offset in source of the first character of the
     // lexeme we are currently considering.
@@ -45,52 +333,158 @@ struct ScannerContext<'a> {
     // lexeme we are currently considering.
     current: usize,
 
-    // line is the line number of the current lexeme.
-    line: u64,
+    // start_position is the source position of start.
+    start_position: Position,
+    // position is the source position of current.
+    position: Position,
+
+    // interpolations is the interpolations open at current, innermost
+    // last.
+    interpolations: Vec<Interpolation>,
+
+    // invalid_utf8 is the offsets and lengths of the sequences of invalid
+    // UTF-8 replaced by spaces in source, by scan_bytes. The sequences are in
+    // reverse order, so that the next one to be reached is last.
+    invalid_utf8: Vec<(usize, usize)>,
+
+    // stopped is true if scanning stopped early, and done is true once the
+    // Eof token has been produced.
+    stopped: bool,
+    done: bool,
 }
 
 impl<'a> ScannerContext<'a> {
-    pub fn new(source: &'a str, error_cb: Option<&'a ErrorCallback>) -> Self {
+    pub fn new(source: &'a str, interner: Interner, scanner: &Scanner<'a>) -> Self {
         ScannerContext {
-            source: source.as_bytes(),
-            tokens: Vec::new(),
-            had_error: false,
-            error_cb,
+            source,
+            shared: Rc::from(source),
+            tokens: VecDeque::new(),
+            errors: VecDeque::new(),
+            interner,
+            error_count: 0,
+            error_budget: scanner.error_budget,
+            observer: scanner.observer,
+            unicode_identifiers: scanner.unicode_identifiers,
             start: 0,
             current: 0,
-            line: 1,
+            start_position: Position::new(1, 1, 0),
+            position: Position::new(1, 1, 0),
+            interpolations: Vec::new(),
+            invalid_utf8: Vec::new(),
+            stopped: false,
+            done: false,
+        }
+    }
+
+    // eof_token returns the Eof token ending a scan at position.
+    fn eof_token(position: Position) -> Token {
+        Token {
+            token_type: TokenType::Eof,
+            lexeme: Lexeme::from(""),
+            symbol: None,
+            span: Span::new(position, position),
+            literal: None,
+            error: false,
         }
     }
 
-    // scan_tokens scans the source for tokens returning a tuple (had_error, tokens)
-    // where had_error is false only if all characters in source were successfully
-    // consumed, and tokens is the successfully scanned tokens.
-    pub fn scan_tokens(&mut self) -> (bool, impl IntoIterator<Item = Token>) {
-        // Scanning the same source multiple times is not supported without
-        // creating a new ScannerContext.
-        assert!(self.current == 0);
+    // resume sets the scan to start at offset in source, at position, with
+    // error_count errors already reported for source.
+    fn resume(&mut self, offset: usize, position: Position, error_count: usize) {
+        self.error_count = error_count;
+        self.start = offset;
+        self.current = offset;
+        self.start_position = position;
+        self.position = position;
+    }
+
+    // scan_tokens scans the source for tokens returning a tuple (tokens, errors)
+    // where tokens is the successfully scanned tokens, and errors is the
+    // errors encountered while scanning.
+    pub fn scan_tokens(&mut self) -> (Vec<Token>, Vec<LoxError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for scanned in self {
+            match scanned {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(tokens = tokens.len(), errors = errors.len(), "scanned");
+        (tokens, errors)
+    }
 
-        while !self.is_at_end() {
-            // We are at the beginning of the next lexeme.
+    // scan_lexeme scans the next lexeme of source, returning false if there
+    // are no more lexemes to scan.
+    fn scan_lexeme(&mut self) -> bool {
+        if self.stopped || self.is_at_end() {
+            return false;
+        }
+        if self.is_error_budget_spent() {
             self.start = self.current;
-            self.scan_token()
+            let span = Span::new(self.position, self.position);
+            self.report_error(span, messages::TOO_MANY_ERRORS);
+            self.stopped = true;
+            return true;
+        }
+        // We are at the beginning of the next lexeme.
+        self.start = self.current;
+        self.start_position = self.position;
+        self.scan_token();
+        true
+    }
+
+    // scan_partial scans the lexemes of source for a source that may yet be
+    // continued. The lexeme reaching the end of source may then continue in
+    // the source not yet seen, so it is not scanned, but left at start to
+    // be scanned again once the source is continued.
+    fn scan_partial(&mut self) {
+        loop {
+            let (tokens, errors) = (self.tokens.len(), self.errors.len());
+            let interpolations = self.interpolations.clone();
+            if !self.scan_lexeme() {
+                break;
+            }
+            if self.is_at_end() && !self.stopped {
+                self.tokens.truncate(tokens);
+                self.error_count -= self.errors.len() - errors;
+                self.errors.truncate(errors);
+                self.interpolations = interpolations;
+                return;
+            }
+            self.notify_observer(tokens, errors);
+        }
+        self.start = self.current;
+        self.start_position = self.position;
+    }
+
+    // notify_observer notifies the observer, if any, of the errors and
+    // tokens from the given indices on, i.e. those of the last lexeme
+    // scanned. Errors are reported before the token recovered from them.
+    fn notify_observer(&self, tokens: usize, errors: usize) {
+        if let Some(observer) = self.observer {
+            self.errors
+                .range(errors..)
+                .for_each(|err| Self::notify_error(observer, err));
+            self.tokens
+                .range(tokens..)
+                .for_each(|token| observer.on_token(token));
+        }
+    }
+
+    fn notify_error(observer: &dyn FrontendObserver, err: &LoxError) {
+        if let LoxError::Scan { span, message, .. } = err {
+            observer.on_diagnostic(*span, message);
         }
-        self.tokens.push(Token {
-            token_type: TokenType::Eof,
-            lexeme: String::from(""),
-            line: self.line,
-            literal: None,
-        });
-        let tokens = std::mem::replace(&mut self.tokens, Vec::new());
-        (self.had_error, tokens)
     }
 
     fn is_digit(ch: char) -> bool {
-        ch.is_digit(10)
+        ch.is_ascii_digit()
     }
 
     fn is_alpha(ch: char) -> bool {
-        (ch >= 'a' && ch <= 'z') || (ch >= 'A' && ch <= 'Z') || ch == '_'
+        ch.is_ascii_alphabetic() || ch == '_'
     }
 
     fn is_alpha_numeric(ch: char) -> bool {
@@ -101,60 +495,134 @@ impl<'a> ScannerContext<'a> {
         ch.is_ascii_whitespace()
     }
 
+    // is_token_start returns true if ch can begin a token, or is otherwise
+    // expected in source (i.e. whitespace).
+    fn is_token_start(ch: char) -> bool {
+        match ch {
+            '(' | ')' | '{' | '}' | '[' | ']' | ',' | '.' | '-' | '+' | ':' | ';' | '*' | '%'
+            | '!' | '=' | '<' | '>' | '/' | '"' => true,
+            _ => Self::is_alpha_numeric(ch) || Self::is_whitespace(ch),
+        }
+    }
+
+    // is_unicode_identifier_start returns true if unicode identifiers are
+    // enabled and the next (non-ASCII) character can begin an identifier.
+    fn is_unicode_identifier_start(&self) -> bool {
+        self.unicode_identifiers && self.peek_char().is_some_and(|ch| ch.is_xid_start())
+    }
+
+    // is_unicode_identifier_continue returns true if unicode identifiers are
+    // enabled and the next (non-ASCII) character can continue an identifier.
+    fn is_unicode_identifier_continue(&self) -> bool {
+        self.unicode_identifiers && self.peek_char().is_some_and(|ch| ch.is_xid_continue())
+    }
+
     // keyword returns the TokenType for the keyword_str, or None
     // if no keyword matched the str.
     fn keyword(keyword_str: &str) -> Option<TokenType> {
         match keyword_str {
             "and" => Some(TokenType::And),
+            "break" => Some(TokenType::Break),
+            "catch" => Some(TokenType::Catch),
             "class" => Some(TokenType::Class),
+            "const" => Some(TokenType::Const),
+            "continue" => Some(TokenType::Continue),
             "else" => Some(TokenType::Else),
             "false" => Some(TokenType::False),
             "fun" => Some(TokenType::Fun),
             "for" => Some(TokenType::For),
             "if" => Some(TokenType::If),
+            "import" => Some(TokenType::Import),
             "nil" => Some(TokenType::Nil),
             "or" => Some(TokenType::Or),
             "print" => Some(TokenType::Print),
             "return" => Some(TokenType::Return),
             "super" => Some(TokenType::Super),
             "this" => Some(TokenType::This),
+            "throw" => Some(TokenType::Throw),
             "true" => Some(TokenType::True),
+            "try" => Some(TokenType::Try),
             "var" => Some(TokenType::Var),
             "while" => Some(TokenType::While),
             _ => None,
         }
     }
 
-    // add_token creates a token from the current lexeme.
+    // add_token creates a token from the current lexeme. The lexeme of
+    // identifiers is interned.
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let lexeme = &self.source[self.start..self.current];
-        let lexeme = str::from_utf8(lexeme).unwrap().to_owned();
-        let line = self.line;
-        self.tokens.push(Token {
+        let lexeme = self.lexeme();
+        self.add_token_with_lexeme(token_type, lexeme, literal)
+    }
+
+    // add_token_with_lexeme is as add_token, but uses lexeme as the lexeme
+    // of the token instead of the source of the current lexeme.
+    fn add_token_with_lexeme(
+        &mut self,
+        token_type: TokenType,
+        lexeme: Lexeme,
+        literal: Option<Literal>,
+    ) {
+        let symbol = if token_type == TokenType::Identifier {
+            Some(self.interner.intern(&lexeme))
+        } else {
+            None
+        };
+        let span = self.lexeme_span();
+        self.push_token(Token {
+            token_type,
+            lexeme,
+            symbol,
+            span,
+            literal,
+            error: false,
+        });
+    }
+
+    // add_error_token is as add_token, but marks the token as recovered
+    // from a scan error, which must be reported separately.
+    fn add_error_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
+        let lexeme = self.lexeme();
+        self.push_token(Token {
             token_type,
             lexeme,
-            line,
+            symbol: None,
+            span: self.lexeme_span(),
             literal,
+            error: true,
         });
     }
 
+    // push_token appends token to the scanned tokens.
+    fn push_token(&mut self, token: Token) {
+        self.tokens.push_back(token);
+    }
+
+    // lexeme returns the current lexeme, sharing source.
+    fn lexeme(&self) -> Lexeme {
+        Lexeme::new(Rc::clone(&self.shared), self.start..self.current)
+    }
+
+    // lexeme_span returns the span of the current lexeme.
+    fn lexeme_span(&self) -> Span {
+        Span::new(self.start_position, self.position)
+    }
+
     // peek_next returns the character following the next character in the source
     // without consuming it.
     fn peek_next(&self) -> char {
-        if (self.current + 1) >= self.source.len() {
-            '\0'
-        } else {
-            self.source[self.current + 1] as char
-        }
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    // peek_char returns the next character in the source without consuming
+    // it, or None at the end of the source.
+    fn peek_char(&self) -> Option<char> {
+        self.source[self.current..].chars().next()
     }
 
     // peek returns the next character in the source without consuming it.
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source[self.current] as char
-        }
+        self.peek_char().unwrap_or('\0')
     }
 
     // advance_if consumes the next character in the source if the character
@@ -162,22 +630,29 @@ impl<'a> ScannerContext<'a> {
     // Note this method is called "match" in lox/Scanner.java, but match is
     // reserved in rust.
     fn advance_if(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
-        let ch = self.source[self.current] as char;
-        if ch == expected {
-            self.current += 1;
-            true
-        } else {
-            false
-        }
+        self.advance();
+        true
     }
 
-    // advance consumes the next character in the source and returns it.
+    // advance consumes the next (possibly multi-byte) character in the
+    // source and returns it, moving position past the character. Columns
+    // count characters, while offsets count bytes.
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source[self.current - 1] as char
+        if self.invalid_utf8.last().map(|&(offset, _)| offset) == Some(self.current) {
+            self.report_invalid_utf8();
+        }
+        let ch = self.peek_char().expect("advance at end of source");
+        self.current += ch.len_utf8();
+        if ch == '\n' {
+            self.position = Position::new(self.position.line + 1, 1, self.current);
+        } else {
+            self.position.column += 1;
+            self.position.offset = self.current;
+        }
+        ch
     }
 
     // consume_line consumes characters until it encounters a newline
@@ -188,18 +663,61 @@ impl<'a> ScannerContext<'a> {
         }
     }
 
-    // string consumes a string, producing a String token.
+    // block_comment consumes the rest of a block comment, the opening "/*"
+    // having been consumed. Block comments nest, so the comment ends at the
+    // "*/" matching its opening "/*".
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                let span = Span::new(self.position, self.position);
+                self.report_error(span, messages::UNTERMINATED_BLOCK_COMMENT);
+                return;
+            }
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                depth -= 1;
+            }
+            self.advance();
+        }
+    }
+
+    // string consumes a string, producing a String token. The literal of
+    // the token is the string with its escape sequences decoded, while the
+    // lexeme is the string as written in the source.
+    //
+    // The opening '"', or the "}" closing an interpolation, has been
+    // consumed. If the string continues with an interpolation, its part up
+    // to the "${" is produced as an Interpolation token instead.
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.peek() == '$' && self.peek_next() == '{' {
+                let dollar = self.position;
+                self.advance();
+                self.advance();
+                self.interpolation_start(dollar);
+                return;
+            }
+            // Skip the character following a backslash, so that an escaped
+            // quote does not end the string.
+            if self.advance() == '\\' && !self.is_at_end() {
+                self.advance();
             }
-            self.advance();
         }
 
-        // Unterminated string.
+        // Unterminated string. The rest of the source is still produced as
+        // a String token, marked as an error, so that tools working on the
+        // tokens do not lose it.
         if self.is_at_end() {
-            self.report_error("Unterminated string.");
+            let span = Span::new(self.position, self.position);
+            self.report_error(span, messages::UNTERMINATED_STRING);
+            let raw = &self.source[(self.start + 1)..self.current];
+            let value = Self::unescape(raw).unwrap_or_else(|_| raw.to_owned());
+            let literal = self.string_literal(&value);
+            self.add_error_token(TokenType::String, Some(literal));
             return;
         }
 
@@ -207,11 +725,104 @@ impl<'a> ScannerContext<'a> {
         self.advance();
 
         // Trim surrounding quotes.
-        let value = &self.source[(self.start + 1)..(self.current - 1)];
-        // Convert to owned String
-        let value = str::from_utf8(value).unwrap();
-        let value = Literal::String(value.to_owned());
-        self.add_token(TokenType::String, Some(value));
+        let raw = &self.source[(self.start + 1)..(self.current - 1)];
+        match Self::unescape(raw) {
+            Ok(value) => {
+                let literal = self.string_literal(&value);
+                self.add_token(TokenType::String, Some(literal))
+            }
+            Err(msg) => {
+                let literal = Some(Literal::String(raw.into()));
+                self.report_error(self.lexeme_span(), msg);
+                self.add_error_token(TokenType::String, literal);
+            }
+        }
+    }
+
+    // interpolation_start produces the Interpolation token of the part of a
+    // string ending with the "${" at dollar, and opens the interpolation.
+    fn interpolation_start(&mut self, dollar: Position) {
+        self.interpolations.push(Interpolation {
+            braces: 0,
+            span: Span::new(dollar, self.position),
+        });
+        let raw = &self.source[(self.start + 1)..(self.current - 2)];
+        match Self::unescape(raw) {
+            Ok(value) => {
+                let literal = self.string_literal(&value);
+                self.add_token(TokenType::Interpolation, Some(literal))
+            }
+            Err(msg) => {
+                let literal = Some(Literal::String(raw.into()));
+                self.report_error(self.lexeme_span(), msg);
+                self.add_error_token(TokenType::Interpolation, literal);
+            }
+        }
+    }
+
+    // report_unterminated_interpolations reports the interpolations still
+    // open at the end of the source.
+    fn report_unterminated_interpolations(&mut self) {
+        for interpolation in std::mem::take(&mut self.interpolations) {
+            self.push_error(LoxError::Scan {
+                span: interpolation.span,
+                lexeme: String::from("${"),
+                message: messages::UNTERMINATED_INTERPOLATION.into(),
+            });
+        }
+    }
+
+    // string_literal returns the literal for a string of value. The value is
+    // interned, so that equal string literals share one allocation.
+    fn string_literal(&mut self, value: &str) -> Literal {
+        let symbol = self.interner.intern(value);
+        Literal::String(self.interner.shared(symbol))
+    }
+
+    // unescape returns raw, the contents of a string literal, with its
+    // escape sequences decoded:
+    //
+    //   \n \t \" \\   newline, tab, quote and backslash.
+    //   \$           a dollar sign, e.g. to write "\${" without starting an
+    //                interpolation.
+    //   \u{XXXX}     the unicode scalar value of 1 to 6 hex digits.
+    //
+    // unescape returns the error message for the first invalid escape
+    // sequence, if any.
+    fn unescape(raw: &str) -> Result<String, Message> {
+        let mut value = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                value.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('$') => value.push('$'),
+                Some('u') => {
+                    let invalid = || Message::from(messages::INVALID_UNICODE_ESCAPE);
+                    let (digits, rest) = chars
+                        .as_str()
+                        .strip_prefix('{')
+                        .and_then(|rest| rest.split_once('}'))
+                        .ok_or_else(invalid)?;
+                    let is_hex = digits.chars().all(|ch| ch.is_ascii_hexdigit());
+                    if !is_hex || !(1..=6).contains(&digits.len()) {
+                        return Err(invalid());
+                    }
+                    let code = u32::from_str_radix(digits, 16).unwrap();
+                    value.push(char::from_u32(code).ok_or_else(invalid)?);
+                    chars = rest.chars();
+                }
+                Some(ch) => return Err(messages::INVALID_ESCAPE.with(&[&ch])),
+                None => return Err(messages::INVALID_TRAILING_ESCAPE.into()),
+            }
+        }
+        Ok(value)
     }
 
     // number consumes a number, producing a Number token.
@@ -229,42 +840,103 @@ impl<'a> ScannerContext<'a> {
             }
         }
 
-        let value = &self.source[(self.start)..(self.current)];
-        let value = str::from_utf8(value).unwrap();
-        let value: f64 = value.parse().unwrap();
-        let value = Literal::Number(value);
-        self.add_token(TokenType::Number, Some(value));
+        let lexeme = &self.source[(self.start)..(self.current)];
+        // Literals too large to be represented are rejected rather than
+        // silently becoming infinity.
+        match lexeme.parse::<f64>() {
+            Ok(value) if value.is_finite() => {
+                self.add_token(TokenType::Number, Some(Literal::Number(value)));
+            }
+            _ => {
+                let msg = messages::INVALID_NUMBER.with(&[&lexeme]);
+                self.report_error(self.lexeme_span(), msg);
+            }
+        }
     }
 
     // identifier consumes an identifier, producing an Identifier token.
     // If the identifier matches a reserved keyword a token for that
     // matched keyword is produced instead.
     fn identifier(&mut self) {
-        while Self::is_alpha_numeric(self.peek()) {
+        while Self::is_alpha_numeric(self.peek()) || self.is_unicode_identifier_continue() {
             self.advance();
         }
 
         // Test for reserved keyword.
         let text = &self.source[(self.start)..(self.current)];
-        let text = str::from_utf8(text).unwrap();
         let token_type = Self::keyword(text).unwrap_or(TokenType::Identifier);
-        self.add_token(token_type, None);
+        if token_type == TokenType::Identifier && !text.is_ascii() {
+            let lexeme: String = text.nfc().collect();
+            self.add_token_with_lexeme(token_type, lexeme.into(), None);
+        } else {
+            self.add_token(token_type, None);
+        }
     }
 
     // scan_token scans a single token.
     fn scan_token(&mut self) {
+        if self.is_unicode_identifier_start() {
+            self.advance();
+            self.identifier();
+            return;
+        }
         let ch = self.advance();
         let tok_type: Option<TokenType> = match ch {
             '(' => Some(TokenType::LeftParen),
             ')' => Some(TokenType::RightParen),
-            '{' => Some(TokenType::LeftBrace),
-            '}' => Some(TokenType::RightBrace),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.braces += 1;
+                }
+                Some(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                // The "}" closing an interpolation continues the string.
+                Some(interpolation) if interpolation.braces == 0 => {
+                    self.interpolations.pop();
+                    self.string();
+                    None
+                }
+                Some(interpolation) => {
+                    interpolation.braces -= 1;
+                    Some(TokenType::RightBrace)
+                }
+                None => Some(TokenType::RightBrace),
+            },
+            '[' => Some(TokenType::LeftBracket),
+            ']' => Some(TokenType::RightBracket),
+            ':' => Some(TokenType::Colon),
             ',' => Some(TokenType::Comma),
             '.' => Some(TokenType::Dot),
-            '-' => Some(TokenType::Minus),
-            '+' => Some(TokenType::Plus),
+            '-' => {
+                if self.advance_if('=') {
+                    Some(TokenType::MinusEqual)
+                } else if self.advance_if('-') {
+                    Some(TokenType::MinusMinus)
+                } else {
+                    Some(TokenType::Minus)
+                }
+            }
+            '+' => {
+                if self.advance_if('=') {
+                    Some(TokenType::PlusEqual)
+                } else if self.advance_if('+') {
+                    Some(TokenType::PlusPlus)
+                } else {
+                    Some(TokenType::Plus)
+                }
+            }
             ';' => Some(TokenType::Semicolon),
-            '*' => Some(TokenType::Star),
+            '%' => Some(TokenType::Percent),
+            '*' => {
+                if self.advance_if('*') {
+                    Some(TokenType::StarStar)
+                } else if self.advance_if('=') {
+                    Some(TokenType::StarEqual)
+                } else {
+                    Some(TokenType::Star)
+                }
+            }
             '!' => {
                 if self.advance_if('=') {
                     Some(TokenType::BangEqual)
@@ -298,14 +970,15 @@ impl<'a> ScannerContext<'a> {
                     // Comments continue until end of line.
                     self.consume_line();
                     None
+                } else if self.advance_if('*') {
+                    self.block_comment();
+                    None
+                } else if self.advance_if('=') {
+                    Some(TokenType::SlashEqual)
                 } else {
                     Some(TokenType::Slash)
                 }
             }
-            '\n' => {
-                self.line += 1;
-                None
-            }
             '"' => {
                 self.string();
                 None // self.string handles adding token.
@@ -320,7 +993,7 @@ impl<'a> ScannerContext<'a> {
             }
             _ if Self::is_whitespace(ch) => None, // Ignore whitespace.
             _ => {
-                self.report_error(&format!("Unexpected character '{}'.", ch));
+                self.unexpected_characters();
                 None
             }
         };
@@ -329,17 +1002,98 @@ impl<'a> ScannerContext<'a> {
         }
     }
 
+    // unexpected_characters consumes a run of characters that cannot begin
+    // a token, reporting them as a single error.
+    fn unexpected_characters(&mut self) {
+        while !self.is_at_end()
+            && !Self::is_token_start(self.peek())
+            && !self.is_unicode_identifier_start()
+        {
+            self.advance();
+        }
+        let lexeme = &self.source[self.start..self.current];
+        let msg = if lexeme.chars().count() == 1 {
+            messages::UNEXPECTED_CHARACTER.with(&[&lexeme])
+        } else {
+            messages::UNEXPECTED_CHARACTERS.with(&[&lexeme])
+        };
+        self.report_error(self.lexeme_span(), msg);
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
-    // report_error reports an error on the current line with the provided
-    // msg to the registered error_cb. report_error also sets the had_error
-    // flag.
-    fn report_error(&mut self, msg: &str) {
-        self.had_error = true;
-        if let Some(f) = self.error_cb {
-            f(self.line, msg)
+    // is_error_budget_spent returns true if the error budget is set, and at
+    // least as many errors as the budget allows have been reported.
+    fn is_error_budget_spent(&self) -> bool {
+        match self.error_budget {
+            Some(budget) => self.error_count >= budget,
+            None => false,
+        }
+    }
+
+    // report_invalid_utf8 reports the next sequence of invalid UTF-8, which
+    // starts at the current character.
+    fn report_invalid_utf8(&mut self) {
+        let (offset, len) = self.invalid_utf8.pop().expect("no invalid UTF-8 to report");
+        let end = Position::new(
+            self.position.line,
+            self.position.column + len as u64,
+            offset + len,
+        );
+        let span = Span::new(self.position, end);
+        self.push_error(LoxError::Scan {
+            span,
+            lexeme: String::from("\u{FFFD}"),
+            message: messages::INVALID_UTF8.into(),
+        });
+    }
+
+    // push_error appends err to the errors reported.
+    fn push_error(&mut self, err: LoxError) {
+        self.error_count += 1;
+        self.errors.push_back(err);
+    }
+
+    // report_error records an error at span with the provided msg, for the
+    // current lexeme.
+    fn report_error(&mut self, span: Span, msg: impl Into<Message>) {
+        self.push_error(LoxError::Scan {
+            span,
+            lexeme: self.source[self.start..self.current].to_owned(),
+            message: msg.into(),
+        });
+    }
+}
+
+// The tokens of a ScannerContext are scanned a lexeme at a time, as they are
+// requested.
+impl Iterator for ScannerContext<'_> {
+    type Item = Result<Token, LoxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(err) = self.errors.pop_front() {
+                if let Some(observer) = self.observer {
+                    Self::notify_error(observer, &err);
+                }
+                return Some(Err(err));
+            }
+            if let Some(token) = self.tokens.pop_front() {
+                if let Some(observer) = self.observer {
+                    observer.on_token(&token);
+                }
+                return Some(Ok(token));
+            }
+            if self.done {
+                return None;
+            }
+            if !self.scan_lexeme() {
+                self.report_unterminated_interpolations();
+                self.push_token(Self::eof_token(self.position));
+                self.done = true;
+            }
         }
     }
 }
@@ -348,15 +1102,33 @@ impl<'a> ScannerContext<'a> {
 mod tests {
     use super::*;
 
-    fn panic_on_error(line: u64, msg: &str) {
-        panic!("error: '{line}:{msg}'", line = line, msg = msg);
+    // expect_tokens returns the tokens of a scan, panicking on the first
+    // error of the scan, if any.
+    fn expect_tokens((tokens, errors): (Vec<Token>, Vec<LoxError>)) -> Vec<Token> {
+        if let Some(err) = errors.first() {
+            panic!(
+                "error: '{line}:{msg}'",
+                line = err.line(),
+                msg = err.message()
+            );
+        }
+        tokens
+    }
+
+    // make_span returns the span from start_column to end_column on the
+    // first line of a source.
+    fn make_span(start_column: u64, end_column: u64) -> Span {
+        Span::new(
+            Position::new(1, start_column, start_column as usize - 1),
+            Position::new(1, end_column, end_column as usize - 1),
+        )
     }
 
     #[test]
     fn test_scan_tokens_appends_eof() {
         let source = "";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut token_types = tokens.into_iter().map(|t| t.token_type);
         assert_eq!(token_types.next(), Some(TokenType::Eof));
         assert_eq!(token_types.next(), None);
@@ -365,89 +1137,220 @@ mod tests {
     #[test]
     fn test_scan_tokens_twice() {
         let source = "";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let tokens: Vec<Token> = tokens.into_iter().collect();
-        let (_, tokens2) = scanner.scan_tokens(source);
+        let tokens2 = expect_tokens(scanner.scan_tokens(source));
         let tokens2: Vec<Token> = tokens2.into_iter().collect();
         assert_eq!(tokens, tokens2);
     }
 
     #[test]
     fn test_scan_simple_tokens() {
-        let source = "( ) { } , . - + ; / * ! != = == > >= < <=";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let source = "( ) { } , . - + ; / * % ! != = == > >= < <= ** += -= *= /= ++ -- [ ] :";
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut tokens = tokens.into_iter();
 
-        fn make_token(token_type: TokenType, lexeme: &str) -> Token {
+        fn make_token(token_type: TokenType, lexeme: &str, column: u64) -> Token {
+            let span = make_span(column, column + lexeme.len() as u64);
             let lexeme = lexeme.to_owned();
             Token {
                 token_type,
-                lexeme,
-                line: 1,
+                lexeme: lexeme.into(),
+                symbol: None,
+                span,
                 literal: None,
+                error: false,
             }
         }
 
         use TokenType::*;
         // One char tokens.
-        assert_eq!(tokens.next(), Some(make_token(LeftParen, "(")));
-        assert_eq!(tokens.next(), Some(make_token(RightParen, ")")));
-        assert_eq!(tokens.next(), Some(make_token(LeftBrace, "{")));
-        assert_eq!(tokens.next(), Some(make_token(RightBrace, "}")));
-        assert_eq!(tokens.next(), Some(make_token(Comma, ",")));
-        assert_eq!(tokens.next(), Some(make_token(Dot, ".")));
-        assert_eq!(tokens.next(), Some(make_token(Minus, "-")));
-        assert_eq!(tokens.next(), Some(make_token(Plus, "+")));
-        assert_eq!(tokens.next(), Some(make_token(Semicolon, ";")));
-        assert_eq!(tokens.next(), Some(make_token(Slash, "/")));
-        assert_eq!(tokens.next(), Some(make_token(Star, "*")));
+        assert_eq!(tokens.next(), Some(make_token(LeftParen, "(", 1)));
+        assert_eq!(tokens.next(), Some(make_token(RightParen, ")", 3)));
+        assert_eq!(tokens.next(), Some(make_token(LeftBrace, "{", 5)));
+        assert_eq!(tokens.next(), Some(make_token(RightBrace, "}", 7)));
+        assert_eq!(tokens.next(), Some(make_token(Comma, ",", 9)));
+        assert_eq!(tokens.next(), Some(make_token(Dot, ".", 11)));
+        assert_eq!(tokens.next(), Some(make_token(Minus, "-", 13)));
+        assert_eq!(tokens.next(), Some(make_token(Plus, "+", 15)));
+        assert_eq!(tokens.next(), Some(make_token(Semicolon, ";", 17)));
+        assert_eq!(tokens.next(), Some(make_token(Slash, "/", 19)));
+        assert_eq!(tokens.next(), Some(make_token(Star, "*", 21)));
+        assert_eq!(tokens.next(), Some(make_token(Percent, "%", 23)));
         // One or two char tokens.
-        assert_eq!(tokens.next(), Some(make_token(Bang, "!")));
-        assert_eq!(tokens.next(), Some(make_token(BangEqual, "!=")));
-        assert_eq!(tokens.next(), Some(make_token(Equal, "=")));
-        assert_eq!(tokens.next(), Some(make_token(EqualEqual, "==")));
-        assert_eq!(tokens.next(), Some(make_token(Greater, ">")));
-        assert_eq!(tokens.next(), Some(make_token(GreaterEqual, ">=")));
-        assert_eq!(tokens.next(), Some(make_token(Less, "<")));
-        assert_eq!(tokens.next(), Some(make_token(LessEqual, "<=")));
+        assert_eq!(tokens.next(), Some(make_token(Bang, "!", 25)));
+        assert_eq!(tokens.next(), Some(make_token(BangEqual, "!=", 27)));
+        assert_eq!(tokens.next(), Some(make_token(Equal, "=", 30)));
+        assert_eq!(tokens.next(), Some(make_token(EqualEqual, "==", 32)));
+        assert_eq!(tokens.next(), Some(make_token(Greater, ">", 35)));
+        assert_eq!(tokens.next(), Some(make_token(GreaterEqual, ">=", 37)));
+        assert_eq!(tokens.next(), Some(make_token(Less, "<", 40)));
+        assert_eq!(tokens.next(), Some(make_token(LessEqual, "<=", 42)));
+        assert_eq!(tokens.next(), Some(make_token(StarStar, "**", 45)));
+        assert_eq!(tokens.next(), Some(make_token(PlusEqual, "+=", 48)));
+        assert_eq!(tokens.next(), Some(make_token(MinusEqual, "-=", 51)));
+        assert_eq!(tokens.next(), Some(make_token(StarEqual, "*=", 54)));
+        assert_eq!(tokens.next(), Some(make_token(SlashEqual, "/=", 57)));
+        assert_eq!(tokens.next(), Some(make_token(PlusPlus, "++", 60)));
+        assert_eq!(tokens.next(), Some(make_token(MinusMinus, "--", 63)));
+        assert_eq!(tokens.next(), Some(make_token(LeftBracket, "[", 66)));
+        assert_eq!(tokens.next(), Some(make_token(RightBracket, "]", 68)));
+        assert_eq!(tokens.next(), Some(make_token(Colon, ":", 70)));
 
-        assert_eq!(tokens.next(), Some(make_token(Eof, "")));
+        assert_eq!(tokens.next(), Some(make_token(Eof, "", 71)));
     }
 
     #[test]
     fn test_scan_identifer() {
         let source = " abc _def gHiJ kl_mn a1 0a ";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut tokens = tokens.into_iter();
 
-        fn make_identifer_token(identifier: &str) -> Token {
+        // Symbols are assigned in order of first occurrence.
+        let mut interner = Interner::new();
+        let mut make_identifer_token = |identifier: &str, column: u64| -> Token {
+            let span = make_span(column, column + identifier.len() as u64);
             let lexeme = identifier.to_owned();
             Token {
                 token_type: TokenType::Identifier,
-                lexeme,
-                line: 1,
+                symbol: Some(interner.intern(identifier)),
+                lexeme: lexeme.into(),
+                span,
                 literal: None,
+                error: false,
             }
-        }
+        };
 
-        assert_eq!(tokens.next(), Some(make_identifer_token("abc")));
-        assert_eq!(tokens.next(), Some(make_identifer_token("_def")));
-        assert_eq!(tokens.next(), Some(make_identifer_token("gHiJ")));
-        assert_eq!(tokens.next(), Some(make_identifer_token("kl_mn")));
-        assert_eq!(tokens.next(), Some(make_identifer_token("a1")));
+        assert_eq!(tokens.next(), Some(make_identifer_token("abc", 2)));
+        assert_eq!(tokens.next(), Some(make_identifer_token("_def", 6)));
+        assert_eq!(tokens.next(), Some(make_identifer_token("gHiJ", 11)));
+        assert_eq!(tokens.next(), Some(make_identifer_token("kl_mn", 16)));
+        assert_eq!(tokens.next(), Some(make_identifer_token("a1", 22)));
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Number));
-        assert_eq!(tokens.next(), Some(make_identifer_token("a")));
+        assert_eq!(tokens.next(), Some(make_identifer_token("a", 26)));
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Eof));
     }
 
+    #[test]
+    fn test_scan_unicode_identifier() {
+        let source = "var größe = über_1;";
+        let scanner = Scanner::new().with_unicode_identifiers(true);
+        let tokens = expect_tokens(scanner.scan_tokens(source));
+        let tokens: Vec<Token> = tokens.into_iter().collect();
+
+        use TokenType::*;
+        let token_types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![Var, Identifier, Equal, Identifier, Semicolon, Eof]
+        );
+        assert_eq!(tokens[1].lexeme, "größe");
+        // Columns count characters, while offsets count bytes.
+        let span = Span::new(Position::new(1, 5, 4), Position::new(1, 10, 11));
+        assert_eq!(tokens[1].span, span);
+        assert_eq!(&source[span.bytes()], "größe");
+        assert_eq!(tokens[3].lexeme, "über_1");
+    }
+
+    #[test]
+    fn test_scan_multi_byte_characters() {
+        let source = "\"日本\" /* ☃ */ \"é\"";
+        let tokens = expect_tokens(Scanner::new().scan_tokens(source));
+        assert_eq!(tokens[0].literal, Some(Literal::String("日本".into())));
+        assert_eq!(tokens[1].literal, Some(Literal::String("é".into())));
+        // Columns count characters, also within strings and comments.
+        let span = Span::new(Position::new(1, 14, 19), Position::new(1, 17, 23));
+        assert_eq!(tokens[1].span, span);
+
+        let (_, errors) = Scanner::new().scan_tokens("a ü ☃☃ b");
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+        assert_eq!(
+            messages,
+            vec!["Unexpected character 'ü'.", "Unexpected characters '☃☃'."]
+        );
+    }
+
+    #[test]
+    fn test_scan_bytes_invalid_utf8() {
+        let source = b"print \"a\xff\";\n\xc3\x28 1;";
+        let (tokens, errors) = Scanner::new().scan_bytes(source);
+        let spans: Vec<(Span, &str)> = errors.iter().map(|e| (e.span(), e.message())).collect();
+        let msg = "Invalid UTF-8 sequence.";
+        assert_eq!(
+            spans,
+            vec![
+                (
+                    Span::new(Position::new(1, 9, 8), Position::new(1, 10, 9)),
+                    msg
+                ),
+                (
+                    Span::new(Position::new(2, 1, 12), Position::new(2, 2, 13)),
+                    msg
+                ),
+            ]
+        );
+        // The rest of the source is still scanned, with tokens at their
+        // positions in source.
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, vec!["print", "\"a \"", ";", "(", "1", ";", ""]);
+        assert_eq!(tokens[3].span.bytes(), 13..14);
+        assert_eq!(Scanner::new().scan_bytes(b"1 + 2").1, Vec::new());
+    }
+
+    #[test]
+    fn test_scan_unicode_identifier_normalized() {
+        // "é" precomposed, and as "e" followed by a combining acute accent.
+        let source = "caf\u{e9} cafe\u{301}";
+        let scanner = Scanner::new().with_unicode_identifiers(true);
+        let tokens = expect_tokens(scanner.scan_tokens(source));
+        let tokens: Vec<Token> = tokens.into_iter().collect();
+        assert_eq!(tokens[0].lexeme, "caf\u{e9}");
+        assert_eq!(tokens[1].lexeme, "caf\u{e9}");
+        assert_eq!(tokens[0].symbol, tokens[1].symbol);
+    }
+
+    #[test]
+    fn test_scan_unicode_identifier_disabled() {
+        let scanner = Scanner::new();
+        let (_, errors) = scanner.scan_tokens("größe");
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Unexpected character")]
+    fn test_scan_unicode_non_identifier() {
+        let scanner = Scanner::new().with_unicode_identifiers(true);
+        expect_tokens(scanner.scan_tokens("a ☃"));
+    }
+
+    #[test]
+    fn test_scan_tokens_with_interner() {
+        let source = "a b a";
+        let mut interner = Interner::new();
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens_with_interner(source, &mut interner));
+        let symbols: Vec<_> = tokens.into_iter().filter_map(|t| t.symbol).collect();
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[0], symbols[2]);
+        assert_ne!(symbols[0], symbols[1]);
+        assert_eq!(interner.resolve(symbols[0]), "a");
+        assert_eq!(interner.resolve(symbols[1]), "b");
+
+        // Symbols are shared between scans using the same interner.
+        let tokens = expect_tokens(scanner.scan_tokens_with_interner("b", &mut interner));
+        let symbol = tokens.into_iter().next().and_then(|t| t.symbol);
+        assert_eq!(symbol, Some(symbols[1]));
+    }
+
     #[test]
     fn test_scan_keyword() {
         let source = " for IF force ";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut token_types = tokens.into_iter().map(|t| t.token_type);
 
         assert_eq!(token_types.next(), Some(TokenType::For));
@@ -459,50 +1362,116 @@ mod tests {
     #[test]
     fn test_scan_string() {
         let source = " \"ab\" \"c\nd\" \"ef\" ";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut tokens = tokens.into_iter();
 
-        fn make_string_token(s: &str, line: u64) -> Token {
+        fn make_string_token(s: &str, span: Span) -> Token {
             let lexeme = format!(r#""{}""#, s); // Add quotes.
-            let literal = Some(Literal::String(s.to_owned()));
+            let literal = Some(Literal::String(s.into()));
             Token {
                 token_type: TokenType::String,
-                lexeme,
-                line,
+                lexeme: lexeme.into(),
+                symbol: None,
+                span,
                 literal,
+                error: false,
             }
         }
 
-        assert_eq!(tokens.next(), Some(make_string_token("ab", 1)));
-        assert_eq!(tokens.next(), Some(make_string_token("c\nd", 2)));
-        assert_eq!(tokens.next(), Some(make_string_token("ef", 2)));
+        let multi_line_span = Span::new(Position::new(1, 7, 6), Position::new(2, 3, 11));
+        assert_eq!(
+            tokens.next(),
+            Some(make_string_token("ab", make_span(2, 6)))
+        );
+        assert_eq!(
+            tokens.next(),
+            Some(make_string_token("c\nd", multi_line_span))
+        );
+        assert_eq!(
+            tokens.next(),
+            Some(make_string_token(
+                "ef",
+                Span::new(Position::new(2, 4, 12), Position::new(2, 8, 16))
+            ))
+        );
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Eof));
     }
 
+    #[test]
+    fn test_scan_interpolation() {
+        let source = r#""a ${b + "${c}"} { ${ {} } \${d}""#;
+        let tokens = expect_tokens(Scanner::new().scan_tokens(source));
+        let tokens: Vec<(TokenType, &str, Option<Literal>)> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_str(), t.literal.clone()))
+            .collect();
+        let string = |s: &str| Some(Literal::String(s.into()));
+
+        use TokenType::*;
+        assert_eq!(
+            tokens,
+            vec![
+                (Interpolation, "\"a ${", string("a ")),
+                (Identifier, "b", None),
+                (Plus, "+", None),
+                (Interpolation, "\"${", string("")),
+                (Identifier, "c", None),
+                (String, "}\"", string("")),
+                (Interpolation, "} { ${", string(" { ")),
+                (LeftBrace, "{", None),
+                (RightBrace, "}", None),
+                (String, r#"} \${d}""#, string(" ${d}")),
+                (Eof, "", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_unterminated_interpolation() {
+        let (tokens, errors) = Scanner::new().scan_tokens("\"a ${b\nc");
+        let token_types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        use TokenType::*;
+        assert_eq!(
+            token_types,
+            vec![Interpolation, Identifier, Identifier, Eof]
+        );
+        assert_eq!(
+            errors,
+            vec![LoxError::Scan {
+                span: make_span(4, 6),
+                lexeme: "${".to_owned(),
+                message: "Unterminated interpolation.".into(),
+            }]
+        );
+    }
+
     #[test]
     fn test_scan_number() {
         let source = " 111 111.222 -333 444. ";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (_, tokens) = scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
         let mut tokens = tokens.into_iter();
 
-        fn make_number_token(n: f64) -> Token {
+        fn make_number_token(n: f64, column: u64) -> Token {
             let lexeme = format!("{}", n);
+            let span = make_span(column, column + lexeme.len() as u64);
             let literal = Some(Literal::Number(n));
             Token {
                 token_type: TokenType::Number,
-                lexeme,
-                line: 1,
+                lexeme: lexeme.into(),
+                symbol: None,
+                span,
                 literal,
+                error: false,
             }
         }
 
-        assert_eq!(tokens.next(), Some(make_number_token(111.0)));
-        assert_eq!(tokens.next(), Some(make_number_token(111.222)));
+        assert_eq!(tokens.next(), Some(make_number_token(111.0, 2)));
+        assert_eq!(tokens.next(), Some(make_number_token(111.222, 6)));
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Minus));
-        assert_eq!(tokens.next(), Some(make_number_token(333.0)));
-        assert_eq!(tokens.next(), Some(make_number_token(444.0)));
+        assert_eq!(tokens.next(), Some(make_number_token(333.0, 15)));
+        assert_eq!(tokens.next(), Some(make_number_token(444.0, 19)));
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Dot));
         assert_eq!(tokens.next().map(|t| t.token_type), Some(TokenType::Eof));
     }
@@ -511,32 +1480,329 @@ mod tests {
     #[should_panic(expected = "2:Unexpected character '~'.")]
     fn test_scan_tokens_unexpected_token() {
         let source = "\n~";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        expect_tokens(scanner.scan_tokens(source));
     }
 
     #[test]
     #[should_panic(expected = "3:Unterminated string.")]
     fn test_scan_tokens_unterminated_string() {
         let source = "\n\"\n";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        scanner.scan_tokens(source);
+        let scanner = Scanner::new();
+        expect_tokens(scanner.scan_tokens(source));
+    }
+
+    #[test]
+    fn test_scan_tokens_unterminated_string_token() {
+        let source = "a \"b\nc";
+        let scanner = Scanner::new();
+        let (tokens, errors) = scanner.scan_tokens(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].lexeme(), "\"b\nc");
+        let string = &tokens[1];
+        assert_eq!(string.token_type, TokenType::String);
+        assert_eq!(string.lexeme, "\"b\nc");
+        assert_eq!(string.literal, Some(Literal::String("b\nc".into())));
+        assert_eq!(
+            string.span,
+            Span::new(Position::new(1, 3, 2), Position::new(2, 2, 6))
+        );
+        assert!(string.error);
+        assert!(!tokens[0].error);
+        assert_eq!(tokens[2].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn test_scan_string_escapes() {
+        let source = r#""a\n\t\"\\b" "\u{41}\u{1F600}""#;
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
+        assert_eq!(tokens[0].lexeme, r#""a\n\t\"\\b""#);
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::String("a\n\t\"\\b".into()))
+        );
+        assert_eq!(
+            tokens[1].literal,
+            Some(Literal::String("A\u{1F600}".into()))
+        );
+    }
+
+    #[test]
+    fn test_scan_string_invalid_escapes() {
+        let cases = [
+            (r#""\q""#, "Invalid escape sequence '\\q'."),
+            (r#""\u41""#, "Invalid unicode escape sequence."),
+            (r#""\u{}""#, "Invalid unicode escape sequence."),
+            (r#""\u{+41}""#, "Invalid unicode escape sequence."),
+            (r#""\u{1234567}""#, "Invalid unicode escape sequence."),
+            (r#""\u{D800}""#, "Invalid unicode escape sequence."),
+        ];
+        for (source, msg) in cases.iter() {
+            let (tokens, errors) = Scanner::new().scan_tokens(source);
+            let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+            assert_eq!(messages, vec![*msg], "scanning {}", source);
+            assert!(tokens[0].error);
+        }
+        // A backslash escapes the quote that would otherwise end the string.
+        let (_, errors) = Scanner::new().scan_tokens(r#""\""#);
+        assert_eq!(errors[0].message(), "Unterminated string.");
+    }
+
+    #[test]
+    fn test_scan_block_comment() {
+        let source = "a /* b\n/* c */ * / */ d /**/ e/*/ f */";
+        let scanner = Scanner::new();
+        let tokens = expect_tokens(scanner.scan_tokens(source));
+        let lexemes: Vec<(&str, u64)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_str(), t.span.start.line))
+            .collect();
+        assert_eq!(lexemes, vec![("a", 1), ("d", 2), ("e", 2), ("", 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "3:Unterminated block comment.")]
+    fn test_scan_tokens_unterminated_block_comment() {
+        let source = "/* a /* b */\n\n";
+        let scanner = Scanner::new();
+        expect_tokens(scanner.scan_tokens(source));
+    }
+
+    #[test]
+    #[should_panic(expected = "1:Invalid number literal '1000")]
+    fn test_scan_tokens_number_overflow() {
+        let source = format!("1{}", "0".repeat(400));
+        let scanner = Scanner::new();
+        expect_tokens(scanner.scan_tokens(&source));
+    }
+
+    #[test]
+    fn test_scan_tokens_error_span() {
+        let source = "a\n  ~";
+        let scanner = Scanner::new();
+        let (_, errors) = scanner.scan_tokens(source);
+        let spans: Vec<Span> = errors.iter().map(LoxError::span).collect();
+        let span = Span::new(Position::new(2, 3, 4), Position::new(2, 4, 5));
+        assert_eq!(spans, vec![span]);
+    }
+
+    #[test]
+    fn test_scan_tokens_notifies_observer() {
+        use crate::observer::FrontendObserver;
+        use std::cell::RefCell;
+
+        #[derive(Default)]
+        struct Recorder {
+            events: RefCell<Vec<String>>,
+        }
+
+        impl FrontendObserver for Recorder {
+            fn on_token(&self, token: &Token) {
+                let event = format!("token {:?}", token.token_type);
+                self.events.borrow_mut().push(event);
+            }
+
+            fn on_diagnostic(&self, span: Span, msg: &Message) {
+                let event = format!("diagnostic {} {}", span, msg);
+                self.events.borrow_mut().push(event);
+            }
+        }
+
+        let recorder = Recorder::default();
+        let scanner = Scanner::new().with_observer(&recorder);
+        scanner.scan_tokens("a ~");
+        assert_eq!(
+            recorder.events.into_inner(),
+            vec![
+                "token Identifier",
+                "diagnostic 1:3-1:4 Unexpected character '~'.",
+                "token Eof",
+            ]
+        );
     }
 
     #[test]
-    fn test_had_error_ok_scan() {
+    fn test_scan_string_literals_shared() {
+        let tokens = expect_tokens(Scanner::new().scan_tokens(r#""abc" "abc" "a\u{62}c""#));
+        let strings: Vec<Rc<str>> = tokens
+            .iter()
+            .filter_map(|token| match token.literal {
+                Some(Literal::String(ref s)) => Some(Rc::clone(s)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strings.len(), 3);
+        assert!(Rc::ptr_eq(&strings[0], &strings[1]));
+        // Literals are shared by value, not by how they are written.
+        assert!(Rc::ptr_eq(&strings[0], &strings[2]));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let source = "a ~ \"b";
+        let scanned: Vec<Result<TokenType, String>> = Scanner::new()
+            .tokens(source)
+            .map(|scanned| match scanned {
+                Ok(token) => Ok(token.token_type),
+                Err(err) => Err(err.message().to_owned()),
+            })
+            .collect();
+        assert_eq!(
+            scanned,
+            vec![
+                Ok(TokenType::Identifier),
+                Err(String::from("Unexpected character '~'.")),
+                Err(String::from("Unterminated string.")),
+                Ok(TokenType::String),
+                Ok(TokenType::Eof),
+            ]
+        );
+
+        let tokens = Scanner::new().tokens("a");
+        let tokens: Result<Vec<Token>, LoxError> = tokens.collect();
+        assert_eq!(Ok(Scanner::new().scan_tokens("a").0), tokens);
+    }
+
+    #[test]
+    fn test_incremental_scanner() {
+        let source = "var s = \"a\nb\"; /* c\n*/ print s != nil ~;\n";
+        let pieces = [
+            "var s = \"a",
+            "\nb\"; /",
+            "* c\n",
+            "*/ print s !",
+            "= nil ~;\n",
+        ];
+        let mut scanner = IncrementalScanner::new(Scanner::new());
+        assert!(scanner.is_empty());
+        scanner.feed(pieces[0]);
+        assert!(scanner.is_pending());
+        let token_types: Vec<TokenType> = scanner.tokens().iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Eof
+            ]
+        );
+        for piece in &pieces[1..] {
+            scanner.feed(piece);
+        }
+        assert!(!scanner.is_pending());
+        assert_eq!(scanner.errors().len(), 1);
+        assert_eq!(scanner.finish(), Scanner::new().scan_tokens(source));
+
+        // The scanner starts over after finish.
+        assert!(scanner.is_empty());
+        scanner.feed("\"a");
+        assert_eq!(scanner.finish(), Scanner::new().scan_tokens("\"a"));
+
+        // Input ending inside an interpolation continues on the next line.
+        scanner.feed("print \"${\n");
+        assert!(scanner.is_pending());
+        scanner.feed("a}\n");
+        assert!(scanner.is_pending());
+        scanner.feed("\";\n");
+        assert!(!scanner.is_pending());
+        let source = "print \"${\na}\n\";\n";
+        assert_eq!(scanner.finish(), Scanner::new().scan_tokens(source));
+    }
+
+    #[test]
+    fn test_scan_tokens_groups_unexpected_characters() {
+        let source = "a ~~~ b ~";
+        let scanner = Scanner::new();
+        let (tokens, errors) = scanner.scan_tokens(source);
+        let token_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+
+        use TokenType::*;
+        assert_eq!(token_types, vec![Identifier, Identifier, Eof]);
+        assert_eq!(
+            errors,
+            vec![
+                LoxError::Scan {
+                    span: make_span(3, 6),
+                    lexeme: "~~~".to_owned(),
+                    message: "Unexpected characters '~~~'.".into(),
+                },
+                LoxError::Scan {
+                    span: make_span(9, 10),
+                    lexeme: "~".to_owned(),
+                    message: "Unexpected character '~'.".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_tokens_unexpected_character_before_bracket() {
+        let source = "@[1~]";
+        let scanner = Scanner::new();
+        let (tokens, errors) = scanner.scan_tokens(source);
+        let token_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+
+        use TokenType::*;
+        assert_eq!(token_types, vec![LeftBracket, Number, RightBracket, Eof]);
+        assert_eq!(
+            messages,
+            vec!["Unexpected character '@'.", "Unexpected character '~'."]
+        );
+    }
+
+    #[test]
+    fn test_scan_tokens_unexpected_character_before_colon() {
+        let source = "{\"a\"#: 1}";
+        let scanner = Scanner::new();
+        let (tokens, errors) = scanner.scan_tokens(source);
+        let token_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+
+        use TokenType::*;
+        assert_eq!(
+            token_types,
+            vec![LeftBrace, String, Colon, Number, RightBrace, Eof]
+        );
+        assert_eq!(messages, vec!["Unexpected character '#'."]);
+    }
+
+    #[test]
+    fn test_scan_tokens_error_budget() {
+        let source = "~ a ~ b ~ c ~";
+        let scanner = Scanner::new().with_error_budget(2);
+        let (tokens, errors) = scanner.scan_tokens(source);
+        let token_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+
+        use TokenType::*;
+        assert_eq!(token_types, vec![Identifier, Eof]);
+        assert_eq!(
+            messages,
+            vec![
+                "Unexpected character '~'.",
+                "Unexpected character '~'.",
+                "Too many errors, stopping.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_ok_scan() {
         let source = "";
-        let scanner = Scanner::new(Some(&panic_on_error));
-        let (had_error, _) = scanner.scan_tokens(source);
-        assert_eq!(had_error, false);
+        let scanner = Scanner::new();
+        let (_, errors) = scanner.scan_tokens(source);
+        assert!(errors.is_empty());
     }
 
     #[test]
-    fn test_had_error_failed_scan() {
+    fn test_errors_failed_scan() {
         let source = "~"; // Unexpected token '~'.
-        let scanner = Scanner::new(None);
-        let (had_error, _) = scanner.scan_tokens(source);
-        assert_eq!(had_error, true);
+        let scanner = Scanner::new();
+        let (_, errors) = scanner.scan_tokens(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].lexeme(), "~");
     }
-
 }
@@ -0,0 +1,67 @@
+use std::io::{self, Read};
+
+// read_source reads the full source of a program from reader, which can be
+// any Read such as a file, a socket or a decompressing stream. The source
+// is buffered incrementally; if max_size is set and the source is larger
+// than max_size bytes, reading stops and an error is returned. An error is
+// also returned if the source is not valid UTF-8.
+pub fn read_source<R: Read>(reader: R, max_size: Option<usize>) -> io::Result<String> {
+    let buf = read_source_bytes(reader, max_size)?;
+    String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// read_source_bytes is like read_source, but returns the source as bytes,
+// without requiring it to be valid UTF-8. This allows the Scanner to report
+// invalid UTF-8 where it occurs in the source.
+pub fn read_source_bytes<R: Read>(reader: R, max_size: Option<usize>) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match max_size {
+        Some(max_size) => {
+            // Read at most one byte past the limit to detect oversized input
+            // without consuming the rest of the reader.
+            reader.take(max_size as u64 + 1).read_to_end(&mut buf)?;
+            if buf.len() > max_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("source exceeds the maximum size of {} bytes", max_size),
+                ));
+            }
+        }
+        None => {
+            let mut reader = reader;
+            reader.read_to_end(&mut buf)?;
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_source() {
+        let source = read_source("print 1;".as_bytes(), None).unwrap();
+        assert_eq!(source, "print 1;");
+    }
+
+    #[test]
+    fn test_read_source_within_max_size() {
+        let source = read_source("print 1;".as_bytes(), Some(8)).unwrap();
+        assert_eq!(source, "print 1;");
+    }
+
+    #[test]
+    fn test_read_source_exceeds_max_size() {
+        let err = read_source("print 1;".as_bytes(), Some(7)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_source_invalid_utf8() {
+        let err = read_source(&[0x22, 0xff, 0x22][..], None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let source = read_source_bytes(&[0x22, 0xff, 0x22][..], None).unwrap();
+        assert_eq!(source, [0x22, 0xff, 0x22]);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::ops::Range;
+
+// Position is a location in the source, as a 1-based line and column, and
+// as the 0-based byte offset into the source.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Position {
+    pub line: u64,
+    pub column: u64,
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn new(line: u64, column: u64, offset: usize) -> Self {
+        Position {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
+// offset_of returns the byte offset of the 1-based line and column in
+// source, with columns counted in characters as by the Scanner, or None if
+// source has no such position. The column just past the end of a line is
+// that of its line break.
+pub fn offset_of(source: &str, line: u64, column: u64) -> Option<usize> {
+    let start = match line {
+        0 => return None,
+        1 => 0,
+        _ => {
+            let (index, _) = source.match_indices('\n').nth(line as usize - 2)?;
+            index + 1
+        }
+    };
+    let text = source[start..].split('\n').next().unwrap_or("");
+    let column = (column as usize).checked_sub(1)?;
+    match text.char_indices().nth(column) {
+        Some((index, _)) => Some(start + index),
+        None if column == text.chars().count() => Some(start + text.len()),
+        None => None,
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// Span is a range in the source, from start up to (but not including) end.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    // to returns a span from the start of self to the end of other.
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+
+    // bytes returns the range of bytes of the source covered by the span,
+    // e.g. to slice the source text of a token.
+    pub fn bytes(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_to() {
+        let a = Span::new(Position::new(1, 1, 0), Position::new(1, 4, 3));
+        let b = Span::new(Position::new(2, 3, 7), Position::new(2, 5, 9));
+        let span = a.to(b);
+        assert_eq!(
+            span,
+            Span::new(Position::new(1, 1, 0), Position::new(2, 5, 9))
+        );
+        assert_eq!(span.bytes(), 0..9);
+    }
+
+    #[test]
+    fn test_offset_of() {
+        let source = "ab\nçd\n";
+        assert_eq!(offset_of(source, 1, 1), Some(0));
+        assert_eq!(offset_of(source, 1, 3), Some(2));
+        assert_eq!(offset_of(source, 2, 2), Some(5));
+        assert_eq!(offset_of(source, 3, 1), Some(7));
+        assert_eq!(offset_of(source, 1, 4), None);
+        assert_eq!(offset_of(source, 4, 1), None);
+        assert_eq!(offset_of(source, 1, 0), None);
+    }
+
+    #[test]
+    fn test_span_display() {
+        let span = Span::new(Position::new(1, 2, 1), Position::new(3, 4, 12));
+        assert_eq!(format!("{}", span), "1:2-3:4");
+    }
+}
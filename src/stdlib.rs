@@ -0,0 +1,424 @@
+use std::rc::Rc;
+
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::interpreter::{Interpreter, LoxList, LoxMap, NativeFunction};
+use super::messages::{self, Message};
+use super::value::Value;
+
+// Stdlib selects the groups of natives of the standard library that are
+// defined as globals of an Interpreter, see InterpreterBuilder::stdlib.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Stdlib {
+    // strings enables len, byte_len, substr, upper, lower, parse_number and
+    // str, and with the graphemes feature grapheme_len, grapheme_substr and
+    // graphemes.
+    pub strings: bool,
+    // math enables floor, ceil, abs, sqrt, min and max.
+    pub math: bool,
+    // lists enables len, push and pop.
+    pub lists: bool,
+    // maps enables len, keys, values, has and remove.
+    pub maps: bool,
+}
+
+impl Stdlib {
+    // all returns the Stdlib enabling every group.
+    pub fn all() -> Self {
+        Stdlib {
+            strings: true,
+            math: true,
+            lists: true,
+            maps: true,
+        }
+    }
+
+    // none returns the Stdlib enabling no group.
+    pub fn none() -> Self {
+        Stdlib {
+            strings: false,
+            math: false,
+            lists: false,
+            maps: false,
+        }
+    }
+}
+
+impl Default for Stdlib {
+    fn default() -> Self {
+        Stdlib::all()
+    }
+}
+
+// natives returns the natives of the groups enabled by stdlib.
+pub fn natives(stdlib: Stdlib) -> Vec<NativeFunction> {
+    let mut natives = Vec::new();
+    if stdlib.strings || stdlib.lists || stdlib.maps {
+        natives.push(NativeFunction::new("len", 1, len));
+    }
+    if stdlib.strings {
+        natives.push(NativeFunction::new("byte_len", 1, |_, arguments| {
+            Ok(Value::Number(string(&arguments[0])?.len() as f64))
+        }));
+        natives.push(NativeFunction::new("substr", 3, substr));
+        #[cfg(feature = "graphemes")]
+        {
+            natives.push(NativeFunction::new("grapheme_len", 1, |_, arguments| {
+                let length = string(&arguments[0])?.graphemes(true).count();
+                Ok(Value::Number(length as f64))
+            }));
+            natives.push(NativeFunction::new("grapheme_substr", 3, grapheme_substr));
+            natives.push(NativeFunction::new("graphemes", 1, |_, arguments| {
+                let s = string(&arguments[0])?;
+                let graphemes = s.graphemes(true).map(Value::from);
+                Ok(Value::List(Rc::new(LoxList::new(graphemes.collect()))))
+            }));
+        }
+        natives.push(NativeFunction::new("upper", 1, |_, arguments| {
+            Ok(Value::from(string(&arguments[0])?.to_uppercase()))
+        }));
+        natives.push(NativeFunction::new("lower", 1, |_, arguments| {
+            Ok(Value::from(string(&arguments[0])?.to_lowercase()))
+        }));
+        natives.push(NativeFunction::new("parse_number", 1, parse_number));
+        natives.push(NativeFunction::new("str", 1, |_, arguments| {
+            Ok(Value::from(arguments[0].to_string()))
+        }));
+    }
+    if stdlib.math {
+        natives.push(math("floor", f64::floor));
+        natives.push(math("ceil", f64::ceil));
+        natives.push(math("abs", f64::abs));
+        natives.push(math("sqrt", f64::sqrt));
+        natives.push(NativeFunction::new("min", 2, |_, arguments| {
+            Ok(Value::Number(
+                number(&arguments[0])?.min(number(&arguments[1])?),
+            ))
+        }));
+        natives.push(NativeFunction::new("max", 2, |_, arguments| {
+            Ok(Value::Number(
+                number(&arguments[0])?.max(number(&arguments[1])?),
+            ))
+        }));
+    }
+    if stdlib.lists {
+        natives.push(NativeFunction::new("push", 2, |_, arguments| {
+            list(&arguments[0])?.push(arguments[1].clone());
+            Ok(Value::Nil)
+        }));
+        natives.push(NativeFunction::new("pop", 1, |_, arguments| {
+            list(&arguments[0])?
+                .pop()
+                .ok_or_else(|| messages::POP_FROM_EMPTY_LIST.into())
+        }));
+    }
+    if stdlib.maps {
+        natives.push(NativeFunction::new("keys", 1, |_, arguments| {
+            let keys = map(&arguments[0])?
+                .entries()
+                .into_iter()
+                .map(|(key, _)| key);
+            Ok(Value::List(Rc::new(LoxList::new(keys.collect()))))
+        }));
+        natives.push(NativeFunction::new("values", 1, |_, arguments| {
+            let values = map(&arguments[0])?.entries().into_iter();
+            let values = values.map(|(_, value)| value);
+            Ok(Value::List(Rc::new(LoxList::new(values.collect()))))
+        }));
+        natives.push(NativeFunction::new("has", 2, |_, arguments| {
+            let value = map(&arguments[0])?.get(&arguments[1])?;
+            Ok(Value::Bool(value.is_some()))
+        }));
+        natives.push(NativeFunction::new("remove", 2, |_, arguments| {
+            let value = map(&arguments[0])?.remove(&arguments[1])?;
+            Ok(value.unwrap_or(Value::Nil))
+        }));
+    }
+    natives
+}
+
+// string returns the contents of value, which must be a string.
+fn string(value: &Value) -> Result<String, Message> {
+    match value {
+        Value::String(s) => Ok(s.to_string()),
+        _ => Err(messages::ARGUMENT_NOT_STRING.into()),
+    }
+}
+
+// list returns the list of value, which must be a list.
+fn list(value: &Value) -> Result<Rc<LoxList>, Message> {
+    match value {
+        Value::List(list) => Ok(Rc::clone(list)),
+        _ => Err(messages::ARGUMENT_NOT_LIST.into()),
+    }
+}
+
+// map returns the map of value, which must be a map.
+fn map(value: &Value) -> Result<Rc<LoxMap>, Message> {
+    match value {
+        Value::Map(map) => Ok(Rc::clone(map)),
+        _ => Err(messages::ARGUMENT_NOT_MAP.into()),
+    }
+}
+
+// number returns the number of value, which must be a number.
+fn number(value: &Value) -> Result<f64, Message> {
+    match *value {
+        Value::Number(n) => Ok(n),
+        _ => Err(messages::ARGUMENT_NOT_NUMBER.into()),
+    }
+}
+
+// index returns value as an index, which must be a non-negative integer.
+fn index(value: &Value) -> Result<usize, Message> {
+    match *value {
+        Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(messages::INDEX_NOT_NATURAL.into()),
+    }
+}
+
+// math returns the native name applying f to its number argument.
+fn math(name: &str, f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::new(name, 1, move |_, arguments| {
+        Ok(Value::Number(f(number(&arguments[0])?)))
+    })
+}
+
+// len returns the number of characters of a string, the number of
+// elements of a list, or the number of entries of a map.
+fn len(_: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Message> {
+    let length = match arguments[0] {
+        Value::String(ref s) => s.with_str(|s| s.chars().count()),
+        Value::List(ref list) => list.len(),
+        Value::Map(ref map) => map.len(),
+        _ => return Err(messages::ARGUMENT_NOT_SIZED.into()),
+    };
+    Ok(Value::Number(length as f64))
+}
+
+// substr returns the part of a string of at most length characters,
+// starting at the character at index start.
+fn substr(_: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Message> {
+    let s = string(&arguments[0])?;
+    let start = index(&arguments[1])?;
+    let length = index(&arguments[2])?;
+    if start > s.chars().count() {
+        return Err(messages::SUBSTRING_OUT_OF_RANGE.into());
+    }
+    let substring: String = s.chars().skip(start).take(length).collect();
+    Ok(Value::from(substring))
+}
+
+// grapheme_substr is substr counting grapheme clusters rather than
+// characters.
+#[cfg(feature = "graphemes")]
+fn grapheme_substr(_: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Message> {
+    let s = string(&arguments[0])?;
+    let start = index(&arguments[1])?;
+    let length = index(&arguments[2])?;
+    if start > s.graphemes(true).count() {
+        return Err(messages::SUBSTRING_OUT_OF_RANGE.into());
+    }
+    let substring: String = s.graphemes(true).skip(start).take(length).collect();
+    Ok(Value::from(substring))
+}
+
+// parse_number returns the number written in a string, in the syntax of a
+// Lox number literal with an optional leading minus sign, or nil if the
+// string is not a number. Surrounding whitespace is ignored.
+fn parse_number(_: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Message> {
+    let s = string(&arguments[0])?;
+    let s = s.trim();
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let valid = match digits.split_once('.') {
+        Some((whole, fraction)) => is_digits(whole) && is_digits(fraction),
+        None => is_digits(digits),
+    };
+    if !valid {
+        return Ok(Value::Nil);
+    }
+    Ok(s.parse().map_or(Value::Nil, Value::Number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(
+        interpreter: &mut Interpreter,
+        name: &str,
+        arguments: &[Value],
+    ) -> Result<Value, String> {
+        interpreter
+            .call(name, arguments)
+            .map_err(|err| err.message.to_string())
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut interpreter = Interpreter::new();
+        let mut call = |name: &str, arguments: &[Value]| call(&mut interpreter, name, arguments);
+        assert_eq!(call("len", &[Value::from("héllo")]), Ok(Value::from(5.0)));
+        let substr = |start: f64, length: f64| [Value::from("héllo"), start.into(), length.into()];
+        assert_eq!(call("substr", &substr(1.0, 3.0)), Ok(Value::from("éll")));
+        assert_eq!(call("substr", &substr(3.0, 9.0)), Ok(Value::from("lo")));
+        assert_eq!(call("substr", &substr(5.0, 1.0)), Ok(Value::from("")));
+        assert_eq!(
+            call("substr", &substr(6.0, 1.0)),
+            Err(String::from("Substring start out of range."))
+        );
+        assert_eq!(
+            call("substr", &substr(0.5, 1.0)),
+            Err(String::from("Index must be a non-negative integer."))
+        );
+        assert_eq!(call("upper", &[Value::from("aé")]), Ok(Value::from("AÉ")));
+        assert_eq!(call("lower", &[Value::from("AÉ")]), Ok(Value::from("aé")));
+        assert_eq!(
+            call("upper", &[Value::Nil]),
+            Err(String::from("Argument must be a string."))
+        );
+        assert_eq!(call("str", &[Value::from(1.5)]), Ok(Value::from("1.5")));
+        assert_eq!(call("str", &[Value::Nil]), Ok(Value::from("nil")));
+        assert_eq!(
+            call("byte_len", &[Value::from("héllo")]),
+            Ok(Value::from(6.0))
+        );
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn test_graphemes() {
+        let mut interpreter = Interpreter::new();
+        let mut call = |name: &str, arguments: &[Value]| call(&mut interpreter, name, arguments);
+        // An e with a combining acute accent, and a family emoji of four
+        // people joined by zero width joiners.
+        let text = "e\u{301}👨\u{200d}👩\u{200d}👧\u{200d}👦!";
+        assert_eq!(call("len", &[Value::from(text)]), Ok(Value::from(10.0)));
+        assert_eq!(
+            call("grapheme_len", &[Value::from(text)]),
+            Ok(Value::from(3.0))
+        );
+        let substr = |start: f64, length: f64| [Value::from(text), start.into(), length.into()];
+        assert_eq!(
+            call("grapheme_substr", &substr(1.0, 1.0)),
+            Ok(Value::from("👨\u{200d}👩\u{200d}👧\u{200d}👦"))
+        );
+        assert_eq!(
+            call("grapheme_substr", &substr(4.0, 1.0)),
+            Err(String::from("Substring start out of range."))
+        );
+        let graphemes = call("graphemes", &[Value::from("e\u{301}a")]).unwrap();
+        assert_eq!(graphemes.to_string(), "[\"e\u{301}\", \"a\"]");
+    }
+
+    #[test]
+    fn test_parse_number() {
+        let mut interpreter = Interpreter::new();
+        let mut parse = |s: &str| call(&mut interpreter, "parse_number", &[Value::from(s)]);
+        assert_eq!(parse("12"), Ok(Value::from(12.0)));
+        assert_eq!(parse(" -1.5\n"), Ok(Value::from(-1.5)));
+        for invalid in &["", "-", "1.", ".5", "1e3", "inf", "NaN", "0x10", "1 2"] {
+            assert_eq!(parse(invalid), Ok(Value::Nil), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_math() {
+        let mut interpreter = Interpreter::new();
+        let mut call = |name: &str, arguments: &[f64]| {
+            let arguments: Vec<Value> = arguments.iter().map(|&n| Value::from(n)).collect();
+            call(&mut interpreter, name, &arguments)
+        };
+        assert_eq!(call("floor", &[-1.5]), Ok(Value::from(-2.0)));
+        assert_eq!(call("ceil", &[1.2]), Ok(Value::from(2.0)));
+        assert_eq!(call("abs", &[-3.0]), Ok(Value::from(3.0)));
+        assert_eq!(call("sqrt", &[16.0]), Ok(Value::from(4.0)));
+        assert_eq!(call("min", &[1.0, 2.0]), Ok(Value::from(1.0)));
+        assert_eq!(call("max", &[1.0, 2.0]), Ok(Value::from(2.0)));
+        assert_eq!(
+            interpreter
+                .call("sqrt", &[Value::from("4")])
+                .unwrap_err()
+                .message,
+            "Argument must be a number."
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let mut interpreter = Interpreter::new();
+        let list = Rc::new(LoxList::new(vec![Value::from(1.0)]));
+        let value = Value::List(Rc::clone(&list));
+        let mut call = |name: &str, arguments: &[Value]| call(&mut interpreter, name, arguments);
+        assert_eq!(call("push", &[value.clone(), "a".into()]), Ok(Value::Nil));
+        let value = [value];
+        assert_eq!(call("len", &value), Ok(Value::from(2.0)));
+        assert_eq!(call("pop", &value), Ok(Value::from("a")));
+        assert_eq!(call("pop", &value), Ok(Value::from(1.0)));
+        assert!(list.is_empty());
+        assert_eq!(
+            call("pop", &value),
+            Err(String::from("Cannot pop from an empty list."))
+        );
+        assert_eq!(
+            call("push", &[Value::Nil, Value::Nil]),
+            Err(String::from("Argument must be a list."))
+        );
+        assert_eq!(
+            call("len", &[Value::Nil]),
+            Err(String::from("Argument must be a string, list or map."))
+        );
+    }
+
+    #[test]
+    fn test_maps() {
+        let mut interpreter = Interpreter::new();
+        let map = Rc::new(LoxMap::new());
+        map.insert(Value::from("b"), Value::from(1.0)).unwrap();
+        map.insert(Value::from("a"), Value::Nil).unwrap();
+        let value = Value::Map(Rc::clone(&map));
+        let mut call = |name: &str, arguments: &[Value]| call(&mut interpreter, name, arguments);
+        let list = |value: Result<Value, String>| match value {
+            Ok(Value::List(list)) => list.elements(),
+            _ => panic!("not a list: {:?}", value),
+        };
+        let arguments = [value.clone()];
+        assert_eq!(list(call("keys", &arguments)), vec!["a".into(), "b".into()]);
+        assert_eq!(
+            list(call("values", &arguments)),
+            vec![Value::Nil, 1.0.into()]
+        );
+        assert_eq!(call("len", &arguments), Ok(Value::from(2.0)));
+        let with_key = |key: &str| [value.clone(), key.into()];
+        assert_eq!(call("has", &with_key("a")), Ok(Value::from(true)));
+        assert_eq!(call("remove", &with_key("b")), Ok(Value::from(1.0)));
+        assert_eq!(call("remove", &with_key("b")), Ok(Value::Nil));
+        assert_eq!(call("has", &with_key("b")), Ok(Value::from(false)));
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            call("keys", &[Value::Nil]),
+            Err(String::from("Argument must be a map."))
+        );
+    }
+
+    #[test]
+    fn test_selective() {
+        let stdlib = Stdlib {
+            strings: false,
+            math: true,
+            lists: false,
+            maps: false,
+        };
+        let mut interpreter = Interpreter::builder().stdlib(stdlib).build();
+        assert!(interpreter.call("abs", &[Value::from(1.0)]).is_ok());
+        assert_eq!(
+            interpreter
+                .call("len", &[Value::from("a")])
+                .unwrap_err()
+                .message,
+            "Undefined variable 'len'."
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::constants::Constants;
+use super::expr::Expr;
+use super::span::Span;
+use super::token::Token;
+
+pub enum Stmt {
+    Block(BlockStmt),
+    Break(BreakStmt),
+    Class(ClassStmt),
+    Continue(ContinueStmt),
+    Expression(ExpressionStmt),
+    Function(Rc<FunctionStmt>),
+    If(IfStmt),
+    Import(ImportStmt),
+    Print(PrintStmt),
+    Return(ReturnStmt),
+    Try(TryStmt),
+    Var(VarStmt),
+    While(WhileStmt),
+}
+
+impl Stmt {
+    pub fn make_block(statements: Vec<Stmt>) -> Stmt {
+        Stmt::Block(BlockStmt { statements })
+    }
+
+    pub fn make_break(keyword: Token) -> Stmt {
+        Stmt::Break(BreakStmt { keyword })
+    }
+
+    pub fn make_class(
+        name: Token,
+        superclass: Option<Expr>,
+        fields: Vec<Rc<VarStmt>>,
+        methods: Vec<Rc<FunctionStmt>>,
+        class_methods: Vec<Rc<FunctionStmt>>,
+    ) -> Stmt {
+        Stmt::Class(ClassStmt {
+            name,
+            superclass,
+            fields,
+            methods,
+            class_methods,
+        })
+    }
+
+    pub fn make_continue(keyword: Token) -> Stmt {
+        Stmt::Continue(ContinueStmt { keyword })
+    }
+
+    pub fn make_expression(expression: Expr) -> Stmt {
+        Stmt::Expression(ExpressionStmt { expression })
+    }
+
+    pub fn make_function(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Stmt {
+        Stmt::Function(Rc::new(FunctionStmt {
+            name,
+            params,
+            body,
+            getter: false,
+            constants: RefCell::default(),
+        }))
+    }
+
+    pub fn make_if(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Stmt {
+        Stmt::If(IfStmt {
+            condition,
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+        })
+    }
+
+    pub fn make_import(keyword: Token, path: Token, name: Token) -> Stmt {
+        Stmt::Import(ImportStmt {
+            keyword,
+            path,
+            name,
+        })
+    }
+
+    pub fn make_print(expression: Expr) -> Stmt {
+        Stmt::Print(PrintStmt { expression })
+    }
+
+    pub fn make_return(keyword: Token, value: Option<Expr>) -> Stmt {
+        Stmt::Return(ReturnStmt { keyword, value })
+    }
+
+    pub fn make_try(keyword: Token, body: Vec<Stmt>, name: Token, handler: Vec<Stmt>) -> Stmt {
+        Stmt::Try(TryStmt {
+            keyword,
+            body,
+            name,
+            handler,
+        })
+    }
+
+    pub fn make_var(name: Token, initializer: Option<Expr>, span: Span) -> Stmt {
+        Stmt::Var(VarStmt {
+            name,
+            initializer,
+            span,
+            constant: false,
+        })
+    }
+
+    pub fn make_const(name: Token, initializer: Expr, span: Span) -> Stmt {
+        Stmt::Var(VarStmt {
+            name,
+            initializer: Some(initializer),
+            span,
+            constant: true,
+        })
+    }
+
+    pub fn make_while(keyword: Token, condition: Expr, body: Stmt) -> Stmt {
+        Stmt::While(WhileStmt {
+            keyword,
+            condition,
+            body: Box::new(body),
+            increment: None,
+        })
+    }
+
+    // make_for makes the loop of a for statement, which is a while loop that
+    // evaluates increment after each iteration.
+    pub fn make_for(keyword: Token, condition: Expr, increment: Option<Expr>, body: Stmt) -> Stmt {
+        Stmt::While(WhileStmt {
+            keyword,
+            condition,
+            body: Box::new(body),
+            increment: increment.map(Box::new),
+        })
+    }
+}
+
+// Dropping a statement iteratively rather than recursively, so that
+// dropping deeply nested statements cannot overflow the stack. Expressions
+// drop iteratively by themselves.
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut stmt) = stack.pop() {
+            // stmt is dropped without children at the end of each iteration.
+            stmt.take_children(&mut stack);
+        }
+    }
+}
+
+impl Stmt {
+    // take_children moves the child statements of the statement to stack,
+    // leaving empty blocks in their place. The body of a function is only
+    // taken if this is the last reference to the function.
+    fn take_children(&mut self, stack: &mut Vec<Stmt>) {
+        fn take(stmt: &mut Box<Stmt>, stack: &mut Vec<Stmt>) {
+            stack.push(std::mem::replace(&mut **stmt, Stmt::make_block(Vec::new())));
+        }
+        fn take_body(function: &mut Rc<FunctionStmt>, stack: &mut Vec<Stmt>) {
+            if let Some(function) = Rc::get_mut(function) {
+                stack.append(&mut function.body);
+            }
+        }
+
+        match self {
+            Stmt::Block(stmt) => stack.append(&mut stmt.statements),
+            Stmt::Class(stmt) => {
+                for method in stmt.methods.iter_mut().chain(&mut stmt.class_methods) {
+                    take_body(method, stack);
+                }
+            }
+            Stmt::Function(function) => take_body(function, stack),
+            Stmt::If(stmt) => {
+                take(&mut stmt.then_branch, stack);
+                if let Some(else_branch) = &mut stmt.else_branch {
+                    take(else_branch, stack);
+                }
+            }
+            Stmt::Try(stmt) => {
+                stack.append(&mut stmt.body);
+                stack.append(&mut stmt.handler);
+            }
+            Stmt::While(stmt) => take(&mut stmt.body, stack),
+            Stmt::Break(_)
+            | Stmt::Continue(_)
+            | Stmt::Expression(_)
+            | Stmt::Import(_)
+            | Stmt::Print(_)
+            | Stmt::Return(_)
+            | Stmt::Var(_) => {}
+        }
+    }
+}
+
+pub struct BlockStmt {
+    pub statements: Vec<Stmt>,
+}
+
+pub struct BreakStmt {
+    pub keyword: Token,
+}
+
+pub struct ClassStmt {
+    pub name: Token,
+    // superclass is the Expr::Variable naming the superclass, if any.
+    pub superclass: Option<Expr>,
+    // fields are the declared fields of the class, initialized on each new
+    // instance. They are shared behind an Rc for the same reason as
+    // FunctionStmt.
+    pub fields: Vec<Rc<VarStmt>>,
+    pub methods: Vec<Rc<FunctionStmt>>,
+    // class_methods are the static methods of the class, declared with a
+    // leading "class" and called on the class itself, with "this" bound to
+    // the class.
+    pub class_methods: Vec<Rc<FunctionStmt>>,
+}
+
+pub struct ContinueStmt {
+    pub keyword: Token,
+}
+
+pub struct ExpressionStmt {
+    pub expression: Expr,
+}
+
+// FunctionStmt is shared behind an Rc, so that function values created at
+// runtime can refer to their declaration without copying the body.
+pub struct FunctionStmt {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    // getter is true for a method declared without a parameter list, which
+    // is called when its property is accessed, rather than returned.
+    pub getter: bool,
+    // constants is the constant table of the literals in body, as collected
+    // by the Resolver.
+    pub constants: RefCell<Constants>,
+}
+
+pub struct IfStmt {
+    pub condition: Expr,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+pub struct ImportStmt {
+    pub keyword: Token,
+    // path is the string token of the path of the imported file.
+    pub path: Token,
+    // name is the variable the module is bound to. Unless given with "as",
+    // it is the file name of path without its extension, with the span of
+    // path.
+    pub name: Token,
+}
+
+pub struct PrintStmt {
+    pub expression: Expr,
+}
+
+pub struct ReturnStmt {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+}
+
+// TryStmt runs body, and runs handler with name bound to the value thrown
+// if body throws. Runtime errors are caught as their message.
+pub struct TryStmt {
+    pub keyword: Token,
+    pub body: Vec<Stmt>,
+    pub name: Token,
+    pub handler: Vec<Stmt>,
+}
+
+pub struct VarStmt {
+    pub name: Token,
+    pub initializer: Option<Expr>,
+    // span is the span of the whole declaration, from "var" (or "const")
+    // to ";".
+    pub span: Span,
+    // constant is true for a const declaration, whose variable cannot be
+    // assigned to after it is initialized.
+    pub constant: bool,
+}
+
+pub struct WhileStmt {
+    // keyword is the "while" or "for" keyword starting the loop.
+    pub keyword: Token,
+    pub condition: Expr,
+    pub body: Box<Stmt>,
+    // increment is the increment clause of a for loop, evaluated after each
+    // iteration of body, including iterations ended by a continue.
+    pub increment: Option<Box<Expr>>,
+}
+
+pub trait StmtVisitor {
+    type Result;
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::Result;
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) -> Self::Result;
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> Self::Result;
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) -> Self::Result;
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Self::Result;
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> Self::Result;
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::Result;
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Self::Result;
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Self::Result;
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::Result;
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> Self::Result;
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Self::Result;
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::Result;
+}
+
+pub trait AcceptsStmtVisitor {
+    fn accept<V: StmtVisitor>(&self, visitor: &mut V) -> V::Result;
+}
+
+impl AcceptsStmtVisitor for Stmt {
+    fn accept<V: StmtVisitor>(&self, visitor: &mut V) -> V::Result {
+        use Stmt::*;
+        match *self {
+            Block(ref stmt) => visitor.visit_block_stmt(stmt),
+            Break(ref stmt) => visitor.visit_break_stmt(stmt),
+            Class(ref stmt) => visitor.visit_class_stmt(stmt),
+            Continue(ref stmt) => visitor.visit_continue_stmt(stmt),
+            Expression(ref stmt) => visitor.visit_expression_stmt(stmt),
+            Function(ref stmt) => visitor.visit_function_stmt(stmt),
+            If(ref stmt) => visitor.visit_if_stmt(stmt),
+            Import(ref stmt) => visitor.visit_import_stmt(stmt),
+            Print(ref stmt) => visitor.visit_print_stmt(stmt),
+            Return(ref stmt) => visitor.visit_return_stmt(stmt),
+            Try(ref stmt) => visitor.visit_try_stmt(stmt),
+            Var(ref stmt) => visitor.visit_var_stmt(stmt),
+            While(ref stmt) => visitor.visit_while_stmt(stmt),
+        }
+    }
+}
+
+impl AcceptsStmtVisitor for Rc<FunctionStmt> {
+    fn accept<V: StmtVisitor>(&self, visitor: &mut V) -> V::Result {
+        visitor.visit_function_stmt(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{Position, Span};
+    use crate::token::{Lexeme, Literal, TokenType};
+
+    #[test]
+    fn test_drop_deep_statements() {
+        let keyword = Token {
+            token_type: TokenType::While,
+            lexeme: Lexeme::from("while"),
+            symbol: None,
+            span: Span::new(Position::new(1, 1, 0), Position::new(1, 6, 5)),
+            literal: None,
+            error: false,
+        };
+        let mut stmt = Stmt::make_block(Vec::new());
+        for i in 0..1_000_000 {
+            let condition = Expr::make_literal(Literal::Bool(true));
+            stmt = if i % 2 == 0 {
+                Stmt::make_block(vec![stmt])
+            } else {
+                Stmt::make_while(keyword.clone(), condition, stmt)
+            };
+        }
+        drop(stmt);
+    }
+}
@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str;
+
+// INLINE_CAPACITY is the length in bytes of the longest string stored
+// inline, without allocating.
+const INLINE_CAPACITY: usize = 22;
+
+// LoxString is the string representation of Value::String. Lox strings are
+// immutable, which allows LoxString to avoid most allocations and copies:
+//
+// - Short strings are stored inline.
+// - Strings created from a string literal share the literal's buffer.
+// - Longer strings are a prefix of an append-only buffer. Concatenating
+//   onto a string that covers all of its buffer appends to the buffer in
+//   place, so building a string by repeated concatenation in a loop takes
+//   amortized linear rather than quadratic time.
+#[derive(Clone)]
+pub struct LoxString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    // Inline holds the first len bytes of bytes.
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    // Literal is a string shared with the literal it was created from.
+    Literal(Rc<str>),
+    // Buffer is the first len bytes of buffer. Bytes are only ever appended
+    // to the buffer, so the prefix never changes.
+    Buffer {
+        buffer: Rc<RefCell<String>>,
+        len: usize,
+    },
+}
+
+impl LoxString {
+    // from_literal creates a LoxString for the string literal s, sharing the
+    // literal's buffer.
+    pub fn from_literal(s: Rc<str>) -> Self {
+        match Self::inline(&s) {
+            Some(inline) => inline,
+            None => LoxString(Repr::Literal(s)),
+        }
+    }
+
+    // inline returns s as an inline LoxString, or None if s is too long to
+    // be stored inline.
+    fn inline(s: &str) -> Option<Self> {
+        if s.len() > INLINE_CAPACITY {
+            return None;
+        }
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        Some(LoxString(Repr::Inline {
+            len: s.len() as u8,
+            bytes,
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        match self.0 {
+            Repr::Inline { len, .. } => len as usize,
+            Repr::Literal(ref s) => s.len(),
+            Repr::Buffer { len, .. } => len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // with_str calls f with the string as a str, returning the result of f.
+    pub fn with_str<R, F: FnOnce(&str) -> R>(&self, f: F) -> R {
+        match self.0 {
+            Repr::Inline { len, ref bytes } => {
+                f(str::from_utf8(&bytes[..len as usize]).expect("inline string is not utf-8"))
+            }
+            Repr::Literal(ref s) => f(s),
+            Repr::Buffer { ref buffer, len } => f(&buffer.borrow()[..len]),
+        }
+    }
+
+    // concat returns the concatenation of self and other.
+    pub fn concat(&self, other: &LoxString) -> LoxString {
+        let len = self.len() + other.len();
+        if len <= INLINE_CAPACITY {
+            let mut s = String::with_capacity(len);
+            self.with_str(|l| s.push_str(l));
+            other.with_str(|r| s.push_str(r));
+            return LoxString::from(s.as_str());
+        }
+
+        if let Repr::Buffer {
+            ref buffer,
+            len: self_len,
+        } = self.0
+        {
+            // Only append in place if no other string has already extended
+            // the buffer past self, and other is not a prefix of the same
+            // buffer (which would be borrowed while appending).
+            let is_whole_buffer = buffer.borrow().len() == self_len;
+            let is_same_buffer = match other.0 {
+                Repr::Buffer {
+                    buffer: ref other_buffer,
+                    ..
+                } => Rc::ptr_eq(buffer, other_buffer),
+                _ => false,
+            };
+            if is_whole_buffer && !is_same_buffer {
+                other.with_str(|r| buffer.borrow_mut().push_str(r));
+                return LoxString(Repr::Buffer {
+                    buffer: Rc::clone(buffer),
+                    len,
+                });
+            }
+        }
+
+        let mut s = String::with_capacity(len);
+        self.with_str(|l| s.push_str(l));
+        other.with_str(|r| s.push_str(r));
+        LoxString(Repr::Buffer {
+            buffer: Rc::new(RefCell::new(s)),
+            len,
+        })
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(s: &str) -> Self {
+        match Self::inline(s) {
+            Some(inline) => inline,
+            None => LoxString(Repr::Buffer {
+                buffer: Rc::new(RefCell::new(s.to_owned())),
+                len: s.len(),
+            }),
+        }
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.with_str(|l| other.with_str(|r| l == r))
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.with_str(|s| f.write_str(s))
+    }
+}
+
+impl fmt::Debug for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.with_str(|s| write!(f, "{:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_inline(s: &LoxString) -> bool {
+        matches!(s.0, Repr::Inline { .. })
+    }
+
+    #[test]
+    fn test_short_strings_are_inline() {
+        let s = LoxString::from("abc");
+        assert!(is_inline(&s));
+        assert_eq!(s.to_string(), "abc");
+        let s = LoxString::from_literal(Rc::from("abc"));
+        assert!(is_inline(&s));
+        let s = LoxString::from("a".repeat(INLINE_CAPACITY + 1).as_str());
+        assert!(!is_inline(&s));
+    }
+
+    #[test]
+    fn test_literal_shares_buffer() {
+        let literal: Rc<str> = Rc::from("a".repeat(30).as_str());
+        let s = LoxString::from_literal(Rc::clone(&literal));
+        assert_eq!(Rc::strong_count(&literal), 2);
+        assert_eq!(s.len(), 30);
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = LoxString::from("ab");
+        let b = LoxString::from("cd");
+        assert_eq!(a.concat(&b), LoxString::from("abcd"));
+        let long = "x".repeat(30);
+        let c = a.concat(&LoxString::from(long.as_str()));
+        assert_eq!(c.to_string(), format!("ab{}", long));
+        assert_eq!(c.concat(&c).to_string(), format!("ab{}ab{}", long, long));
+    }
+
+    #[test]
+    fn test_concat_appends_in_place() {
+        let mut s = LoxString::from("a".repeat(30).as_str());
+        for _ in 0..100 {
+            s = s.concat(&LoxString::from("b"));
+        }
+        assert_eq!(s.len(), 130);
+        match s.0 {
+            Repr::Buffer { ref buffer, .. } => assert_eq!(Rc::strong_count(buffer), 1),
+            _ => panic!("expected a buffer"),
+        }
+    }
+
+    #[test]
+    fn test_concat_does_not_change_prefixes() {
+        let base = LoxString::from("a".repeat(30).as_str());
+        let b = base.concat(&LoxString::from("b"));
+        // base is no longer the whole buffer, so this must copy.
+        let c = base.concat(&LoxString::from("c"));
+        assert_eq!(base.to_string(), "a".repeat(30));
+        assert_eq!(b.to_string(), format!("{}b", "a".repeat(30)));
+        assert_eq!(c.to_string(), format!("{}c", "a".repeat(30)));
+    }
+
+    #[test]
+    fn test_eq_across_representations() {
+        let long = "y".repeat(40);
+        let literal = LoxString::from_literal(Rc::from(long.as_str()));
+        let buffer = LoxString::from("y".repeat(20).as_str())
+            .concat(&LoxString::from("y".repeat(20).as_str()));
+        assert_eq!(literal, buffer);
+        assert_ne!(literal, LoxString::from("y"));
+    }
+}
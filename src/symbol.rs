@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Symbol is an interned string, identifying the string within the Interner
+// that produced it. Comparing two symbols from the same Interner is
+// equivalent to comparing their strings.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// Interner maps strings to Symbols, storing each distinct string once. The
+// stored strings are shared, so that e.g. every string literal with the same
+// value can share one allocation.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    // intern returns the Symbol for s, adding s to the interner if it has
+    // not been interned before.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(s) {
+            return symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: Rc<str> = Rc::from(s);
+        self.strings.push(Rc::clone(&s));
+        self.symbols.insert(s, symbol);
+        symbol
+    }
+
+    // lookup returns the Symbol for s if s has been interned.
+    pub fn lookup(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    // resolve returns the string for symbol. symbol must have been produced
+    // by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+
+    // shared returns the string for symbol, sharing the interner's copy of
+    // it. symbol must have been produced by this interner.
+    pub fn shared(&self, symbol: Symbol) -> Rc<str> {
+        Rc::clone(&self.strings[symbol.0 as usize])
+    }
+
+    // len returns the number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_same_string() {
+        let mut interner = Interner::new();
+        let a = interner.intern("abc");
+        let b = interner.intern("def");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("abc"), a);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut interner = Interner::new();
+        let a = interner.intern("abc");
+        let b = interner.intern("def");
+        assert_eq!(interner.resolve(a), "abc");
+        assert_eq!(interner.resolve(b), "def");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut interner = Interner::new();
+        assert_eq!(interner.lookup("abc"), None);
+        let a = interner.intern("abc");
+        assert_eq!(interner.lookup("abc"), Some(a));
+    }
+
+    #[test]
+    fn test_shared() {
+        let mut interner = Interner::new();
+        let a = interner.intern("abc");
+        let shared = interner.shared(a);
+        assert_eq!(&*shared, "abc");
+        let b = interner.intern("abc");
+        assert!(Rc::ptr_eq(&shared, &interner.shared(b)));
+    }
+}
@@ -1,11 +1,119 @@
 use std::fmt;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+// Span and Position are re-exported, as the location of tokens, and of the
+// nodes built from them.
+pub use super::span::{Position, Span};
+use super::symbol::Symbol;
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
-    pub line: u64,
+    pub lexeme: Lexeme,
+    // symbol is the interned lexeme of Identifier tokens, None for all
+    // other tokens.
+    pub symbol: Option<Symbol>,
+    pub span: Span,
     pub literal: Option<Literal>,
+    // error is true if the token was recovered from a scan error, such as
+    // the String token of an unterminated string. The error itself is
+    // reported by the Scanner.
+    pub error: bool,
+}
+
+impl Token {
+    // expanded returns a token of token_type, lexeme and literal standing in
+    // for origin, as produced by a TokenExpander. The token has the span of
+    // origin, so that diagnostics about it point at origin in the source.
+    pub fn expanded(
+        token_type: TokenType,
+        lexeme: &str,
+        literal: Option<Literal>,
+        origin: &Token,
+    ) -> Token {
+        Token {
+            token_type,
+            lexeme: Lexeme::from(lexeme),
+            symbol: None,
+            span: origin.span,
+            literal,
+            error: false,
+        }
+    }
+}
+
+// Lexeme is the text of a token, as written in the source. Lexemes share the
+// source they were scanned from, so that scanning a large source does not
+// copy the text of each token into a string of its own.
+#[derive(Clone)]
+pub struct Lexeme {
+    source: Rc<str>,
+    range: Range<usize>,
+}
+
+impl Lexeme {
+    // new creates the lexeme for range of source. range must start and end
+    // on character boundaries of source.
+    pub fn new(source: Rc<str>, range: Range<usize>) -> Self {
+        assert!(source.get(range.clone()).is_some(), "lexeme out of source");
+        Lexeme { source, range }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source[self.range.clone()]
+    }
+}
+
+impl Deref for Lexeme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Lexeme {
+    fn from(s: &str) -> Self {
+        Lexeme::new(Rc::from(s), 0..s.len())
+    }
+}
+
+impl From<String> for Lexeme {
+    fn from(s: String) -> Self {
+        let len = s.len();
+        Lexeme::new(Rc::from(s), 0..len)
+    }
+}
+
+impl PartialEq for Lexeme {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<str> for Lexeme {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -15,6 +123,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -22,6 +133,7 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
 
     // One or two character tokens.
     Bang,
@@ -32,37 +144,60 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    StarStar,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
 
     // Literals.
     Identifier,
     String,
     Number,
+    // Interpolation is the part of an interpolated string up to and
+    // including a "${", the interpolated expression following it. The part
+    // after the closing "}" is scanned as another Interpolation, or as a
+    // String ending the interpolated string.
+    Interpolation,
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Const,
+    Continue,
     Else,
     False,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
     Eof,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
-    String(String),
+    // String is shared, so that each evaluation of a string literal does
+    // not copy the string.
+    String(Rc<str>),
     Number(f64),
+    Bool(bool),
+    Nil,
 }
 
 impl fmt::Display for Literal {
@@ -70,6 +205,26 @@ impl fmt::Display for Literal {
         match *self {
             Literal::String(ref s) => write!(f, "{}", s),
             Literal::Number(n) => write!(f, "{}", n),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Nil => write!(f, "nil"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lexeme() {
+        let source: Rc<str> = Rc::from("var abc = 1;");
+        let lexeme = Lexeme::new(Rc::clone(&source), 4..7);
+        assert_eq!(lexeme.as_str(), "abc");
+        assert_eq!(lexeme, "abc");
+        assert_eq!(lexeme, Lexeme::from(String::from("abc")));
+        assert_eq!(lexeme.len(), 3);
+        assert_eq!(format!("{} {:?}", lexeme, lexeme), "abc \"abc\"");
+        // The lexeme shares the source rather than copying it.
+        assert_eq!(Rc::strong_count(&source), 2);
+    }
+}
@@ -0,0 +1,471 @@
+use std::fmt;
+
+use super::value::Value;
+
+// Event is a change of the state of a running program, as recorded in a
+// Trace. Values are recorded as text, as shown by describe.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    // Define is the declaration of a variable, function or class, or the
+    // binding of a parameter, with its initial value.
+    Define {
+        line: u64,
+        global: bool,
+        name: String,
+        value: String,
+    },
+    // Assign is an assignment to a declared variable.
+    Assign {
+        line: u64,
+        global: bool,
+        name: String,
+        value: String,
+    },
+    // SetField is an assignment to a field of object.
+    SetField {
+        line: u64,
+        object: String,
+        name: String,
+        value: String,
+    },
+    // Call is a call of function with arguments.
+    Call {
+        line: u64,
+        function: String,
+        arguments: Vec<String>,
+    },
+    // Return is the return of the innermost call in progress, with the
+    // returned value.
+    Return {
+        line: u64,
+        function: String,
+        value: String,
+    },
+    // Unwind is the end of the innermost call in progress by an error,
+    // such as a thrown value, raised in the call and not caught by it.
+    Unwind {
+        line: u64,
+        function: String,
+    },
+}
+
+impl Event {
+    // line returns the source line of the code causing the event.
+    pub fn line(&self) -> u64 {
+        match *self {
+            Event::Define { line, .. }
+            | Event::Assign { line, .. }
+            | Event::SetField { line, .. }
+            | Event::Call { line, .. }
+            | Event::Return { line, .. }
+            | Event::Unwind { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Define { name, value, .. } => write!(f, "define {} = {}", name, value),
+            Event::Assign { name, value, .. } => write!(f, "{} = {}", name, value),
+            Event::SetField {
+                object,
+                name,
+                value,
+                ..
+            } => write!(f, "{}.{} = {}", object, name, value),
+            Event::Call {
+                function,
+                arguments,
+                ..
+            } => write!(f, "call {}({})", function, arguments.join(", ")),
+            Event::Return {
+                function, value, ..
+            } => write!(f, "{} returned {}", function, value),
+            Event::Unwind { function, .. } => write!(f, "{} unwound", function),
+        }
+    }
+}
+
+// describe returns the text a value is recorded as in a Trace. Unlike the
+// printed form of a value, strings are quoted.
+pub fn describe(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        _ => value.to_string(),
+    }
+}
+
+// TraceFrame is a frame of the call stack of a traced program, as
+// reconstructed from a Trace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceFrame {
+    // function is the name of the function called, or "script" for the top
+    // level of the program.
+    pub function: String,
+    // variables are the local variables written in the frame, in the order
+    // they were first defined, with their last value. Variables of blocks
+    // that have ended are still included.
+    pub variables: Vec<(String, String)>,
+}
+
+impl TraceFrame {
+    fn new(function: &str) -> Self {
+        TraceFrame {
+            function: function.to_owned(),
+            variables: Vec::new(),
+        }
+    }
+}
+
+// write sets the variable name to value in variables.
+fn write(variables: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match variables.iter_mut().find(|(variable, _)| variable == name) {
+        Some((_, slot)) => *slot = value.to_owned(),
+        None => variables.push((name.to_owned(), value.to_owned())),
+    }
+}
+
+// State is the state of a traced program after some event of its Trace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct State {
+    pub globals: Vec<(String, String)>,
+    // frames is the call stack, outermost frame first. The first frame is
+    // always the top level of the program.
+    pub frames: Vec<TraceFrame>,
+}
+
+// Trace is the record of the state changes of a program run, in the order
+// they happened. Any earlier state of the program can be reconstructed from
+// the trace, which allows stepping backwards through a run.
+//
+// A trace is written as text, one event per line, with the fields of the
+// event separated by tabs, e.g.:
+//
+//   define	1	global	a	1
+//   call	2	f	1	"b"
+//   assign	3	local	x	2
+//   set	4	A instance	n	2
+//   return	5	f	nil
+//   unwind	6	g
+//
+// Tabs, line breaks and backslashes in the fields are escaped with a
+// backslash.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Trace {
+    events: Vec<Event>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace::default()
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    // state returns the state of the program after the first steps events.
+    pub fn state(&self, steps: usize) -> State {
+        let mut state = State {
+            globals: Vec::new(),
+            frames: vec![TraceFrame::new("script")],
+        };
+        for event in self.events.iter().take(steps) {
+            match event {
+                Event::Define {
+                    global,
+                    name,
+                    value,
+                    ..
+                }
+                | Event::Assign {
+                    global,
+                    name,
+                    value,
+                    ..
+                } => {
+                    let variables = match state.frames.last_mut() {
+                        Some(frame) if !global => &mut frame.variables,
+                        _ => &mut state.globals,
+                    };
+                    write(variables, name, value);
+                }
+                Event::Call { function, .. } => state.frames.push(TraceFrame::new(function)),
+                Event::Return { .. } | Event::Unwind { .. } => {
+                    if state.frames.len() > 1 {
+                        state.frames.pop();
+                    }
+                }
+                Event::SetField { .. } => {}
+            }
+        }
+        state
+    }
+
+    // parse parses a trace from its text form.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut trace = Trace::new();
+        for (i, line) in text.lines().enumerate() {
+            let invalid = || format!("Invalid trace line {}.", i + 1);
+            let fields: Vec<String> = line.split('\t').map(unescape).collect();
+            if fields.len() < 3 {
+                return Err(invalid());
+            }
+            let line: u64 = fields[1].parse().map_err(|_| invalid())?;
+            let global = |scope: &str| match scope {
+                "global" => Ok(true),
+                "local" => Ok(false),
+                _ => Err(invalid()),
+            };
+            let event = match (fields[0].as_str(), fields.len()) {
+                ("define", 5) => Event::Define {
+                    line,
+                    global: global(&fields[2])?,
+                    name: fields[3].clone(),
+                    value: fields[4].clone(),
+                },
+                ("assign", 5) => Event::Assign {
+                    line,
+                    global: global(&fields[2])?,
+                    name: fields[3].clone(),
+                    value: fields[4].clone(),
+                },
+                ("set", 5) => Event::SetField {
+                    line,
+                    object: fields[2].clone(),
+                    name: fields[3].clone(),
+                    value: fields[4].clone(),
+                },
+                ("call", _) => Event::Call {
+                    line,
+                    function: fields[2].clone(),
+                    arguments: fields[3..].to_vec(),
+                },
+                ("return", 4) => Event::Return {
+                    line,
+                    function: fields[2].clone(),
+                    value: fields[3].clone(),
+                },
+                ("unwind", 3) => Event::Unwind {
+                    line,
+                    function: fields[2].clone(),
+                },
+                _ => return Err(invalid()),
+            };
+            trace.push(event);
+        }
+        Ok(trace)
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn scope(global: bool) -> &'static str {
+            if global {
+                "global"
+            } else {
+                "local"
+            }
+        }
+
+        for event in &self.events {
+            let mut fields = match event {
+                Event::Define {
+                    line,
+                    global,
+                    name,
+                    value,
+                }
+                | Event::Assign {
+                    line,
+                    global,
+                    name,
+                    value,
+                } => {
+                    let kind = match event {
+                        Event::Define { .. } => "define",
+                        _ => "assign",
+                    };
+                    vec![
+                        kind.to_owned(),
+                        line.to_string(),
+                        scope(*global).to_owned(),
+                        name.clone(),
+                        value.clone(),
+                    ]
+                }
+                Event::SetField {
+                    line,
+                    object,
+                    name,
+                    value,
+                } => vec![
+                    String::from("set"),
+                    line.to_string(),
+                    object.clone(),
+                    name.clone(),
+                    value.clone(),
+                ],
+                Event::Call {
+                    line,
+                    function,
+                    arguments,
+                } => {
+                    let mut fields = vec![String::from("call"), line.to_string(), function.clone()];
+                    fields.extend(arguments.iter().cloned());
+                    fields
+                }
+                Event::Return {
+                    line,
+                    function,
+                    value,
+                } => vec![
+                    String::from("return"),
+                    line.to_string(),
+                    function.clone(),
+                    value.clone(),
+                ],
+                Event::Unwind { line, function } => {
+                    vec![String::from("unwind"), line.to_string(), function.clone()]
+                }
+            };
+            for field in &mut fields {
+                *field = escape(field);
+            }
+            writeln!(f, "{}", fields.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+// escape escapes the tabs, line breaks and backslashes of field.
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for ch in field.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// unescape reverses escape.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Trace {
+        let mut trace = Trace::new();
+        trace.push(Event::Define {
+            line: 1,
+            global: true,
+            name: String::from("a"),
+            value: String::from("\"x\ty\""),
+        });
+        trace.push(Event::Call {
+            line: 2,
+            function: String::from("f"),
+            arguments: vec![String::from("1"), String::from("nil")],
+        });
+        trace.push(Event::Define {
+            line: 1,
+            global: false,
+            name: String::from("b"),
+            value: String::from("1"),
+        });
+        trace.push(Event::Assign {
+            line: 3,
+            global: true,
+            name: String::from("a"),
+            value: String::from("2"),
+        });
+        trace.push(Event::SetField {
+            line: 3,
+            object: String::from("A instance"),
+            name: String::from("n"),
+            value: String::from("3"),
+        });
+        trace.push(Event::Return {
+            line: 2,
+            function: String::from("f"),
+            value: String::from("nil"),
+        });
+        trace.push(Event::Call {
+            line: 4,
+            function: String::from("g"),
+            arguments: Vec::new(),
+        });
+        trace.push(Event::Unwind {
+            line: 4,
+            function: String::from("g"),
+        });
+        trace
+    }
+
+    #[test]
+    fn test_trace_text() {
+        let trace = sample();
+        let text = trace.to_string();
+        assert_eq!(text.lines().next(), Some("define\t1\tglobal\ta\t\"x\\ty\""));
+        assert_eq!(Trace::parse(&text), Ok(trace));
+        assert_eq!(
+            Trace::parse("define\t1\ta\t1\n"),
+            Err(String::from("Invalid trace line 1."))
+        );
+    }
+
+    #[test]
+    fn test_trace_state() {
+        let trace = sample();
+        let state = trace.state(4);
+        assert_eq!(state.globals, vec![(String::from("a"), String::from("2"))]);
+        assert_eq!(
+            state.frames,
+            vec![
+                TraceFrame::new("script"),
+                TraceFrame {
+                    function: String::from("f"),
+                    variables: vec![(String::from("b"), String::from("1"))],
+                },
+            ]
+        );
+        // Stepping back undoes the events.
+        let state = trace.state(1);
+        assert_eq!(
+            state.globals,
+            vec![(String::from("a"), String::from("\"x\ty\""))]
+        );
+        assert_eq!(state.frames.len(), 1);
+        assert_eq!(trace.state(6).frames.len(), 1);
+        assert_eq!(trace.state(7).frames.len(), 2);
+        assert_eq!(trace.state(8).frames.len(), 1);
+    }
+}
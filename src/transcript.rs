@@ -0,0 +1,175 @@
+use std::fmt;
+
+// Entry is a single entry of a Transcript.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Entry {
+    // Input is a line of input entered in the REPL.
+    Input(String),
+    // Output is text printed by the program, made up of whole lines.
+    Output(String),
+    // Diagnostic is an error or warning reported for the input.
+    Diagnostic(String),
+}
+
+// Transcript is a record of a REPL session: the lines of input, interleaved
+// with the output and diagnostics they produced.
+//
+// A transcript is written as text, one line per line of input, output or
+// diagnostic, each prefixed by its kind:
+//
+//   > print 1 + 2;
+//   | 3
+//   > print -"a";
+//   ! Operand must be a number.
+//   ! [line 1]
+//
+// Lines starting with '#' and empty lines are comments. Since the text can
+// be parsed back into a Transcript, a recorded session can be replayed and
+// its output compared with the recording.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Transcript {
+    entries: Vec<Entry>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Transcript::default()
+    }
+
+    // parse parses a transcript from its text form.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut transcript = Transcript::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut chars = line.chars();
+            let kind = chars.next().expect("empty transcript line");
+            let rest = chars.as_str();
+            let content = match rest.strip_prefix(' ') {
+                Some(content) => content,
+                None if rest.is_empty() => rest,
+                None => return Err(format!("Invalid transcript line {}.", i + 1)),
+            };
+            match kind {
+                '>' => transcript.push_input(content),
+                '|' => transcript.push_output(&format!("{}\n", content)),
+                '!' => transcript.push_diagnostic(content),
+                _ => return Err(format!("Invalid transcript line {}.", i + 1)),
+            }
+        }
+        Ok(transcript)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    // inputs returns the lines of input of the transcript, in order.
+    pub fn inputs(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Input(line) => Some(line.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn push_input(&mut self, line: &str) {
+        self.entries.push(Entry::Input(line.to_owned()));
+    }
+
+    // push_output records text printed by the program. Consecutive output is
+    // merged into a single entry.
+    pub fn push_output(&mut self, text: &str) {
+        match self.entries.last_mut() {
+            Some(Entry::Output(output)) => output.push_str(text),
+            _ => self.entries.push(Entry::Output(text.to_owned())),
+        }
+    }
+
+    // push_diagnostic records a diagnostic, which may span multiple lines.
+    pub fn push_diagnostic(&mut self, diagnostic: &str) {
+        match self.entries.last_mut() {
+            Some(Entry::Diagnostic(existing)) => {
+                existing.push('\n');
+                existing.push_str(diagnostic);
+            }
+            _ => self.entries.push(Entry::Diagnostic(diagnostic.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_lines(f: &mut fmt::Formatter, kind: &str, text: &str) -> fmt::Result {
+            for line in text.lines() {
+                if line.is_empty() {
+                    writeln!(f, "{}", kind)?;
+                } else {
+                    writeln!(f, "{} {}", kind, line)?;
+                }
+            }
+            Ok(())
+        }
+
+        for entry in &self.entries {
+            match entry {
+                Entry::Input(line) => write_lines(f, ">", line)?,
+                Entry::Output(text) => write_lines(f, "|", text)?,
+                Entry::Diagnostic(text) => write_lines(f, "!", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let mut transcript = Transcript::new();
+        transcript.push_input("print 1; print 2;");
+        transcript.push_output("1\n");
+        transcript.push_output("\n2\n");
+        transcript.push_input("print -\"a\";");
+        transcript.push_diagnostic("Operand must be a number.\n[line 1]");
+        assert_eq!(
+            transcript.to_string(),
+            "> print 1; print 2;\n| 1\n|\n| 2\n> print -\"a\";\n! Operand must be a number.\n! [line 1]\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let text = "# A comment.\n> var a = 1;\n\n> print a;\n| 1\n|\n! warning\n";
+        let transcript = Transcript::parse(text).unwrap();
+        assert_eq!(
+            transcript.entries(),
+            &[
+                Entry::Input("var a = 1;".to_owned()),
+                Entry::Input("print a;".to_owned()),
+                Entry::Output("1\n\n".to_owned()),
+                Entry::Diagnostic("warning".to_owned()),
+            ]
+        );
+        assert_eq!(
+            transcript.inputs().collect::<Vec<_>>(),
+            vec!["var a = 1;", "print a;"]
+        );
+        assert_eq!(Transcript::parse(&transcript.to_string()), Ok(transcript));
+    }
+
+    #[test]
+    fn test_parse_invalid_line() {
+        assert_eq!(
+            Transcript::parse("> a\nb\n"),
+            Err("Invalid transcript line 2.".to_owned())
+        );
+        assert!(Transcript::parse(">a").is_err());
+        assert_eq!(
+            Transcript::parse("é"),
+            Err("Invalid transcript line 1.".to_owned())
+        );
+    }
+}
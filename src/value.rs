@@ -0,0 +1,122 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::interpreter::{
+    LoxClass, LoxFunction, LoxInstance, LoxList, LoxMap, LoxModule, NativeFunction,
+};
+use super::string::LoxString;
+use super::token::Literal;
+
+// Value is a Lox value at runtime.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(LoxString),
+    Function(Rc<LoxFunction>),
+    NativeFn(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<LoxInstance>),
+    List(Rc<LoxList>),
+    Map(Rc<LoxMap>),
+    Module(Rc<LoxModule>),
+}
+
+impl Value {
+    // is_truthy returns the truthiness of the value. As in Ruby, nil and
+    // false are falsey, and every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            Value::Nil => false,
+            Value::Bool(b) => b,
+            _ => true,
+        }
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(literal: Literal) -> Self {
+        match literal {
+            Literal::Nil => Value::Nil,
+            Literal::Bool(b) => Value::Bool(b),
+            Literal::Number(n) => Value::Number(n),
+            Literal::String(s) => Value::String(LoxString::from_literal(s)),
+        }
+    }
+}
+
+// Values can be converted from the corresponding Rust types, e.g. to pass
+// arguments to Interpreter::call.
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(LoxString::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::from(s.as_str())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(ref s) => write!(f, "{}", s),
+            Value::Function(ref function) => write!(f, "<fn {}>", function.name()),
+            Value::NativeFn(_) => write!(f, "<native fn>"),
+            Value::Class(ref class) => write!(f, "{}", class.name()),
+            Value::Instance(ref instance) => write!(f, "{} instance", instance.class().name()),
+            Value::List(ref list) => write!(f, "{}", list),
+            Value::Map(ref map) => write!(f, "{}", map),
+            Value::Module(ref module) => write!(f, "<module {}>", module.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(!Value::Nil.is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String(LoxString::from("")).is_truthy());
+    }
+
+    #[test]
+    fn test_from() {
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(1.5), Value::Number(1.5));
+        assert_eq!(Value::from("a"), Value::String(LoxString::from("a")));
+        assert_eq!(Value::from(String::from("a")), Value::from("a"));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(format!("{}", Value::Nil), "nil");
+        assert_eq!(format!("{}", Value::Bool(true)), "true");
+        assert_eq!(format!("{}", Value::Number(1.0)), "1");
+        assert_eq!(format!("{}", Value::Number(1.5)), "1.5");
+        assert_eq!(format!("{}", Value::String(LoxString::from("a"))), "a");
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Outcome is the observable result of running a script with an
+// implementation: what it printed to stdout and how it exited.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: String,
+    exit_code: Option<i32>,
+}
+
+// run runs the "xtest" command. args are the arguments following "xtest":
+// a "--reference=<path>" option naming the reference implementation, followed
+// by one or more corpus files or directories. Every .lox script in the corpus
+// is run with both loxrust and the reference, and any difference in output
+// or exit code is reported. run returns true if all scripts matched.
+pub fn run(args: &[String]) -> Result<bool, String> {
+    let mut reference = None;
+    let mut corpus = Vec::new();
+    for arg in args {
+        if let Some(path) = arg.strip_prefix("--reference=") {
+            reference = Some(PathBuf::from(path));
+        } else {
+            corpus.push(PathBuf::from(arg));
+        }
+    }
+    let reference = reference.ok_or("Missing --reference=<path>.")?;
+    if corpus.is_empty() {
+        return Err(String::from("Missing corpus path."));
+    }
+
+    let loxrust = std::env::current_exe().map_err(|err| err.to_string())?;
+    let mut scripts = Vec::new();
+    for path in &corpus {
+        collect_scripts(path, &mut scripts)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    scripts.sort();
+
+    let mut failed = 0;
+    for script in &scripts {
+        let actual = run_script(&loxrust, script)?;
+        let expected = run_script(&reference, script)?;
+        if actual == expected {
+            println!("PASS {}", script.display());
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}", script.display());
+        if actual.exit_code != expected.exit_code {
+            println!(
+                "  exit code: expected {:?}, got {:?}",
+                expected.exit_code, actual.exit_code
+            );
+        }
+        let difference = first_difference(&expected.stdout, &actual.stdout);
+        if let Some((line, expected, actual)) = difference {
+            println!("  line {}: expected {:?}, got {:?}", line, expected, actual);
+        }
+    }
+    println!("{} passed, {} failed", scripts.len() - failed, failed);
+    Ok(failed == 0)
+}
+
+// collect_scripts appends path to scripts if it is a file, or every .lox
+// file found (recursively) below path if it is a directory.
+fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        scripts.push(path.to_owned());
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_scripts(&path, scripts)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            scripts.push(path);
+        }
+    }
+    Ok(())
+}
+
+// run_script runs program with script as its only argument.
+fn run_script(program: &Path, script: &Path) -> Result<Outcome, String> {
+    let output = Command::new(program)
+        .arg(script)
+        .output()
+        .map_err(|err| format!("{}: {}", program.display(), err))?;
+    Ok(Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        exit_code: output.status.code(),
+    })
+}
+
+// first_difference returns the first line (1-based) at which expected and
+// actual differ, together with the differing lines. A missing line is
+// returned as an empty string.
+fn first_difference<'a>(expected: &'a str, actual: &'a str) -> Option<(usize, &'a str, &'a str)> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (e, a) if e == a => line += 1,
+            (e, a) => return Some((line, e.unwrap_or(""), a.unwrap_or(""))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_difference_equal() {
+        assert_eq!(first_difference("1\n2\n", "1\n2\n"), None);
+    }
+
+    #[test]
+    fn test_first_difference_changed_line() {
+        assert_eq!(
+            first_difference("1\n2\n3\n", "1\n4\n3\n"),
+            Some((2, "2", "4"))
+        );
+    }
+
+    #[test]
+    fn test_first_difference_missing_line() {
+        assert_eq!(first_difference("1\n2\n", "1\n"), Some((2, "2", "")));
+        assert_eq!(first_difference("1\n", "1\n2\n"), Some((2, "", "2")));
+    }
+
+    #[test]
+    fn test_run_requires_reference() {
+        let args = vec![String::from("tests/")];
+        assert!(run(&args).is_err());
+    }
+}